@@ -1,19 +1,31 @@
 use std::{path::PathBuf, sync::Arc};
 
-use druid::{im::HashSet, Selector};
+use druid::{
+    im::{HashSet, Vector},
+    Selector, Size,
+};
 
 use crate::{
     app_data::{ImageFolder, Thumbnail},
-    folder_view::FolderView,
+    folder_view::{CopyDetail, FolderView},
+    metadata::ImageProbe,
+    palette::ImagePalette,
 };
 
-pub const SELECTED_FOLDER: Selector<usize> =
-    Selector::new("app.selected-folder");
+// carries the folder's id rather than its position, which can change
+// between the folder being shown and clicked
+pub const SELECTED_FOLDER: Selector<u64> = Selector::new("app.selected-folder");
 
-pub const FINISHED_READING_ALL_PATHS: Selector<HashSet<Arc<PathBuf>>> =
-    Selector::new("app.finished-reading-all-paths");
+// the scan results carry the generation of the scan that found them, so
+// ones from a scan that was started over can be told apart. The scan's roots
+// come along with every folder found so far
+pub const FINISHED_READING_ALL_PATHS: Selector<(
+    u64,
+    Vec<PathBuf>,
+    HashSet<Arc<PathBuf>>,
+)> = Selector::new("app.finished-reading-all-paths");
 
-pub const FINISHED_READING_FOLDER_IMAGE: Selector<ImageFolder> =
+pub const FINISHED_READING_FOLDER_IMAGE: Selector<(u64, ImageFolder)> =
     Selector::new("finished_reading_image_folder");
 
 pub const POP_VIEW: Selector<()> = Selector::new("app.pop-view");
@@ -29,8 +41,124 @@ pub const SELECT_IMAGE_SELECTOR: Selector<usize> =
 pub const FINISHED_READING_IMAGE: Selector<()> =
     Selector::new("finished_reading_image");
 
+// the thumbnail carries the id of the image it was created for
 pub const CREATED_THUMBNAIL: Selector<Thumbnail> =
     Selector::new("created_thumbnail");
 
-pub const CREATED_FIRST_IMAGE_THUMBNAIL: Selector<(Thumbnail, usize)> =
+// carries the folder's id, folders move around when they're sorted
+pub const CREATED_FIRST_IMAGE_THUMBNAIL: Selector<(Thumbnail, u64)> =
     Selector::new("app.created-first-image-thumbnail");
+
+pub const THUMBNAIL_CACHE_PROGRESS: Selector<Option<(usize, usize)>> =
+    Selector::new("app.thumbnail-cache-progress");
+
+pub const RESCAN_ALL_FOLDERS: Selector<()> =
+    Selector::new("app.rescan-all-folders");
+
+pub const OPEN_CONTAINING_FOLDER: Selector<Arc<PathBuf>> =
+    Selector::new("app.open-containing-folder");
+
+pub const HIGHLIGHT_IMAGE: Selector<Arc<PathBuf>> =
+    Selector::new("app.highlight-image");
+
+// the folder read for an image whose folder wasn't in the library yet. It's
+// opened in the image view when the flag is set, otherwise the image is
+// highlighted in its gallery
+pub const SCANNED_CONTAINING_FOLDER: Selector<(
+    Arc<PathBuf>,
+    bool,
+    ImageFolder,
+)> = Selector::new("app.scanned-containing-folder");
+
+// reads a file that timed out while scanning again, by image id
+pub const RETRY_TIMED_OUT_IMAGE: Selector<u64> =
+    Selector::new("app.retry-timed-out-image");
+
+// what was read from a file that timed out before, by image id
+pub const PROBED_TIMED_OUT_IMAGE: Selector<(u64, ImageProbe)> =
+    Selector::new("app.probed-timed-out-image");
+
+// adds a folder to the library the same way choosing it in the open panel
+// does
+pub const OPEN_ROOT_FOLDER: Selector<Arc<PathBuf>> =
+    Selector::new("app.open-root-folder");
+
+// opens a folder or image given to the app from outside, like on the
+// command line
+pub const OPEN_PATH: Selector<Arc<PathBuf>> = Selector::new("app.open-path");
+
+// the sorted images a rescan of a single folder found, along with the
+// folder so results for one that was left since can be dropped
+pub const RESCANNED_FOLDER: Selector<(
+    Arc<PathBuf>,
+    Vector<Thumbnail>,
+    Vector<Arc<PathBuf>>,
+)> = Selector::new("app.rescanned-folder");
+
+// moves the root folder at this position one place up, or down when false
+pub const MOVE_ROOT_FOLDER: Selector<(usize, bool)> =
+    Selector::new("app.move-root-folder");
+
+// collapses or expands the folder with this id in the main view
+pub const TOGGLE_FOLDER_COLLAPSED: Selector<u64> =
+    Selector::new("app.toggle-folder-collapsed");
+
+// adds the gallery image at this position to the selection, or takes it out
+// when it's already selected
+pub const TOGGLE_IMAGE_SELECTED: Selector<usize> =
+    Selector::new("app.toggle-image-selected");
+
+// puts something about the gallery image at this position on the clipboard
+pub const COPY_IMAGE_DETAIL: Selector<(usize, CopyDetail)> =
+    Selector::new("app.copy-image-detail");
+
+// the memory cache's usage in megabytes
+pub const CACHE_USAGE: Selector<usize> = Selector::new("app.cache-usage");
+
+// saves what the image view currently shows to a new file
+pub const EXPORT_VIEW: Selector<()> = Selector::new("app.export-view");
+
+// asks the gallery scroll to work out which thumbnails should be decoded
+pub const UPDATE_DECODE_RANGE: Selector<()> =
+    Selector::new("app.update-decode-range");
+
+// the thumbnail of the image with this id couldn't be decoded
+pub const FAILED_THUMBNAIL: Selector<u64> =
+    Selector::new("app.failed-thumbnail");
+
+// decodes the thumbnail of the image with this id again, skipping the
+// caches
+pub const REGENERATE_THUMBNAIL: Selector<u64> =
+    Selector::new("app.regenerate-thumbnail");
+
+// shows a latitude and longitude in the system's maps app
+pub const OPEN_LOCATION: Selector<(f64, f64)> =
+    Selector::new("app.open-location");
+
+// asks where to save a straightened copy of the shown image, carries the
+// angle to turn it by
+pub const SAVE_STRAIGHTENED: Selector<f64> =
+    Selector::new("app.save-straightened");
+
+// the zoom was changed from the slider, the image view centers the image
+// at the new zoom
+pub const CENTER_ZOOMED_IMAGE: Selector<()> =
+    Selector::new("app.center-zoomed-image");
+
+// pans a zoomed image down by this many view heights, or up when it's
+// negative. The bool is whether paging down from the bottom goes on to the
+// next image
+pub const SCROLL_IMAGE_PAGE: Selector<(f64, bool)> =
+    Selector::new("app.scroll-image-page");
+
+// the image view changed size, carries the size it had before so its pan
+// can follow
+pub const IMAGE_VIEW_RESIZED: Selector<Size> =
+    Selector::new("app.image-view-resized");
+
+// the average and dominant colors worked out for the image at this path
+pub const COMPUTED_PALETTE: Selector<(PathBuf, Arc<ImagePalette>)> =
+    Selector::new("app.computed-palette");
+
+// a warning or error was logged while the diagnostics window might be open
+pub const LOG_UPDATED: Selector<()> = Selector::new("app.log-updated");
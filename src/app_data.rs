@@ -1,4 +1,10 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
 
 use druid::{
     im::{HashSet, Vector},
@@ -7,7 +13,20 @@ use druid::{
     Widget,
 };
 
-use crate::main_view::AppView;
+use crate::{
+    decode::is_psd,
+    main_view::AppView,
+    settings::{thumbnail_fill, Settings, COVER_THUMBNAILS},
+    thumbnailer,
+};
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+// ids identify folders and images no matter where they currently are in
+// their list, 0 is left for placeholders
+pub fn next_id() -> u64 {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
 
 #[derive(Clone, Data, Lens, Debug)]
 pub struct AppState {
@@ -16,26 +35,105 @@ pub struct AppState {
     pub views: Vector<AppView>,
     pub all_images: Vector<ImageFolder>,
     pub selected_folder: Option<usize>,
+    // number of thumbnails written to the disk cache out of the total
+    // while the cache is being filled in the background
+    pub thumbnail_cache_progress: Option<(usize, usize)>,
+    // megabytes held by the shared memory cache
+    pub cache_usage: usize,
+    pub settings: Settings,
+    // folders that were opened by the user, everything else was found by
+    // walking them
+    pub root_folders: Vector<Arc<PathBuf>>,
+    // folder the keyboard acts on in the main view
+    pub focused_folder: Option<usize>,
+    // recent warnings and errors, shown in the diagnostics window
+    pub log: Vector<String>,
+    // goes up every time the library is scanned again from scratch, results
+    // of older scans still coming in are dropped
+    pub scan_generation: u64,
+    // folders that were collapsed when the library was last scanned again,
+    // they come back collapsed. Only set while that scan runs
+    pub rescan_collapsed: Option<HashSet<Arc<PathBuf>>>,
+    // folders shown from the library index that the scan hasn't found again
+    // yet
+    pub indexed_folders: HashSet<Arc<PathBuf>>,
+    // folder or image the app was started with, opened instead of the
+    // startup view
+    pub opened_path: Option<Arc<PathBuf>>,
 }
 
 #[derive(Debug, Clone, Data, Lens)]
 pub struct ImageFolder {
+    pub id: u64,
     pub name: Arc<PathBuf>,
     pub folder_thumbnail: Thumbnail,
     pub paths: Vector<Arc<PathBuf>>,
     pub selected: Option<usize>,
     pub thumbnails: Vector<Thumbnail>,
+    // only the folder's name and image count are shown
+    pub collapsed: bool,
+}
+
+impl ImageFolder {
+    // bytes taken up by the images whose size is known
+    pub fn total_size(&self) -> u64 {
+        self.thumbnails
+            .iter()
+            .filter_map(|thumbnail| thumbnail.file_size)
+            .sum()
+    }
 }
 
 #[derive(Clone, Lens, Debug)]
 pub struct Thumbnail {
+    pub id: u64,
+    // position in the folder, used for ordering
     pub index: usize,
     pub image: ImageBuf,
+    pub format: FileFormat,
+    // the file is truncated or corrupt and only part of it could be decoded
+    pub partial: bool,
+    // when the photo was taken, in seconds since the unix epoch
+    pub captured: Option<i64>,
+    pub flag: Flag,
+    // stars from 1 to 5, 0 when the image isn't rated
+    pub rating: u8,
+    // free text the user wrote about the image
+    pub caption: Option<Arc<String>>,
+    // width and height of the original image
+    pub dimensions: Option<(u32, u32)>,
+    // RAW file saved alongside this JPEG for the same shot
+    pub raw_pair: Option<Arc<PathBuf>>,
+    // the file didn't respond while scanning, so nothing about it is known
+    // beyond its name
+    pub timed_out: bool,
+    // when the file was last modified, in seconds since the unix epoch
+    pub modified: Option<i64>,
+    // size of the file in bytes
+    pub file_size: Option<u64>,
+    // latitude and longitude in degrees from the photo's GPS tags
+    pub location: Option<(f64, f64)>,
+    // the thumbnail couldn't be decoded, clicking the tile tries again
+    pub failed: bool,
 }
 
 impl Data for Thumbnail {
     fn same(&self, other: &Self) -> bool {
-        self.index == other.index
+        self.id == other.id
+            && self.index == other.index
+            && self.format == other.format
+            && self.partial == other.partial
+            && self.captured == other.captured
+            && self.flag == other.flag
+            && self.rating == other.rating
+            && self.caption == other.caption
+            && self.dimensions == other.dimensions
+            && self.raw_pair == other.raw_pair
+            && self.timed_out == other.timed_out
+            && self.modified == other.modified
+            && self.file_size == other.file_size
+            && self.location == other.location
+            && self.failed == other.failed
             && self
                 .image
                 .raw_pixels_shared()
@@ -43,6 +141,84 @@ impl Data for Thumbnail {
     }
 }
 
+// quick culling mark, separate from anything else about the image
+#[derive(Debug, Clone, Copy, Data, PartialEq, Eq)]
+pub enum Flag {
+    None,
+    Pick,
+    Reject,
+}
+
+impl Flag {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Flag::None => "none",
+            Flag::Pick => "pick",
+            Flag::Reject => "reject",
+        }
+    }
+
+    pub fn parse(flag: &str) -> Option<Self> {
+        match flag {
+            "none" => Some(Flag::None),
+            "pick" => Some(Flag::Pick),
+            "reject" => Some(Flag::Reject),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Data, PartialEq, Eq)]
+pub enum FileFormat {
+    Jpeg,
+    Png,
+    Psd,
+    // formats only the external thumbnailer can read
+    Avif,
+    Jxl,
+    Heif,
+    Raw,
+    Unknown,
+}
+
+impl FileFormat {
+    pub fn from_path(path: &Path) -> Self {
+        if is_psd(path) {
+            return FileFormat::Psd;
+        }
+        if let Some(format) = thumbnailer::format(path) {
+            return format;
+        }
+        match image::ImageFormat::from_path(path) {
+            Ok(format) => format.into(),
+            Err(_) => FileFormat::Unknown,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FileFormat::Jpeg => "JPG",
+            FileFormat::Png => "PNG",
+            FileFormat::Psd => "PSD",
+            FileFormat::Avif => "AVIF",
+            FileFormat::Jxl => "JXL",
+            FileFormat::Heif => "HEIF",
+            FileFormat::Raw => "RAW",
+            FileFormat::Unknown => "",
+        }
+    }
+}
+
+impl From<image::ImageFormat> for FileFormat {
+    fn from(format: image::ImageFormat) -> Self {
+        match format {
+            image::ImageFormat::Jpeg => FileFormat::Jpeg,
+            image::ImageFormat::Png => FileFormat::Png,
+            _ => FileFormat::Unknown,
+        }
+    }
+}
+
 pub struct GalleryThumbnailController;
 
 impl Controller<Thumbnail, Image> for GalleryThumbnailController {
@@ -56,6 +232,7 @@ impl Controller<Thumbnail, Image> for GalleryThumbnailController {
     ) {
         if let LifeCycle::WidgetAdded = event {
             child.set_image_data(data.image.clone());
+            child.set_fill_mode(thumbnail_fill(env));
             ctx.request_layout();
             ctx.request_paint();
         }
@@ -77,6 +254,10 @@ impl Controller<Thumbnail, Image> for GalleryThumbnailController {
             ctx.request_layout();
             ctx.request_paint();
         }
+        if ctx.env_key_changed(&COVER_THUMBNAILS) {
+            child.set_fill_mode(thumbnail_fill(env));
+            ctx.request_paint();
+        }
         child.update(ctx, old_data, data, env)
     }
 }
@@ -0,0 +1,116 @@
+use std::{
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+// number of decodes the user is actively waiting on, background work holds
+// off until these are finished
+static FOREGROUND_JOBS: AtomicUsize = AtomicUsize::new(0);
+// number of folder scans running, several can overlap like when a folder is
+// added during a rescan
+static SCANS: AtomicUsize = AtomicUsize::new(0);
+// background work holds off while paused, and so do foreground decodes when
+// they're set to wait
+static PAUSED: AtomicBool = AtomicBool::new(false);
+static PAUSE_FOREGROUND: AtomicBool = AtomicBool::new(false);
+// number of files being written on background threads, quitting waits for
+// these so a file isn't left half written
+static WRITE_JOBS: AtomicUsize = AtomicUsize::new(0);
+
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// marks a user triggered decode for as long as it is alive
+pub struct ForegroundJob;
+
+impl ForegroundJob {
+    pub fn start() -> Self {
+        FOREGROUND_JOBS.fetch_add(1, Ordering::SeqCst);
+        ForegroundJob
+    }
+}
+
+impl Drop for ForegroundJob {
+    fn drop(&mut self) {
+        FOREGROUND_JOBS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// marks a folder scan for as long as it is alive
+pub struct ScanJob;
+
+impl ScanJob {
+    pub fn start() -> Self {
+        SCANS.fetch_add(1, Ordering::SeqCst);
+        ScanJob
+    }
+}
+
+impl Drop for ScanJob {
+    fn drop(&mut self) {
+        SCANS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// marks a file being written for as long as it is alive
+pub struct WriteJob;
+
+impl WriteJob {
+    pub fn start() -> Self {
+        WRITE_JOBS.fetch_add(1, Ordering::SeqCst);
+        WriteJob
+    }
+}
+
+impl Drop for WriteJob {
+    fn drop(&mut self) {
+        WRITE_JOBS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub fn set_paused(paused: bool) {
+    PAUSED.store(paused, Ordering::SeqCst);
+}
+
+pub fn set_pause_foreground(pause: bool) {
+    PAUSE_FOREGROUND.store(pause, Ordering::SeqCst);
+}
+
+// blocks the calling thread for as long as background work is paused
+pub fn wait_while_paused() {
+    while PAUSED.load(Ordering::SeqCst) {
+        thread::sleep(IDLE_POLL_INTERVAL);
+    }
+}
+
+// called before a decode the user is waiting on, which only waits out a
+// pause when foreground decodes are set to
+pub fn wait_for_foreground() {
+    if PAUSE_FOREGROUND.load(Ordering::SeqCst) {
+        wait_while_paused();
+    }
+}
+
+// blocks the calling thread until there are no foreground decodes or scans
+// running and background work isn't paused
+pub fn wait_for_idle() {
+    while FOREGROUND_JOBS.load(Ordering::SeqCst) > 0
+        || SCANS.load(Ordering::SeqCst) > 0
+        || PAUSED.load(Ordering::SeqCst)
+    {
+        thread::sleep(IDLE_POLL_INTERVAL);
+    }
+}
+
+// blocks the calling thread until no files are being written or the timeout
+// passes, returns how many writes were still running
+pub fn wait_for_writes(timeout: Duration) -> usize {
+    let started = Instant::now();
+    loop {
+        let running = WRITE_JOBS.load(Ordering::SeqCst);
+        if running == 0 || started.elapsed() >= timeout {
+            return running;
+        }
+        thread::sleep(IDLE_POLL_INTERVAL);
+    }
+}
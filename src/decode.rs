@@ -0,0 +1,451 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, Read},
+    path::Path,
+};
+
+use image::{
+    codecs::{jpeg::JpegDecoder, png::PngDecoder},
+    imageops,
+    io::Reader,
+    ColorType, DynamicImage, GrayImage, ImageBuffer, ImageDecoder, ImageError,
+    ImageFormat, Luma, Pixel, Rgb, RgbImage, Rgba, RgbaImage,
+};
+use log::error;
+
+use crate::thumbnailer;
+
+// resizes the image down to the given size. imageops averages the stored
+// sRGB values, which darkens fine high contrast detail, so when linear_light
+// is set the pixels are averaged as light intensities instead. That's a fair
+// bit slower
+pub fn downscale(
+    image: &RgbImage,
+    width: u32,
+    height: u32,
+    linear_light: bool,
+) -> RgbImage {
+    if !linear_light {
+        return imageops::thumbnail(image, width, height);
+    }
+    let to_linear: Vec<u16> = (0..=255u8)
+        .map(|value| {
+            (srgb_to_linear(value as f32 / 255.) * 65535.).round() as u16
+        })
+        .collect();
+    let (source_width, source_height) = image.dimensions();
+    let linear: ImageBuffer<image::Rgb<u16>, Vec<u16>> = ImageBuffer::from_raw(
+        source_width,
+        source_height,
+        image
+            .as_raw()
+            .iter()
+            .map(|value| to_linear[*value as usize])
+            .collect(),
+    )
+    .unwrap();
+    let resized = imageops::thumbnail(&linear, width, height);
+    let pixels = resized
+        .as_raw()
+        .iter()
+        .map(|value| {
+            (linear_to_srgb(*value as f32 / 65535.) * 255.).round() as u8
+        })
+        .collect();
+    RgbImage::from_raw(width, height, pixels).unwrap()
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1. / 2.4) - 0.055
+    }
+}
+
+// TIFF based camera RAW formats, which can't be decoded directly but embed a
+// JPEG preview that can
+const RAW_EXTENSIONS: [&str; 5] = ["cr2", "nef", "arw", "dng", "pef"];
+
+pub fn is_raw(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map_or(false, |extension| {
+            RAW_EXTENSIONS
+                .iter()
+                .any(|raw| raw.eq_ignore_ascii_case(extension))
+        })
+}
+
+// Photoshop files can only be shown when the app is built with the psd
+// feature, and then only by the flattened composite they embed
+pub fn is_psd(path: &Path) -> bool {
+    cfg!(feature = "psd")
+        && path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map_or(false, |extension| extension.eq_ignore_ascii_case("psd"))
+}
+
+// width and height from the file's header, which the image crate can't read
+pub fn psd_dimensions(path: &Path) -> Option<(u32, u32)> {
+    let mut header = [0; 22];
+    File::open(path).ok()?.read_exact(&mut header).ok()?;
+    if &header[..4] != b"8BPS" {
+        return None;
+    }
+    let number = |bytes: &[u8]| {
+        u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    };
+    Some((number(&header[18..22]), number(&header[14..18])))
+}
+
+// decodes an image, falling back to whatever could be read before the error
+// for truncated or corrupt files. The returned bool is true when the image
+// is only partially decoded
+pub fn decode_lenient(path: &Path) -> Result<(RgbImage, bool), ImageError> {
+    // RAW files without a usable preview go to the external thumbnailer
+    if is_raw(path) {
+        let image = raw_preview(path)
+            .or_else(|err| thumbnailer::preview(path).ok_or(err))?;
+        return Ok((image, false));
+    }
+    if thumbnailer::format(path).is_some() {
+        return match thumbnailer::preview(path) {
+            Some(image) => Ok((image, false)),
+            None => Err(ImageError::IoError(io::Error::new(
+                io::ErrorKind::Other,
+                "the external thumbnailer couldn't read it",
+            ))),
+        };
+    }
+    #[cfg(feature = "psd")]
+    if is_psd(path) {
+        return Ok((psd_preview(path)?, false));
+    }
+    let reader = Reader::open(path)?.with_guessed_format()?;
+    let format = reader.format();
+    let error = match reader.decode() {
+        Ok(image) => return Ok((image.to_rgb8(), false)),
+        Err(err) => err,
+    };
+    error!("Error decoding {}: {}", path.display(), error);
+    let file = BufReader::new(File::open(path)?);
+    let partial = match format {
+        Some(ImageFormat::Png) => decode_partial(PngDecoder::new(file)?),
+        Some(ImageFormat::Jpeg) => decode_partial(JpegDecoder::new(file)?),
+        _ => None,
+    };
+    match partial {
+        Some(image) => Ok((image.to_rgb8(), true)),
+        None => Err(error),
+    }
+}
+
+// the alpha channel of a PNG that has one, read on its own since everything
+// else works with RGB. Only the header is read for PNGs without one
+pub fn decode_alpha(path: &Path) -> Option<GrayImage> {
+    if ImageFormat::from_path(path).ok()? != ImageFormat::Png {
+        return None;
+    }
+    let decoder =
+        PngDecoder::new(BufReader::new(File::open(path).ok()?)).ok()?;
+    if !decoder.color_type().has_alpha() {
+        return None;
+    }
+    let image = DynamicImage::from_decoder(decoder).ok()?.to_rgba8();
+    let (width, height) = image.dimensions();
+    Some(GrayImage::from_fn(width, height, |x, y| {
+        Luma([image.get_pixel(x, y)[3]])
+    }))
+}
+
+// width and height of the largest upright rectangle that fits inside a
+// width by height rectangle turned by angle radians
+pub fn inscribed_size(width: f64, height: f64, angle: f64) -> (f64, f64) {
+    if width <= 0. || height <= 0. {
+        return (0., 0.);
+    }
+    let (sin, cos) = (angle.sin().abs(), angle.cos().abs());
+    let (long, short) = if width >= height {
+        (width, height)
+    } else {
+        (height, width)
+    };
+    // narrow rectangles only touch the turned one at two corners
+    if short <= 2. * sin * cos * long || (sin - cos).abs() < 1e-10 {
+        let half = short / 2.;
+        if width >= height {
+            (half / sin, half / cos)
+        } else {
+            (half / cos, half / sin)
+        }
+    } else {
+        let cos_2a = cos * cos - sin * sin;
+        (
+            (width * cos - height * sin) / cos_2a,
+            (height * cos - width * sin) / cos_2a,
+        )
+    }
+}
+
+// turns the image by angle radians about its center, the way the image view
+// shows it while straightening, and crops away the empty corners. An alpha
+// channel read on its own turns the same way as the colors
+pub fn straighten<P: Pixel<Subpixel = u8> + 'static>(
+    image: &ImageBuffer<P, Vec<u8>>,
+    angle: f64,
+) -> ImageBuffer<P, Vec<u8>> {
+    let (width, height) = image.dimensions();
+    let (crop_width, crop_height) =
+        inscribed_size(width as f64, height as f64, angle);
+    let crop_width = (crop_width.floor() as u32).max(1);
+    let crop_height = (crop_height.floor() as u32).max(1);
+    let (sin, cos) = angle.sin_cos();
+    let (center_x, center_y) = (width as f64 / 2., height as f64 / 2.);
+    ImageBuffer::from_fn(crop_width, crop_height, |x, y| {
+        let u = x as f64 + 0.5 - crop_width as f64 / 2.;
+        let v = y as f64 + 0.5 - crop_height as f64 / 2.;
+        sample_bilinear(
+            image,
+            center_x + cos * u + sin * v - 0.5,
+            center_y - sin * u + cos * v - 0.5,
+        )
+    })
+}
+
+fn sample_bilinear<P: Pixel<Subpixel = u8> + 'static>(
+    image: &ImageBuffer<P, Vec<u8>>,
+    x: f64,
+    y: f64,
+) -> P {
+    let (max_x, max_y) = (image.width() - 1, image.height() - 1);
+    let x = x.max(0.).min(max_x as f64);
+    let y = y.max(0.).min(max_y as f64);
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(max_x), (y0 + 1).min(max_y));
+    let (fx, fy) = (x - x0 as f64, y - y0 as f64);
+    let channel = |c: usize| {
+        let value = |x, y| image.get_pixel(x, y).channels()[c] as f64;
+        let top = value(x0, y0) * (1. - fx) + value(x1, y0) * fx;
+        let bottom = value(x0, y1) * (1. - fx) + value(x1, y1) * fx;
+        (top * (1. - fy) + bottom * fy).round() as u8
+    };
+    let mut channels = [0; 4];
+    let count = P::CHANNEL_COUNT as usize;
+    for (c, value) in channels[..count].iter_mut().enumerate() {
+        *value = channel(c);
+    }
+    *P::from_slice(&channels[..count])
+}
+
+// puts an alpha channel read with decode_alpha back together with the
+// colors, they have to be the same size
+pub fn join_alpha(image: &RgbImage, alpha: &GrayImage) -> RgbaImage {
+    RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+        let Rgb([red, green, blue]) = *image.get_pixel(x, y);
+        Rgba([red, green, blue, alpha.get_pixel(x, y)[0]])
+    })
+}
+
+// turns the stored pixels upright according to an EXIF orientation
+pub fn apply_orientation<P: Pixel + 'static>(
+    image: ImageBuffer<P, Vec<P::Subpixel>>,
+    orientation: u32,
+) -> ImageBuffer<P, Vec<P::Subpixel>> {
+    match orientation {
+        2 => imageops::flip_horizontal(&image),
+        3 => imageops::rotate180(&image),
+        4 => imageops::flip_vertical(&image),
+        5 => imageops::flip_horizontal(&imageops::rotate90(&image)),
+        6 => imageops::rotate90(&image),
+        7 => imageops::flip_horizontal(&imageops::rotate270(&image)),
+        8 => imageops::rotate270(&image),
+        _ => image,
+    }
+}
+
+// the size of an image once apply_orientation has turned it
+pub fn oriented_size(
+    (width, height): (u32, u32),
+    orientation: u32,
+) -> (u32, u32) {
+    match orientation {
+        5..=8 => (height, width),
+        _ => (width, height),
+    }
+}
+
+// decodes the largest JPEG preview referenced by the RAW file's EXIF data
+fn raw_preview(path: &Path) -> Result<RgbImage, ImageError> {
+    let invalid = |message: String| {
+        ImageError::IoError(io::Error::new(io::ErrorKind::InvalidData, message))
+    };
+    let data = fs::read(path)?;
+    let exif = exif::Reader::new()
+        .read_raw(data.clone())
+        .map_err(|err| invalid(err.to_string()))?;
+    let preview = |ifd| {
+        let offset = exif
+            .get_field(exif::Tag::JPEGInterchangeFormat, ifd)?
+            .value
+            .get_uint(0)? as usize;
+        let length = exif
+            .get_field(exif::Tag::JPEGInterchangeFormatLength, ifd)?
+            .value
+            .get_uint(0)? as usize;
+        data.get(offset..offset + length)
+    };
+    let preview = [exif::In::PRIMARY, exif::In::THUMBNAIL]
+        .iter()
+        .filter_map(|ifd| preview(*ifd))
+        .max_by_key(|preview| preview.len())
+        .ok_or_else(|| invalid("no embedded preview".to_string()))?;
+    Ok(image::load_from_memory(preview)?.to_rgb8())
+}
+
+// the composite image Photoshop saves next to the layers. Files saved
+// without maximized compatibility leave it empty, those get the placeholder
+#[cfg(feature = "psd")]
+fn psd_preview(path: &Path) -> Result<RgbImage, ImageError> {
+    let invalid = |message: String| {
+        ImageError::IoError(io::Error::new(io::ErrorKind::InvalidData, message))
+    };
+    let psd = psd::Psd::from_bytes(&fs::read(path)?)
+        .map_err(|err| invalid(err.to_string()))?;
+    let pixels = psd.rgba();
+    if pixels.chunks(4).all(|pixel| pixel[3] == 0) {
+        return Err(invalid("no flattened preview".to_string()));
+    }
+    let image = image::RgbaImage::from_raw(psd.width(), psd.height(), pixels)
+        .ok_or_else(|| invalid("preview is cut short".to_string()))?;
+    Ok(DynamicImage::ImageRgba8(image).to_rgb8())
+}
+
+// reads as much of the image as the decoder manages, the rest stays black
+fn decode_partial<'a>(decoder: impl ImageDecoder<'a>) -> Option<DynamicImage> {
+    let (width, height) = decoder.dimensions();
+    let color_type = decoder.color_type();
+    let mut buffer = vec![0; decoder.total_bytes() as usize];
+    // the error is expected, the decoder has already written the rows it
+    // could read into the buffer
+    let _ = decoder.read_image(&mut buffer);
+    // nothing was recovered
+    if buffer.iter().all(|byte| *byte == 0) {
+        return None;
+    }
+    to_dynamic_image(width, height, color_type, buffer)
+}
+
+fn to_dynamic_image(
+    width: u32,
+    height: u32,
+    color_type: ColorType,
+    buffer: Vec<u8>,
+) -> Option<DynamicImage> {
+    let image = match color_type {
+        ColorType::L8 => DynamicImage::ImageLuma8(ImageBuffer::from_raw(
+            width, height, buffer,
+        )?),
+        ColorType::La8 => DynamicImage::ImageLumaA8(ImageBuffer::from_raw(
+            width, height, buffer,
+        )?),
+        ColorType::Rgb8 => DynamicImage::ImageRgb8(ImageBuffer::from_raw(
+            width, height, buffer,
+        )?),
+        ColorType::Rgba8 => DynamicImage::ImageRgba8(ImageBuffer::from_raw(
+            width, height, buffer,
+        )?),
+        _ => return None,
+    };
+    Some(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // whether a width by height rectangle turned by angle fits inside the
+    // bounds
+    fn fits(size: (f64, f64), angle: f64, bounds: (f64, f64)) -> bool {
+        let (sin, cos) = (angle.sin().abs(), angle.cos().abs());
+        let (width, height) = size;
+        width * cos + height * sin <= bounds.0 + 1e-9
+            && width * sin + height * cos <= bounds.1 + 1e-9
+    }
+
+    fn assert_close(actual: (f64, f64), expected: (f64, f64)) {
+        assert!(
+            (actual.0 - expected.0).abs() < 1e-6
+                && (actual.1 - expected.1).abs() < 1e-6,
+            "{:?} isn't {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn unturned_rectangles_keep_their_size() {
+        assert_close(inscribed_size(400., 300., 0.), (400., 300.));
+        assert_close(inscribed_size(300., 400., 0.), (300., 400.));
+    }
+
+    #[test]
+    fn quarter_turns_swap_the_sides() {
+        let quarter = std::f64::consts::FRAC_PI_2;
+        assert_close(inscribed_size(400., 300., quarter), (300., 400.));
+    }
+
+    #[test]
+    fn squares_turned_by_45_degrees() {
+        let side = 100. / 2f64.sqrt();
+        let eighth = std::f64::consts::FRAC_PI_4;
+        assert_close(inscribed_size(100., 100., eighth), (side, side));
+    }
+
+    #[test]
+    fn inscribed_rectangles_fit() {
+        for &(width, height) in &[(400., 300.), (300., 400.), (1000., 10.)] {
+            for &angle in &[0.05, -0.1, 0.3, 0.5, 1.2] {
+                let size = inscribed_size(width, height, angle);
+                assert!(size.0 > 0. && size.1 > 0.);
+                assert!(
+                    fits(size, angle, (width, height)),
+                    "{:?} doesn't fit in {}x{} at {}",
+                    size,
+                    width,
+                    height,
+                    angle
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn empty_rectangles_have_nothing_inside() {
+        assert_eq!(inscribed_size(0., 300., 0.2), (0., 0.));
+        assert_eq!(inscribed_size(400., -1., 0.2), (0., 0.));
+    }
+
+    #[test]
+    fn oriented_size_matches_apply_orientation() {
+        let image = RgbImage::new(3, 2);
+        for orientation in 1..=8 {
+            let turned = apply_orientation(image.clone(), orientation);
+            assert_eq!(
+                oriented_size(image.dimensions(), orientation),
+                turned.dimensions(),
+                "orientation {}",
+                orientation
+            );
+        }
+    }
+}
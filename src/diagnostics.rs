@@ -0,0 +1,176 @@
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use druid::{
+    im::Vector,
+    widget::{
+        Controller, CrossAxisAlignment, Flex, Label, LineBreaking, List, Scroll,
+    },
+    Application, Color, Env, Event, EventCtx, ExtEventSink, Target, Widget,
+    WidgetExt, WindowDesc,
+};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use once_cell::sync::Lazy;
+
+use crate::{
+    app_commands::LOG_UPDATED, app_data::AppState, widgets::text_button,
+};
+
+// warnings and errors that happened recently, oldest first
+static RECENT: Lazy<Mutex<VecDeque<String>>> =
+    Lazy::new(|| Mutex::new(VecDeque::new()));
+// enough to see what went wrong while scanning a big folder without keeping
+// every message of a long session
+const MAX_RECENT: usize = 500;
+
+// lets an open diagnostics window know a message was added
+static SINK: Lazy<Mutex<Option<ExtEventSink>>> = Lazy::new(|| Mutex::new(None));
+
+static LOGGER: DiagnosticsLogger = DiagnosticsLogger;
+
+// prints to stderr like the simple logger did and keeps the warnings and
+// errors around to be shown in the app
+struct DiagnosticsLogger;
+
+impl Log for DiagnosticsLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Info
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = format!(
+            "{} {} {}: {}",
+            time_of_day(),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        eprintln!("{}", line);
+        if record.level() > Level::Warn {
+            return;
+        }
+        {
+            let mut recent = RECENT.lock().unwrap();
+            if recent.len() >= MAX_RECENT {
+                recent.pop_front();
+            }
+            recent.push_back(line);
+        }
+        if let Some(sink) = SINK.lock().unwrap().as_ref() {
+            let _ = sink.submit_command(LOG_UPDATED, (), Target::Global);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+pub fn init() {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(LevelFilter::Info);
+    }
+}
+
+// called once the app is launching, messages before then are still kept
+pub fn set_sink(sink: ExtEventSink) {
+    *SINK.lock().unwrap() = Some(sink);
+}
+
+pub fn recent() -> Vector<String> {
+    RECENT.lock().unwrap().iter().cloned().collect()
+}
+
+// the current UTC time as HH:MM:SS
+fn time_of_day() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_secs())
+        .unwrap_or(0)
+        % 86400;
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+// shows the files that were skipped and the errors that happened, which
+// otherwise only end up on the terminal
+pub fn open_diagnostics(ctx: &mut EventCtx, data: &mut AppState) {
+    data.log = recent();
+    let window = WindowDesc::new(diagnostics_view)
+        .title("Diagnostics")
+        .window_size((640., 420.));
+    ctx.new_window(window);
+}
+
+fn diagnostics_view() -> impl Widget<AppState> {
+    let copy_btn =
+        text_button("Copy Log", |_ctx, data: &mut AppState, _env| {
+            let log = data.log.iter().cloned().collect::<Vec<_>>().join("\n");
+            Application::global().clipboard().put_string(log);
+        });
+    let clear_btn = text_button("Clear", |_ctx, data: &mut AppState, _env| {
+        RECENT.lock().unwrap().clear();
+        data.log.clear();
+    });
+
+    let header = Flex::row()
+        .with_child(
+            Label::dynamic(|data: &AppState, _env| match data.log.len() {
+                0 => "Nothing has gone wrong".to_string(),
+                1 => "1 message".to_string(),
+                len => format!("{} messages", len),
+            })
+            .with_text_color(Color::BLACK),
+        )
+        .with_flex_spacer(1.)
+        .with_child(clear_btn.fix_height(36.))
+        .with_spacer(8.)
+        .with_child(copy_btn.fix_height(36.))
+        .padding((12., 8.));
+
+    let messages = List::new(|| {
+        Label::dynamic(|line: &String, _env| line.clone())
+            .with_text_color(Color::BLACK)
+            .with_text_size(13.)
+            .with_line_break_mode(LineBreaking::WordWrap)
+            .padding((12., 2.))
+            .expand_width()
+    })
+    .lens(AppState::log);
+
+    Flex::column()
+        .with_child(header)
+        .with_flex_child(Scroll::new(messages).vertical().expand(), 1.)
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .background(Color::WHITE)
+        .controller(DiagnosticsController)
+}
+
+// keeps the open window up to date as messages come in
+struct DiagnosticsController;
+
+impl<W: Widget<AppState>> Controller<AppState, W> for DiagnosticsController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        match event {
+            Event::Command(cmd) if cmd.is(LOG_UPDATED) => {
+                data.log = recent();
+            }
+            _ => child.event(ctx, event, data, env),
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use std::{
+    env::temp_dir,
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use druid::Data;
+
+// operations on image files that can be undone
+#[derive(Debug, Clone, Data)]
+pub enum FileOperation {
+    Trashed {
+        original: Arc<PathBuf>,
+        trashed: Arc<PathBuf>,
+    },
+}
+
+// kept with the user's app data rather than in the temp folder, which the
+// system can clear while trashed files are still meant to be restorable
+fn trash_dir() -> PathBuf {
+    match dirs::data_dir() {
+        Some(data_dir) => data_dir.join("image-viewer").join("trash"),
+        None => temp_dir().join("image-viewer-trash"),
+    }
+}
+
+// moves the file into the app's trash folder, returning where it ended up
+pub fn move_to_trash(path: &Path) -> io::Result<PathBuf> {
+    let trash = trash_dir();
+    fs::create_dir_all(&trash)?;
+    let file_name = path.file_name().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "path has no file name")
+    })?;
+    // prefixed so files with the same name from different folders don't
+    // overwrite each other
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or(0);
+    let mut trashed_name = OsString::from(format!("{}-", stamp));
+    trashed_name.push(file_name);
+    let trashed = trash.join(trashed_name);
+    move_file(path, &trashed)?;
+    Ok(trashed)
+}
+
+// copies the file into the folder, numbering the copy when a file with the
+// same name is already there, returning where it ended up
+pub fn copy_to_folder(path: &Path, folder: &Path) -> io::Result<PathBuf> {
+    fs::create_dir_all(folder)?;
+    let stem = path.file_stem().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "path has no file name")
+    })?;
+    let extension = path.extension();
+    let mut source = File::open(path)?;
+    let mut copy = folder.join(path.file_name().unwrap());
+    let mut suffix = 1;
+    loop {
+        // creating the file fails when it's already there, so a file that
+        // shows up between looking and copying isn't overwritten
+        match OpenOptions::new().write(true).create_new(true).open(&copy) {
+            Ok(mut file) => {
+                if let Err(err) = io::copy(&mut source, &mut file) {
+                    drop(file);
+                    let _ = fs::remove_file(&copy);
+                    return Err(err);
+                }
+                return Ok(copy);
+            }
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                let mut name = stem.to_os_string();
+                name.push(format!("-{}", suffix));
+                if let Some(extension) = extension {
+                    name.push(".");
+                    name.push(extension);
+                }
+                copy = folder.join(name);
+                suffix += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+// puts the file back where it was before the operation
+pub fn undo(operation: &FileOperation) -> io::Result<()> {
+    match operation {
+        FileOperation::Trashed { original, trashed } => {
+            move_file(trashed, original)
+        }
+    }
+}
+
+// moves the file without ever replacing one at the destination, even one
+// that shows up while it's moving. Linking fails when the destination is
+// there, and files that can't be linked, like ones going to another drive,
+// are copied into a file that's created new
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    let already_exists = || {
+        io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", to.display()),
+        )
+    };
+    match fs::hard_link(from, to) {
+        Ok(()) => return remove_moved(from, to),
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+            return Err(already_exists())
+        }
+        Err(_) => (),
+    }
+    let mut source = File::open(from)?;
+    let mut file =
+        match OpenOptions::new().write(true).create_new(true).open(to) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                return Err(already_exists())
+            }
+            Err(err) => return Err(err),
+        };
+    if let Err(err) = io::copy(&mut source, &mut file) {
+        drop(file);
+        let _ = fs::remove_file(to);
+        return Err(err);
+    }
+    if let Ok(metadata) = source.metadata() {
+        let _ = file.set_permissions(metadata.permissions());
+    }
+    remove_moved(from, to)
+}
+
+// removes the file that was moved, taking the new one back when it can't so
+// the file isn't left in both places
+fn remove_moved(from: &Path, to: &Path) -> io::Result<()> {
+    fs::remove_file(from).map_err(|err| {
+        let _ = fs::remove_file(to);
+        err
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_folder(name: &str) -> PathBuf {
+        let folder = temp_dir().join(format!(
+            "image-viewer-file-ops-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+        folder
+    }
+
+    #[test]
+    fn moves_the_file() {
+        let folder = test_folder("move");
+        let (from, to) = (folder.join("a.jpg"), folder.join("b.jpg"));
+        fs::write(&from, "image").unwrap();
+        move_file(&from, &to).unwrap();
+        assert!(!from.exists());
+        assert_eq!(fs::read_to_string(&to).unwrap(), "image");
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn never_replaces_the_destination() {
+        let folder = test_folder("no-replace");
+        let (from, to) = (folder.join("a.jpg"), folder.join("b.jpg"));
+        fs::write(&from, "moved").unwrap();
+        fs::write(&to, "kept").unwrap();
+        let err = move_file(&from, &to).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(&from).unwrap(), "moved");
+        assert_eq!(fs::read_to_string(&to).unwrap(), "kept");
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
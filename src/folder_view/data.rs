@@ -1,29 +1,182 @@
 use std::{
-    path::PathBuf,
+    cell::RefCell,
+    cmp::{Ordering, Reverse},
+    collections::{HashMap, HashSet},
+    f64::consts::{FRAC_PI_2, FRAC_PI_4},
+    path::{Path, PathBuf},
+    rc::Rc,
     sync::{
+        atomic::{self, AtomicUsize},
         mpsc::{sync_channel, Receiver, SyncSender},
         Arc,
     },
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use druid::{
-    im::{vector, Vector},
-    piet::ImageFormat,
-    widget::{Container, Controller, Image, ScopeTransfer},
-    Data, Env, Event, ExtEventSink, ImageBuf, Lens, LifeCycle, LifeCycleCtx,
-    UpdateCtx, Widget, WidgetId,
+    commands::{SAVE_FILE_AS, SHOW_SAVE_PANEL},
+    im::{self, vector, Vector},
+    piet::{Device, ImageFormat, InterpolationMode, RenderContext},
+    widget::{
+        Container, Controller, FillStrat, Image, ListIter, ScopeTransfer,
+    },
+    Affine, Application, Command, Cursor, Data, Env, Event, ExtEventSink,
+    FileDialogOptions, FileSpec, ImageBuf, KbKey, Lens, LifeCycle,
+    LifeCycleCtx, MouseButton, Point, Rect, Screen, Size, Target, TimerToken,
+    UpdateCtx, Vec2, Widget, WidgetId, WindowHandle,
 };
 use druid_gridview::GridIter;
 use druid_navigator::navigator::{View, ViewController};
-use image::RgbImage;
+use image::{imageops::FilterType, GrayImage, ImageError, RgbImage};
+use log::error;
 
 use crate::{
     app_commands::{
-        CREATED_THUMBNAIL, FINISHED_READING_IMAGE, POP_FOLDER_VIEW,
-        PUSH_VIEW_WITH_SELECTED_IMAGE,
+        CACHE_USAGE, CENTER_ZOOMED_IMAGE, COMPUTED_PALETTE, COPY_IMAGE_DETAIL,
+        CREATED_THUMBNAIL, EXPORT_VIEW, FAILED_THUMBNAIL,
+        FINISHED_READING_IMAGE, HIGHLIGHT_IMAGE, IMAGE_VIEW_RESIZED,
+        OPEN_LOCATION, POP_FOLDER_VIEW, POP_VIEW, PROBED_TIMED_OUT_IMAGE,
+        PUSH_VIEW_WITH_SELECTED_IMAGE, REGENERATE_THUMBNAIL, RESCANNED_FOLDER,
+        RETRY_TIMED_OUT_IMAGE, SAVE_STRAIGHTENED, SCROLL_IMAGE_PAGE,
+        TOGGLE_IMAGE_SELECTED, UPDATE_DECODE_RANGE,
+    },
+    app_data::{AppState, FileFormat, Flag, Thumbnail},
+    background::{self, ForegroundJob, WriteJob},
+    decode::{
+        apply_orientation, decode_alpha, decode_lenient, join_alpha,
+        oriented_size, straighten,
+    },
+    file_ops::{self, FileOperation},
+    format,
+    main_view::rescan_folder,
+    memory_cache::{self, CacheKind},
+    metadata::{self, ProbeWorker},
+    natural_order,
+    palette::{self, ImagePalette},
+    preferences::open_preferences,
+    remote, render_cache,
+    settings::{
+        thumbnail_fill, CaptionContent, CaptionPosition, DateGrouping,
+        FolderEndAction, Settings, SlideshowTransition, WheelAction,
+        COVER_THUMBNAILS,
+    },
+    sidecar, thumbnail_cache,
+    widgets::{
+        JustifiedRow, JustifiedRows, Scroll, Zoom, SCROLL_TO_WINDOW_RECT,
+        SHOW_TOAST, TOAST_DURATION,
     },
-    app_data::{AppState, Thumbnail},
+    window::open_window,
 };
+
+use super::{draw_caption, load_thumbnails};
+// what the copy menu of an image can put on the clipboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyDetail {
+    Path,
+    FileName,
+    Dimensions,
+    Exif,
+    Location,
+}
+
+impl CopyDetail {
+    pub fn label(&self) -> &'static str {
+        match self {
+            CopyDetail::Path => "path",
+            CopyDetail::FileName => "file name",
+            CopyDetail::Dimensions => "dimensions",
+            CopyDetail::Exif => "EXIF data",
+            CopyDetail::Location => "GPS coordinates",
+        }
+    }
+}
+
+// which flagged images the gallery shows
+#[derive(Debug, Clone, Copy, Data, PartialEq, Eq)]
+pub enum FlagFilter {
+    All,
+    Picked,
+    Rejected,
+    Unflagged,
+}
+
+impl FlagFilter {
+    pub fn label(&self) -> &'static str {
+        match self {
+            FlagFilter::All => "All",
+            FlagFilter::Picked => "Picked",
+            FlagFilter::Rejected => "Rejected",
+            FlagFilter::Unflagged => "Unflagged",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            FlagFilter::All => FlagFilter::Picked,
+            FlagFilter::Picked => FlagFilter::Rejected,
+            FlagFilter::Rejected => FlagFilter::Unflagged,
+            FlagFilter::Unflagged => FlagFilter::All,
+        }
+    }
+
+    pub fn matches(&self, flag: Flag) -> bool {
+        match self {
+            FlagFilter::All => true,
+            FlagFilter::Picked => flag == Flag::Pick,
+            FlagFilter::Rejected => flag == Flag::Reject,
+            FlagFilter::Unflagged => flag == Flag::None,
+        }
+    }
+}
+
+// what the rows of the details view can be sorted by
+#[derive(Debug, Clone, Copy, Data, PartialEq, Eq)]
+pub enum DetailsColumn {
+    Name,
+    Dimensions,
+    Size,
+    Date,
+}
+
+impl DetailsColumn {
+    pub fn label(&self) -> &'static str {
+        match self {
+            DetailsColumn::Name => "Name",
+            DetailsColumn::Dimensions => "Dimensions",
+            DetailsColumn::Size => "Size",
+            DetailsColumn::Date => "Date",
+        }
+    }
+}
+
+// the date the details view shows, when the photo was taken if that's known
+pub fn details_date(thumbnail: &Thumbnail) -> Option<i64> {
+    thumbnail.captured.or(thumbnail.modified)
+}
+
+// the most operations that can be undone
+const UNDO_LIMIT: usize = 20;
+
+#[derive(Debug, Clone, Data)]
+pub enum UndoEntry {
+    // a file operation along with what's needed to put the image back into
+    // the gallery it was removed from
+    File {
+        operation: FileOperation,
+        folder: Arc<PathBuf>,
+        index: usize,
+        path: Arc<PathBuf>,
+        thumbnail: Thumbnail,
+    },
+    // the flag and rating the image had before another image's were copied
+    // onto it
+    Flag {
+        path: Arc<PathBuf>,
+        flag: Flag,
+        rating: u8,
+    },
+}
+
 #[derive(Debug, Clone, Data, Lens)]
 pub struct FolderGalleryState {
     pub name: Arc<PathBuf>,
@@ -32,6 +185,83 @@ pub struct FolderGalleryState {
     pub selected_image: usize,
     pub views: Vector<FolderView>,
     pub paths: Vector<Arc<PathBuf>>,
+    pub force_nearest_interpolation: bool,
+    // image that stands out in the gallery, like one that was just jumped to
+    pub highlighted_image: Option<usize>,
+    // images picked out in the gallery for acting on together
+    pub selected: im::HashSet<Arc<PathBuf>>,
+    // indices of the thumbnails that should be decoded, the visible ones
+    // plus the overscan
+    pub decode_range: (usize, usize),
+    pub flag_filter: FlagFilter,
+    // only shows images modified within the recent days setting, newest
+    // first
+    pub recent_only: bool,
+    // zoom relative to the image fit inside the window
+    pub zoom: f64,
+    pub pan: Vec2,
+    pub settings: Settings,
+    pub slideshow: bool,
+    // plays the numbered sequence the current image is part of as frames
+    pub playing: bool,
+    // image the selected one gets compared against when flickering
+    pub compare_image: Option<usize>,
+    // alternates between the selected image and the compare image
+    pub flicker: bool,
+    pub flicker_showing_compare: bool,
+    // the image that was shown before the selected one, and whether it's
+    // being shown instead for as long as the peek key is held
+    pub last_viewed: Option<Arc<PathBuf>>,
+    pub peeking: bool,
+    // progress of the transition into the current image, 1.0 when there
+    // isn't one running
+    pub transition: f64,
+    // operations from this session that can be undone, newest last
+    pub undo_stack: Vector<UndoEntry>,
+    // text typed into the go to image input while it's open
+    pub goto_input: Option<String>,
+    // briefly shown when the go to input didn't match any image
+    pub goto_error: Option<String>,
+    // text typed into the shown image's caption while it's being edited
+    pub caption_input: Option<String>,
+    // the image waiting on the user to confirm moving it to the trash
+    pub confirm_trash: Option<Arc<PathBuf>>,
+    // briefly confirms something that happened without a dialog, like
+    // copying to the favorites folder
+    pub toast: Option<String>,
+    // how many folders there are in the library, so navigation can move
+    // past the end of this one
+    pub folder_count: usize,
+    // folder that navigation moved into, which gets loaded when the state is
+    // written back to the library
+    pub requested_folder: Option<usize>,
+    // shows the RAW half of RAW+JPEG pairs instead of the JPEG
+    pub show_raw: bool,
+    // size of the decoded image on screen and the scale that fits it in the
+    // view, kept up to date by the image display
+    pub displayed_size: Size,
+    pub fit_scale: f64,
+    // hides the image view behind solid black, for presentations
+    pub blanked: bool,
+    // clicking the image samples the color of the pixel under the cursor
+    pub eyedropper: bool,
+    // position and color of the last pixel that was sampled
+    pub picked_color: Option<(u32, u32, (u8, u8, u8))>,
+    // the pixels around the cursor while the eyedropper is on, and where
+    // the cursor is
+    pub loupe: Option<(Point, ImageBuf)>,
+    // shows the average and dominant colors of the image below it
+    pub show_palette: bool,
+    // the colors of the image being shown, worked out while the palette is
+    // shown
+    pub palette: Option<Arc<ImagePalette>>,
+    // column the details view is sorted by and whether it's descending, the
+    // folder's own order is kept until a column is picked
+    pub details_sort: Option<(DetailsColumn, bool)>,
+    // the angle in radians the image is turned by while straightening it
+    pub straighten: Option<f64>,
+    // the line being dragged along what should be level while straightening
+    pub straighten_line: Option<(Point, Point)>,
 }
 
 impl FolderGalleryState {
@@ -44,6 +274,43 @@ impl FolderGalleryState {
                 selected_image: 0,
                 views: vector![FolderView::Folder],
                 paths: state.all_images[idx].paths.clone(),
+                force_nearest_interpolation: false,
+                highlighted_image: None,
+                selected: im::HashSet::new(),
+                decode_range: (0, 0),
+                flag_filter: FlagFilter::All,
+                recent_only: false,
+                zoom: 1.,
+                pan: Vec2::ZERO,
+                settings: state.settings.clone(),
+                slideshow: false,
+                playing: false,
+                compare_image: None,
+                flicker: false,
+                flicker_showing_compare: false,
+                last_viewed: None,
+                peeking: false,
+                undo_stack: Vector::new(),
+                goto_input: None,
+                goto_error: None,
+                caption_input: None,
+                confirm_trash: None,
+                toast: None,
+                folder_count: state.all_images.len(),
+                requested_folder: None,
+                show_raw: false,
+                displayed_size: Size::ZERO,
+                fit_scale: 1.,
+                blanked: false,
+                eyedropper: false,
+                picked_color: None,
+                loupe: None,
+                show_palette: false,
+                palette: None,
+                details_sort: None,
+                straighten: None,
+                straighten_line: None,
+                transition: 1.,
             }
         } else {
             Self {
@@ -53,9 +320,984 @@ impl FolderGalleryState {
                 selected_image: 0,
                 views: vector![FolderView::Folder],
                 paths: Vector::new(),
+                force_nearest_interpolation: false,
+                highlighted_image: None,
+                selected: im::HashSet::new(),
+                decode_range: (0, 0),
+                flag_filter: FlagFilter::All,
+                recent_only: false,
+                zoom: 1.,
+                pan: Vec2::ZERO,
+                settings: state.settings.clone(),
+                slideshow: false,
+                playing: false,
+                compare_image: None,
+                flicker: false,
+                flicker_showing_compare: false,
+                last_viewed: None,
+                peeking: false,
+                undo_stack: Vector::new(),
+                goto_input: None,
+                goto_error: None,
+                caption_input: None,
+                confirm_trash: None,
+                toast: None,
+                folder_count: state.all_images.len(),
+                requested_folder: None,
+                show_raw: false,
+                displayed_size: Size::ZERO,
+                fit_scale: 1.,
+                blanked: false,
+                eyedropper: false,
+                picked_color: None,
+                loupe: None,
+                show_palette: false,
+                palette: None,
+                details_sort: None,
+                straighten: None,
+                straighten_line: None,
+                transition: 1.,
+            }
+        }
+    }
+
+    pub fn select_image(&mut self, idx: usize) {
+        if idx != self.selected_image {
+            self.last_viewed = self.paths.get(self.selected_image).cloned();
+        }
+        self.peeking = false;
+        self.selected_image = idx;
+        self.transition = 1.;
+        self.reset_zoom();
+    }
+
+    pub fn is_selected(&self, idx: usize) -> bool {
+        self.paths
+            .get(idx)
+            .map_or(false, |path| self.selected.contains(path))
+    }
+
+    pub fn toggle_selected(&mut self, idx: usize) {
+        if let Some(path) = self.paths.get(idx).cloned() {
+            if self.selected.remove(&path).is_none() {
+                self.selected.insert(path);
+            }
+        }
+    }
+
+    // selects every image the gallery shows, ones hidden by the filter are
+    // left as they were
+    pub fn select_all(&mut self) {
+        for idx in self.filtered_images() {
+            self.selected.insert(self.paths[idx].clone());
+        }
+    }
+
+    pub fn deselect_all(&mut self) {
+        self.selected.clear();
+    }
+
+    // flips the selection of every image the gallery shows
+    pub fn invert_selection(&mut self) {
+        for idx in self.filtered_images() {
+            self.toggle_selected(idx);
+        }
+    }
+
+    // indices of the images the gallery shows with the current filter
+    pub fn filtered_images(&self) -> Vec<usize> {
+        let mut images: Vec<usize> = self
+            .images
+            .iter()
+            .enumerate()
+            .filter(|(_, thumbnail)| self.flag_filter.matches(thumbnail.flag))
+            .filter(|(_, thumbnail)| {
+                !self.recent_only || self.is_recent(thumbnail)
+            })
+            .map(|(idx, _)| idx)
+            .collect();
+        if self.recent_only {
+            images.sort_by_key(|idx| Reverse(self.images[*idx].modified));
+        }
+        // images without a known date go after the rest
+        if self.groups_by_date() {
+            images.sort_by_key(|idx| Reverse(details_date(&self.images[*idx])));
+        }
+        if let Some((column, descending)) =
+            self.details_sort.filter(|_| self.settings.details_gallery)
+        {
+            images.sort_by(|a, b| {
+                let order = self.compare_details(*a, *b, column);
+                if descending {
+                    order.reverse()
+                } else {
+                    order
+                }
+            });
+        }
+        images
+    }
+
+    // the details and name layouts keep their own order and aren't split
+    // into sections
+    pub fn groups_by_date(&self) -> bool {
+        self.settings.date_grouping != DateGrouping::Off
+            && !self.settings.details_gallery
+            && !self.settings.list_mode
+    }
+
+    // the filtered images split into runs taken on the same day, month or
+    // year, each with its heading
+    pub fn date_groups(&self) -> Vec<(String, Vec<usize>)> {
+        let grouping = self.settings.date_grouping;
+        let mut groups: Vec<(String, Vec<usize>)> = Vec::new();
+        let mut last_key = None;
+        for idx in self.filtered_images() {
+            let date = details_date(&self.images[idx]);
+            let key = date.map(|date| format::date_group_key(date, grouping));
+            if groups.is_empty() || key != last_key {
+                let heading = match date {
+                    Some(date) => format::date_heading(
+                        date,
+                        grouping,
+                        self.settings.number_locale,
+                    ),
+                    None => "Unknown Date".to_string(),
+                };
+                groups.push((heading, Vec::new()));
+                last_key = key;
+            }
+            groups.last_mut().unwrap().1.push(idx);
+        }
+        groups
+    }
+
+    fn date_group_tiles(&self) -> Vec<DateGroup> {
+        self.date_groups()
+            .into_iter()
+            .map(|(heading, images)| DateGroup {
+                heading,
+                tiles: images
+                    .into_iter()
+                    .map(|i| {
+                        let highlighted = self.highlighted_image == Some(i);
+                        let selected = self.is_selected(i);
+                        (self.images[i].clone(), i, highlighted, selected)
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    fn compare_details(
+        &self,
+        a: usize,
+        b: usize,
+        column: DetailsColumn,
+    ) -> Ordering {
+        let (first, second) = (&self.images[a], &self.images[b]);
+        match column {
+            DetailsColumn::Name => {
+                let name = |idx: usize| {
+                    self.paths[idx]
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default()
+                };
+                natural_order::compare(&name(a), &name(b))
+            }
+            DetailsColumn::Dimensions => {
+                let pixels = |thumbnail: &Thumbnail| {
+                    thumbnail
+                        .dimensions
+                        .map(|(width, height)| width as u64 * height as u64)
+                };
+                pixels(first).cmp(&pixels(second))
+            }
+            DetailsColumn::Size => first.file_size.cmp(&second.file_size),
+            DetailsColumn::Date => {
+                details_date(first).cmp(&details_date(second))
+            }
+        }
+    }
+
+    // sorts the details view by the column, picking the same column again
+    // reverses the order
+    pub fn sort_details_by(&mut self, column: DetailsColumn) {
+        self.details_sort = match self.details_sort {
+            Some((current, descending)) if current == column => {
+                Some((column, !descending))
+            }
+            _ => Some((column, false)),
+        };
+    }
+
+    fn is_recent(&self, thumbnail: &Thumbnail) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.as_secs() as i64);
+        let cutoff = now - self.settings.recent_days as i64 * 86400;
+        thumbnail
+            .modified
+            .map_or(false, |modified| modified >= cutoff)
+    }
+
+    // indices of the images whose thumbnails should be decoded
+    pub fn decode_indices(&self) -> Vec<usize> {
+        if self.settings.list_mode {
+            return Vec::new();
+        }
+        let filtered = self.filtered_images();
+        let (start, end) = self.decode_range;
+        let end = end.min(filtered.len());
+        filtered[start.min(end)..end].to_vec()
+    }
+
+    // sets the flag on the selected image, or clears it if it's already set,
+    // and saves the flags of the image's folder
+    pub fn toggle_flag(&mut self, flag: Flag) {
+        let idx = self.selected_image;
+        if idx >= self.images.len() {
+            return;
+        }
+        if self.images[idx].flag == flag {
+            self.set_flag(idx, Flag::None);
+        } else {
+            self.set_flag(idx, flag);
+        }
+    }
+
+    // gives the selected image the flag and rating of the image before it,
+    // for culling a burst of shots the same way. Returns what to tell the
+    // user
+    pub fn copy_previous_flag(&mut self) -> String {
+        let idx = self.selected_image;
+        let previous = self.step_in_scope(1, false);
+        if idx >= self.images.len() || previous == idx {
+            return "There's no previous image to copy from".into();
+        }
+        let (flag, rating) =
+            (self.images[previous].flag, self.images[previous].rating);
+        let (old_flag, old_rating) =
+            (self.images[idx].flag, self.images[idx].rating);
+        self.set_flag(idx, flag);
+        self.set_rating(idx, rating);
+        self.push_undo(UndoEntry::Flag {
+            path: self.paths[idx].clone(),
+            flag: old_flag,
+            rating: old_rating,
+        });
+        let flag = match flag {
+            Flag::None => "Unflagged".to_string(),
+            flag => format!("Flagged {}", flag.as_str()),
+        };
+        let rating = match rating {
+            0 => "unrated".to_string(),
+            1 => "1 star".to_string(),
+            stars => format!("{} stars", stars),
+        };
+        format!("{}, {} like the previous image", flag, rating)
+    }
+
+    // rates the selected image, which moves on to the next one when rating
+    // advances. Stays on the last image instead of leaving the folder
+    pub fn rate_selected_image(&mut self, rating: u8) {
+        let idx = self.selected_image;
+        if idx >= self.images.len() {
+            return;
+        }
+        self.set_rating(idx, rating);
+        if self.settings.advance_on_rate && !self.is_last_image() {
+            self.next_image(1);
+        }
+    }
+
+    fn set_rating(&mut self, idx: usize, rating: u8) {
+        self.images[idx].rating = rating;
+        sidecar::save_rating(&self.paths[idx], rating);
+    }
+
+    // sets the caption of the shown image, an empty one removes it
+    pub fn caption_displayed_image(&mut self, caption: &str) {
+        let idx = self.displayed_image();
+        if idx >= self.images.len() {
+            return;
+        }
+        let caption = caption.trim();
+        self.images[idx].caption = if caption.is_empty() {
+            None
+        } else {
+            Some(Arc::new(caption.to_string()))
+        };
+        let caption = self.images[idx].caption.as_deref();
+        sidecar::save_caption(&self.paths[idx], caption.map(String::as_str));
+    }
+
+    fn set_flag(&mut self, idx: usize, flag: Flag) {
+        self.images[idx].flag = flag;
+        sidecar::save_flag(&self.paths[idx], flag);
+    }
+
+    // finds an image by its 1-based number in the folder, by the start of
+    // its file name, or by a part of its caption
+    pub fn find_image(&self, query: &str) -> Option<usize> {
+        let query = query.trim();
+        if query.is_empty() {
+            return None;
+        }
+        if let Ok(number) = query.parse::<usize>() {
+            return if number >= 1 && number <= self.paths.len() {
+                Some(number - 1)
+            } else {
+                None
+            };
+        }
+        let query = query.to_lowercase();
+        let file_name = |path: &Arc<PathBuf>| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().to_lowercase())
+                .unwrap_or_default()
+        };
+        self.paths
+            .iter()
+            .position(|path| file_name(path) == query)
+            .or_else(|| {
+                self.paths
+                    .iter()
+                    .position(|path| file_name(path).starts_with(&query))
+            })
+            .or_else(|| {
+                self.images.iter().position(|thumbnail| {
+                    thumbnail.caption.as_ref().map_or(false, |caption| {
+                        caption.to_lowercase().contains(&query)
+                    })
+                })
+            })
+    }
+
+    // whether the selected image was downloaded from a url rather than being
+    // one of the user's files
+    pub fn shows_download(&self) -> bool {
+        self.paths
+            .get(self.selected_image)
+            .map_or(false, |path| remote::is_download(path))
+    }
+
+    // moves the selected image to the trash, keeping it so it can be undone
+    pub fn trash_selected_image(&mut self) {
+        let idx = self.selected_image;
+        if idx >= self.paths.len() || self.shows_download() {
+            return;
+        }
+        let path = self.paths[idx].clone();
+        let trashed = match file_ops::move_to_trash(&path) {
+            Ok(trashed) => trashed,
+            Err(err) => {
+                error!("Error moving {} to trash: {}", path.display(), err);
+                return;
+            }
+        };
+        let thumbnail = self.remove_image(idx);
+        self.push_undo(UndoEntry::File {
+            operation: FileOperation::Trashed {
+                original: path.clone(),
+                trashed: Arc::new(trashed),
+            },
+            folder: self.name.clone(),
+            index: idx,
+            path,
+            thumbnail,
+        });
+    }
+
+    fn push_undo(&mut self, entry: UndoEntry) {
+        self.undo_stack.push_back(entry);
+        if self.undo_stack.len() > UNDO_LIMIT {
+            self.undo_stack.pop_front();
+        }
+    }
+
+    // reverses the last file operation, putting the image back where it
+    // was, or the last copied flag
+    pub fn undo(&mut self) {
+        let entry = match self.undo_stack.pop_back() {
+            Some(entry) => entry,
+            None => return,
+        };
+        match entry {
+            UndoEntry::File {
+                operation,
+                folder,
+                index,
+                path,
+                thumbnail,
+            } => {
+                if let Err(err) = file_ops::undo(&operation) {
+                    error!("Error undoing {:?}: {}", operation, err);
+                    return;
+                }
+                // the file is back on disk either way, but images from
+                // another folder only show up there after a rescan
+                if folder == self.name {
+                    let idx = index.min(self.paths.len());
+                    self.insert_image(idx, path, thumbnail);
+                    self.select_image(idx);
+                }
+            }
+            // the flag is only put back while its image is in the gallery
+            UndoEntry::Flag { path, flag, rating } => {
+                if let Some(idx) = self.paths.index_of(&path) {
+                    self.set_flag(idx, flag);
+                    self.set_rating(idx, rating);
+                    self.select_image(idx);
+                }
+            }
+        }
+    }
+
+    fn remove_image(&mut self, idx: usize) -> Thumbnail {
+        self.paths.remove(idx);
+        let thumbnail = self.images.remove(idx);
+        self.reindex_images();
+        if self.paths.is_empty() {
+            self.views = vector![FolderView::Folder];
+        } else if self.selected_image >= self.paths.len() {
+            self.select_image(self.paths.len() - 1);
+        }
+        thumbnail
+    }
+
+    fn insert_image(
+        &mut self,
+        idx: usize,
+        path: Arc<PathBuf>,
+        thumbnail: Thumbnail,
+    ) {
+        self.paths.insert(idx, path);
+        self.images.insert(idx, thumbnail);
+        self.reindex_images();
+    }
+
+    // positions shift when images are removed or inserted, so anything
+    // pointing at an index is reset
+    fn reindex_images(&mut self) {
+        for (index, thumbnail) in self.images.iter_mut().enumerate() {
+            thumbnail.index = index;
+        }
+        self.highlighted_image = None;
+        self.compare_image = None;
+        self.flicker = false;
+    }
+
+    // swaps in what a rescan found, keeping the thumbnails of files that
+    // didn't change and the selected image when it's still there. Returns
+    // what changed, to be shown to the user
+    pub fn replace_images(
+        &mut self,
+        thumbnails: Vector<Thumbnail>,
+        paths: Vector<Arc<PathBuf>>,
+    ) -> String {
+        let old: HashMap<Arc<PathBuf>, usize> = self
+            .paths
+            .iter()
+            .cloned()
+            .enumerate()
+            .map(|(idx, path)| (path, idx))
+            .collect();
+        let new: HashSet<&Arc<PathBuf>> = paths.iter().collect();
+        let added =
+            paths.iter().filter(|path| !old.contains_key(*path)).count();
+        let removed =
+            self.paths.iter().filter(|path| !new.contains(path)).count();
+        let selected = self.paths.get(self.selected_image).cloned();
+        let images = thumbnails
+            .into_iter()
+            .zip(paths.iter())
+            .map(|(thumbnail, path)| match old.get(path) {
+                Some(idx)
+                    if self.images[*idx].modified == thumbnail.modified =>
+                {
+                    self.images[*idx].clone()
+                }
+                // the file changed on disk, so what was decoded from it
+                // before is stale
+                Some(_) => {
+                    for kind in [
+                        CacheKind::Thumbnail,
+                        CacheKind::LinearThumbnail,
+                        CacheKind::FullImage,
+                    ]
+                    .iter()
+                    {
+                        memory_cache::remove(*kind, path);
+                    }
+                    thumbnail
+                }
+                None => thumbnail,
+            })
+            .collect();
+        self.images = images;
+        self.paths = paths;
+        self.reindex_images();
+        let selected = selected.and_then(|path| self.paths.index_of(&path));
+        if self.paths.is_empty() {
+            self.views = vector![FolderView::Folder];
+            self.select_image(0);
+        } else {
+            self.selected_image = selected
+                .unwrap_or(self.selected_image.min(self.paths.len() - 1));
+        }
+        let locale = self.settings.number_locale;
+        format!(
+            "Rescanned the folder, {} added and {} removed",
+            format::count(added, locale),
+            format::count(removed, locale)
+        )
+    }
+
+    // the image that is actually on screen, which is the compare image for
+    // half of the time while flickering and the last viewed one while
+    // peeking at it
+    pub fn displayed_image(&self) -> usize {
+        if let Some(idx) = self
+            .last_viewed
+            .as_ref()
+            .filter(|_| self.peeking)
+            .and_then(|path| self.paths.index_of(path))
+        {
+            return idx;
+        }
+        match self.compare_image {
+            Some(idx)
+                if self.flicker
+                    && self.flicker_showing_compare
+                    && idx < self.paths.len() =>
+            {
+                idx
             }
+            _ => self.selected_image,
         }
     }
+
+    // file of the image on screen, which is the RAW file of a pair when
+    // those are being shown
+    pub fn displayed_path(&self) -> Option<Arc<PathBuf>> {
+        let idx = self.displayed_image();
+        let raw = self
+            .images
+            .get(idx)
+            .and_then(|thumbnail| thumbnail.raw_pair.clone())
+            .filter(|_| self.show_raw);
+        raw.or_else(|| self.paths.get(idx).cloned())
+    }
+
+    // copies the displayed image into the favorites folder on another
+    // thread, what to tell the user about it comes back with SHOW_TOAST
+    pub fn copy_to_favorites(&self, ctx: &mut druid::EventCtx) {
+        let folder = match &self.settings.favorites_folder {
+            Some(folder) => folder.clone(),
+            None => {
+                ctx.submit_command(SHOW_TOAST.with(
+                    "Choose a favorites folder in Preferences".to_string(),
+                ));
+                return;
+            }
+        };
+        let path = match self.displayed_path() {
+            Some(path) => path,
+            None => return,
+        };
+        let handle = ctx.get_external_handle();
+        let job = WriteJob::start();
+        std::thread::spawn(move || {
+            let _job = job;
+            let message = match file_ops::copy_to_folder(&path, &folder) {
+                Ok(copy) => format!(
+                    "Copied to favorites as {}",
+                    copy.file_name().unwrap().to_string_lossy()
+                ),
+                Err(err) => {
+                    error!(
+                        "Error copying {} to favorites: {}",
+                        path.display(),
+                        err
+                    );
+                    format!("Couldn't copy to favorites: {}", err)
+                }
+            };
+            let _ = handle.submit_command(SHOW_TOAST, message, Target::Auto);
+        });
+    }
+
+    // puts the path of the folder on the clipboard, returning what to tell
+    // the user about it
+    pub fn copy_folder_path(&self) -> String {
+        let path = self.name.to_string_lossy().to_string();
+        Application::global().clipboard().put_string(&path);
+        format!("Copied {}", path)
+    }
+
+    // puts the detail of the image at idx on the clipboard, returning what to
+    // tell the user about it
+    pub fn copy_image_detail(&self, idx: usize, detail: CopyDetail) -> String {
+        let (path, thumbnail) =
+            match (self.paths.get(idx), self.images.get(idx)) {
+                (Some(path), Some(thumbnail)) => (path, thumbnail),
+                _ => {
+                    return "The image is no longer in the gallery".to_string()
+                }
+            };
+        let text = match detail {
+            CopyDetail::Path => Some(path.to_string_lossy().to_string()),
+            CopyDetail::FileName => path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string()),
+            CopyDetail::Dimensions => thumbnail
+                .dimensions
+                .map(|(width, height)| format!("{}x{}", width, height)),
+            CopyDetail::Exif => metadata::exif_text(path),
+            CopyDetail::Location => {
+                thumbnail.location.map(|(latitude, longitude)| {
+                    format!("{:.6}, {:.6}", latitude, longitude)
+                })
+            }
+        };
+        match text {
+            Some(text) => {
+                Application::global().clipboard().put_string(&text);
+                format!("Copied the {}", detail.label())
+            }
+            None => format!("The image has no {} to copy", detail.label()),
+        }
+    }
+
+    // one line about the displayed image for the status bar
+    pub fn status_text(&self) -> String {
+        let idx = self.displayed_image();
+        let name = self
+            .displayed_path()
+            .and_then(|path| {
+                path.file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+            })
+            .unwrap_or_default();
+        let locale = self.settings.number_locale;
+        let mut parts = vec![
+            name,
+            format!(
+                "{}/{}",
+                format::count(idx + 1, locale),
+                format::count(self.paths.len(), locale)
+            ),
+            format!(
+                "{} × {}",
+                format::count(self.displayed_size.width as usize, locale),
+                format::count(self.displayed_size.height as usize, locale)
+            ),
+            format!(
+                "{}%",
+                format::count(
+                    (self.fit_scale * self.zoom * 100.).round() as usize,
+                    locale
+                )
+            ),
+        ];
+        if let Some(captured) = self
+            .images
+            .get(idx)
+            .and_then(|thumbnail| thumbnail.captured)
+        {
+            parts.push(format::date(captured, locale));
+        }
+        match self.images.get(idx).map(|thumbnail| thumbnail.flag) {
+            Some(Flag::Pick) => parts.push("Picked".to_string()),
+            Some(Flag::Reject) => parts.push("Rejected".to_string()),
+            _ => (),
+        }
+        match self.images.get(idx).map(|thumbnail| thumbnail.rating) {
+            Some(0) | None => (),
+            Some(rating) => parts.push("★".repeat(rating as usize)),
+        }
+        if let Some((x, y, (r, g, b))) = self.picked_color {
+            parts.push(format!(
+                "{}, {}: rgb({}, {}, {}) #{:02X}{:02X}{:02X}",
+                x, y, r, g, b, r, g, b
+            ));
+        }
+        parts.join("    ")
+    }
+
+    // moves the slideshow to the next image, starting over after the last
+    pub fn advance_slideshow(&mut self) {
+        if self.paths.is_empty() {
+            return;
+        }
+        // the frames of a sequence go by like playback until its last one
+        if self.slideshow_in_sequence()
+            && self.selected_image < self.sequence_range().1
+        {
+            self.selected_image += 1;
+            self.transition = 1.;
+            return;
+        }
+        let next = if self.is_last_image() {
+            self.navigation_scope()[0]
+        } else {
+            self.step_in_scope(1, true)
+        };
+        self.select_image(next);
+        if self.settings.slideshow_transition != SlideshowTransition::None {
+            self.transition = 0.;
+        }
+    }
+
+    // the images the slideshow shows next, up to the prefetch setting and
+    // only as many as fit in half the memory cache so they don't push out
+    // the one being shown
+    pub fn slideshow_prefetch_paths(&self) -> Vec<PathBuf> {
+        let scope = self.navigation_scope();
+        let pos = match scope.iter().position(|idx| *idx == self.selected_image)
+        {
+            Some(pos) => pos,
+            None => return Vec::new(),
+        };
+        let count = self.settings.slideshow_prefetch.min(scope.len() - 1);
+        let mut room = self.settings.cache_budget_mb * 1024 * 1024 / 2;
+        let mut paths = Vec::new();
+        // the slideshow wraps around to the start after the last image
+        for idx in scope.iter().cycle().skip(pos + 1).take(count) {
+            let bytes = self.images[*idx]
+                .dimensions
+                .map_or(0, |(width, height)| width as usize * height as usize)
+                * 3;
+            if bytes > room {
+                break;
+            }
+            room -= bytes;
+            paths.push(self.paths[*idx].as_ref().clone());
+        }
+        paths
+    }
+
+    // the metadata caption for the shown image, once its EXIF has been read,
+    // or the caption the user wrote for it
+    pub fn caption_text(&self) -> Option<String> {
+        if !self.settings.show_caption {
+            return None;
+        }
+        if self.settings.caption_content == CaptionContent::Description {
+            let thumbnail = self.images.get(self.displayed_image())?;
+            return thumbnail
+                .caption
+                .as_ref()
+                .map(|caption| caption.to_string());
+        }
+        let path = self.displayed_path()?;
+        metadata::cached_exif_summary(path)?
+            .caption(self.settings.caption_content)
+    }
+
+    // first and last index of the run of numbered frames, like frame001.png
+    // to frame240.png, that the current image belongs to, or the whole
+    // folder when it isn't part of one
+    pub fn sequence_range(&self) -> (usize, usize) {
+        let last = self.paths.len().saturating_sub(1);
+        let key = match self
+            .paths
+            .get(self.selected_image)
+            .and_then(|path| sequence_key(path))
+        {
+            Some(key) => key,
+            None => return (0, last),
+        };
+        let in_sequence =
+            |idx: usize| sequence_key(&self.paths[idx]).as_ref() == Some(&key);
+        let mut start = self.selected_image;
+        while start > 0 && in_sequence(start - 1) {
+            start -= 1;
+        }
+        let mut end = self.selected_image;
+        while end < last && in_sequence(end + 1) {
+            end += 1;
+        }
+        (start, end)
+    }
+
+    // whether the slideshow is playing through the numbered sequence the
+    // current image is part of rather than showing it for the whole interval
+    pub fn slideshow_in_sequence(&self) -> bool {
+        if !self.settings.slideshow_plays_sequences {
+            return false;
+        }
+        let in_sequence = self
+            .paths
+            .get(self.selected_image)
+            .and_then(|path| sequence_key(path))
+            .is_some();
+        let (start, end) = self.sequence_range();
+        in_sequence && start < end
+    }
+
+    // shows the next frame of the sequence, looping back to its first, and
+    // keeps the zoom so a detail can be watched
+    pub fn advance_playback(&mut self) {
+        if self.paths.is_empty() {
+            return;
+        }
+        let (start, end) = self.sequence_range();
+        self.selected_image = if self.selected_image >= end {
+            start
+        } else {
+            self.selected_image + 1
+        };
+        self.transition = 1.;
+    }
+
+    // indices the arrows move through in order. A combined gallery only
+    // goes through the images from the same folder as the shown one when
+    // navigation is kept within folders
+    fn navigation_scope(&self) -> Vec<usize> {
+        let folder = self
+            .paths
+            .get(self.selected_image)
+            .and_then(|path| path.parent())
+            .filter(|_| self.settings.navigate_within_folder);
+        (0..self.paths.len())
+            .filter(|idx| {
+                folder.is_none() || self.paths[*idx].parent() == folder
+            })
+            .collect()
+    }
+
+    // the image step images away in the navigation scope, stopping at its
+    // ends
+    fn step_in_scope(&self, step: usize, forward: bool) -> usize {
+        let scope = self.navigation_scope();
+        let pos = match scope.iter().position(|idx| *idx == self.selected_image)
+        {
+            Some(pos) => pos,
+            None => return self.selected_image,
+        };
+        let pos = if forward {
+            (pos + step).min(scope.len() - 1)
+        } else {
+            pos.saturating_sub(step)
+        };
+        scope[pos]
+    }
+
+    pub fn is_first_image(&self) -> bool {
+        self.paths.is_empty()
+            || self.navigation_scope().first() == Some(&self.selected_image)
+    }
+
+    pub fn is_last_image(&self) -> bool {
+        self.paths.is_empty()
+            || self.navigation_scope().last() == Some(&self.selected_image)
+    }
+
+    pub fn previous_image(&mut self, step: usize) {
+        // moving to another image brings back a blanked screen, and steps
+        // through a sequence frame by frame
+        self.blanked = false;
+        self.playing = false;
+        if self.is_first_image() {
+            return;
+        }
+        self.select_image(self.step_in_scope(step, false));
+    }
+
+    // whether moving forward does anything from the current image
+    pub fn has_next_image(&self) -> bool {
+        match self.settings.folder_end_action {
+            FolderEndAction::Stop => !self.is_last_image(),
+            FolderEndAction::Wrap => !self.paths.is_empty(),
+            FolderEndAction::NextFolder => {
+                !self.is_last_image() || self.next_folder().is_some()
+            }
+        }
+    }
+
+    fn next_folder(&self) -> Option<usize> {
+        self.selected_folder
+            .map(|idx| idx + 1)
+            .filter(|idx| *idx < self.folder_count)
+    }
+
+    fn previous_folder(&self) -> Option<usize> {
+        self.selected_folder.and_then(|idx| idx.checked_sub(1))
+    }
+
+    // moves on to the folder after this one in the library's order, or the
+    // one before it. Returns false when there's no folder that way
+    pub fn open_adjacent_folder(&mut self, forward: bool) -> bool {
+        let folder = if forward {
+            self.next_folder()
+        } else {
+            self.previous_folder()
+        };
+        if folder.is_some() {
+            self.playing = false;
+            self.requested_folder = folder;
+        }
+        folder.is_some()
+    }
+
+    pub fn next_image(&mut self, step: usize) {
+        self.blanked = false;
+        self.playing = false;
+        if self.is_last_image() {
+            match self.settings.folder_end_action {
+                FolderEndAction::Stop => (),
+                FolderEndAction::Wrap if !self.paths.is_empty() => {
+                    self.select_image(self.navigation_scope()[0])
+                }
+                FolderEndAction::Wrap => (),
+                FolderEndAction::NextFolder => {
+                    self.requested_folder = self.next_folder()
+                }
+            }
+            return;
+        }
+        self.select_image(self.step_in_scope(step, true));
+    }
+
+    // a tall image zoomed in to be read from top to bottom, which the up and
+    // down keys page through
+    pub fn reads_tall_image(&self) -> bool {
+        let size = self.displayed_size;
+        self.settings.scroll_tall_images
+            && self.zoom > 1.
+            && size.width > 0.
+            && size.height / size.width >= TALL_IMAGE_ASPECT_RATIO
+    }
+
+    pub fn reset_zoom(&mut self) {
+        self.zoom = 1.;
+        self.pan = Vec2::ZERO;
+    }
+
+    pub fn zoom_transform(&self, size: Size) -> Affine {
+        let slide = match self.settings.slideshow_transition {
+            SlideshowTransition::SlideLeft => {
+                Vec2::new(size.width * (1. - self.transition), 0.)
+            }
+            _ => Vec2::ZERO,
+        };
+        // straightening turns the image about the middle of the view
+        let rotation = match self.straighten {
+            Some(angle) => {
+                let center = size.to_vec2() / 2.;
+                Affine::translate(center)
+                    * Affine::rotate(angle)
+                    * Affine::translate(-center)
+            }
+            None => Affine::IDENTITY,
+        };
+        Affine::translate(self.pan + slide)
+            * Affine::scale(self.zoom)
+            * rotation
+    }
 }
 
 impl ViewController<FolderView> for FolderGalleryState {
@@ -87,7 +1329,41 @@ pub enum FolderView {
 }
 impl View for FolderView {}
 
-pub struct FolderViewController;
+// shown when there's no folder to move on to
+pub fn end_of_library(forward: bool) -> String {
+    if forward {
+        "This is the last folder".to_string()
+    } else {
+        "This is the first folder".to_string()
+    }
+}
+
+// a link each platform's own maps app opens
+fn map_url(latitude: f64, longitude: f64) -> String {
+    if cfg!(target_os = "windows") {
+        format!("bingmaps:?collection=point.{}_{}", latitude, longitude)
+    } else if cfg!(target_os = "macos") {
+        format!("https://maps.apple.com/?ll={},{}", latitude, longitude)
+    } else {
+        format!("geo:{},{}", latitude, longitude)
+    }
+}
+
+pub struct FolderViewController {
+    // ids of the thumbnails that were sent off to be decoded and haven't
+    // been evicted
+    requested: HashSet<u64>,
+    toast_timer: TimerToken,
+}
+
+impl FolderViewController {
+    pub fn new() -> Self {
+        Self {
+            requested: HashSet::new(),
+            toast_timer: TimerToken::INVALID,
+        }
+    }
+}
 
 impl Controller<FolderGalleryState, Container<FolderGalleryState>>
     for FolderViewController
@@ -107,57 +1383,823 @@ impl Controller<FolderGalleryState, Container<FolderGalleryState>>
                 let (view, idx) =
                     selector.get_unchecked(PUSH_VIEW_WITH_SELECTED_IMAGE);
                 data.add_view(view.clone());
-                data.selected_image = *idx;
+                data.select_image(*idx);
+                data.highlighted_image = None;
             }
             Event::Command(cmd) if cmd.is(CREATED_THUMBNAIL) => {
                 let thumbnail = cmd.get_unchecked(CREATED_THUMBNAIL);
-                // TODO: this isn't workign correctly because this can be out of bounds
-                data.images[thumbnail.index] = thumbnail.clone();
+                // the image may have moved while decoding, and thumbnails
+                // that were scrolled away from are dropped
+                let idx = data
+                    .images
+                    .iter()
+                    .position(|image| image.id == thumbnail.id);
+                if let Some(idx) =
+                    idx.filter(|idx| data.decode_indices().contains(idx))
+                {
+                    // the capture time, flag, rating, caption and dimensions
+                    // are only read while scanning
+                    let current = &data.images[idx];
+                    data.images[idx] = Thumbnail {
+                        index: current.index,
+                        captured: current.captured,
+                        flag: current.flag,
+                        rating: current.rating,
+                        caption: current.caption.clone(),
+                        dimensions: current.dimensions,
+                        raw_pair: current.raw_pair.clone(),
+                        timed_out: current.timed_out,
+                        modified: current.modified,
+                        file_size: current.file_size,
+                        location: current.location,
+                        ..thumbnail.clone()
+                    };
+                }
+            }
+            Event::Command(cmd) if cmd.is(RETRY_TIMED_OUT_IMAGE) => {
+                let id = *cmd.get_unchecked(RETRY_TIMED_OUT_IMAGE);
+                if let Some(idx) =
+                    data.images.iter().position(|image| image.id == id)
+                {
+                    let path = data.paths[idx].clone();
+                    let timeout =
+                        Duration::from_secs_f64(data.settings.scan_timeout);
+                    let retries = data.settings.open_retries;
+                    let handle = ctx.get_external_handle();
+                    std::thread::spawn(move || {
+                        let mut probes = ProbeWorker::new(retries);
+                        match probes.probe(&path, timeout) {
+                            Ok(Some(probe)) => handle
+                                .submit_command(
+                                    PROBED_TIMED_OUT_IMAGE,
+                                    (id, probe),
+                                    Target::Auto,
+                                )
+                                .unwrap(),
+                            _ => error!(
+                                "Timed out reading {} again",
+                                path.display()
+                            ),
+                        }
+                    });
+                }
+            }
+            Event::Command(cmd) if cmd.is(PROBED_TIMED_OUT_IMAGE) => {
+                let (id, probe) = cmd.get_unchecked(PROBED_TIMED_OUT_IMAGE);
+                if let Some(thumbnail) =
+                    data.images.iter_mut().find(|image| image.id == *id)
+                {
+                    thumbnail.format = probe.format;
+                    thumbnail.captured = probe.captured;
+                    thumbnail.dimensions = probe.dimensions;
+                    thumbnail.modified = probe.modified;
+                    thumbnail.file_size = probe.file_size;
+                    thumbnail.location = probe.location;
+                    thumbnail.timed_out = false;
+                }
+                // lets the thumbnail get decoded now
+                self.requested.remove(id);
+            }
+            Event::Command(cmd) if cmd.is(RESCANNED_FOLDER) => {
+                let (folder, thumbnails, paths) =
+                    cmd.get_unchecked(RESCANNED_FOLDER);
+                if *folder == data.name {
+                    data.toast = Some(
+                        data.replace_images(thumbnails.clone(), paths.clone()),
+                    );
+                    self.toast_timer = ctx.request_timer(TOAST_DURATION);
+                }
+            }
+            Event::Command(cmd) if cmd.is(FAILED_THUMBNAIL) => {
+                let id = *cmd.get_unchecked(FAILED_THUMBNAIL);
+                if let Some(thumbnail) =
+                    data.images.iter_mut().find(|image| image.id == id)
+                {
+                    thumbnail.failed = true;
+                }
+            }
+            Event::Command(cmd) if cmd.is(REGENERATE_THUMBNAIL) => {
+                let id = *cmd.get_unchecked(REGENERATE_THUMBNAIL);
+                if let Some(idx) =
+                    data.images.iter().position(|image| image.id == id)
+                {
+                    data.images[idx].failed = false;
+                    let path = data.paths[idx].clone();
+                    let linear_light = data.settings.linear_thumbnails;
+                    let kind = if linear_light {
+                        CacheKind::LinearThumbnail
+                    } else {
+                        CacheKind::Thumbnail
+                    };
+                    memory_cache::remove(kind, &path);
+                    thumbnail_cache::remove(&path, linear_light);
+                    load_thumbnails(
+                        ctx.get_external_handle(),
+                        vec![(id, data.images[idx].index, path)],
+                        data.settings.auto_rotate,
+                        linear_light,
+                    );
+                }
+            }
+            Event::Command(cmd) if cmd.is(OPEN_LOCATION) => {
+                let (latitude, longitude) = *cmd.get_unchecked(OPEN_LOCATION);
+                let url = map_url(latitude, longitude);
+                if let Err(err) = open::that(&url) {
+                    error!("Error opening {}: {}", url, err);
+                }
+            }
+            Event::Command(cmd) if cmd.is(COPY_IMAGE_DETAIL) => {
+                let (idx, detail) = *cmd.get_unchecked(COPY_IMAGE_DETAIL);
+                data.toast = Some(data.copy_image_detail(idx, detail));
+                self.toast_timer = ctx.request_timer(TOAST_DURATION);
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(TOGGLE_IMAGE_SELECTED) => {
+                data.toggle_selected(*cmd.get_unchecked(TOGGLE_IMAGE_SELECTED));
+            }
+            Event::Command(cmd) if cmd.is(HIGHLIGHT_IMAGE) => {
+                let path = cmd.get_unchecked(HIGHLIGHT_IMAGE);
+                if let Some(idx) = data.paths.index_of(path) {
+                    while data.len() > 1 {
+                        data.pop_view();
+                    }
+                    data.select_image(idx);
+                    data.highlighted_image = Some(idx);
+                    ctx.request_focus();
+                }
             }
             Event::Command(selector) if selector.is(POP_FOLDER_VIEW) => {
                 // let view = selector.get_unchecked(POP_FOLDER_VIEW);
                 data.pop_view();
+                // the gallery scrolls to the image that was being viewed
+                if !data.paths.is_empty() {
+                    data.highlighted_image = Some(data.selected_image);
+                }
+                // focus was held by the image view that was just removed
+                ctx.request_focus();
+            }
+            // the back side button leaves the gallery like the back button
+            Event::MouseDown(mouse) if mouse.button == MouseButton::X1 => {
+                ctx.submit_command(Command::new(POP_VIEW, (), Target::Auto));
+                ctx.set_handled();
+                return;
             }
+            Event::Timer(token) if *token == self.toast_timer => {
+                data.toast = None;
+            }
+            Event::KeyDown(key_event) => match &key_event.key {
+                KbKey::Character(key)
+                    if key == "," && key_event.mods.ctrl() =>
+                {
+                    open_preferences(ctx);
+                }
+                KbKey::Character(key)
+                    if key.eq_ignore_ascii_case("n")
+                        && key_event.mods.ctrl() =>
+                {
+                    open_window(ctx);
+                }
+                // goes back to the image that was viewed last, or the one
+                // that is highlighted
+                KbKey::Tab if !data.paths.is_empty() => {
+                    let idx =
+                        data.highlighted_image.unwrap_or(data.selected_image);
+                    ctx.submit_command(Command::new(
+                        PUSH_VIEW_WITH_SELECTED_IMAGE,
+                        (FolderView::SingleImage, idx),
+                        Target::Auto,
+                    ));
+                }
+                KbKey::Character(key)
+                    if key.eq_ignore_ascii_case("c")
+                        && key_event.mods.ctrl()
+                        && key_event.mods.shift() =>
+                {
+                    data.toast = Some(data.copy_folder_path());
+                    self.toast_timer = ctx.request_timer(TOAST_DURATION);
+                }
+                // picks up files that were added, removed or changed on disk
+                KbKey::F5 => rescan_folder(
+                    data.name.clone(),
+                    ctx.get_external_handle(),
+                    data.settings.clone(),
+                ),
+                KbKey::Character(key)
+                    if key.eq_ignore_ascii_case("a")
+                        && key_event.mods.ctrl()
+                        && key_event.mods.shift() =>
+                {
+                    data.deselect_all();
+                }
+                KbKey::Character(key)
+                    if key.eq_ignore_ascii_case("a")
+                        && key_event.mods.ctrl() =>
+                {
+                    data.select_all();
+                }
+                KbKey::Character(key)
+                    if key.eq_ignore_ascii_case("i")
+                        && key_event.mods.ctrl() =>
+                {
+                    data.invert_selection();
+                }
+                KbKey::Character(key) if key.eq_ignore_ascii_case("t") => {
+                    data.settings.show_format_badges =
+                        !data.settings.show_format_badges;
+                }
+                KbKey::Character(key) if key.eq_ignore_ascii_case("d") => {
+                    data.settings.show_tile_dates =
+                        !data.settings.show_tile_dates;
+                }
+                KbKey::Character(key)
+                    if key.eq_ignore_ascii_case("z")
+                        && (key_event.mods.ctrl() || key_event.mods.meta()) =>
+                {
+                    data.undo();
+                }
+                KbKey::Character(key) if key.eq_ignore_ascii_case("p") => {
+                    data.toggle_flag(Flag::Pick);
+                }
+                KbKey::Character(key) if key.eq_ignore_ascii_case("x") => {
+                    data.toggle_flag(Flag::Reject);
+                }
+                _ => {
+                    if let Some(forward) =
+                        data.settings.folder_keys.direction(key_event)
+                    {
+                        if !data.open_adjacent_folder(forward) {
+                            data.toast = Some(end_of_library(forward));
+                            self.toast_timer =
+                                ctx.request_timer(TOAST_DURATION);
+                        }
+                    }
+                }
+            },
             _ => (),
         }
         child.event(ctx, event, data, env)
     }
+
+    fn lifecycle(
+        &mut self,
+        child: &mut Container<FolderGalleryState>,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &FolderGalleryState,
+        env: &Env,
+    ) {
+        // the gallery needs focus to receive key events
+        if let LifeCycle::WidgetAdded = event {
+            ctx.request_focus();
+        }
+        child.lifecycle(ctx, event, data, env)
+    }
+
+    fn update(
+        &mut self,
+        child: &mut Container<FolderGalleryState>,
+        ctx: &mut UpdateCtx,
+        old_data: &FolderGalleryState,
+        data: &FolderGalleryState,
+        env: &Env,
+    ) {
+        // thumbnails get decoded again the other way up, or at the other
+        // quality
+        if data.settings.auto_rotate != old_data.settings.auto_rotate
+            || data.settings.linear_thumbnails
+                != old_data.settings.linear_thumbnails
+        {
+            self.requested.clear();
+        }
+        // requests are tracked by image id, which stays the same when the
+        // folder changes or images are removed and put back
+        let wanted: Vec<(u64, usize, Arc<PathBuf>)> = data
+            .decode_indices()
+            .into_iter()
+            .filter(|idx| *idx < data.paths.len())
+            // timed out files would only stall a decoding thread
+            .filter(|idx| !data.images[*idx].timed_out)
+            .map(|idx| (data.images[idx].id, idx, data.paths[idx].clone()))
+            .collect();
+        self.requested
+            .retain(|id| wanted.iter().any(|(wanted, _, _)| wanted == id));
+        let paths: Vec<(u64, usize, Arc<PathBuf>)> = wanted
+            .into_iter()
+            .filter(|(id, _, _)| self.requested.insert(*id))
+            .collect();
+        if !paths.is_empty() {
+            load_thumbnails(
+                ctx.get_external_handle(),
+                paths,
+                data.settings.auto_rotate,
+                data.settings.linear_thumbnails,
+            );
+        }
+        child.update(ctx, old_data, data, env)
+    }
+}
+
+// size of a gallery tile including its padding
+const GALLERY_TILE_SIZE: f64 = 160.;
+// height of a row in the details view including its padding
+pub const DETAILS_ROW_HEIGHT: f64 = 44.;
+
+// a details row's thumbnail, path, index and whether it's highlighted and
+// selected
+pub type DetailsRow = (Thumbnail, Arc<PathBuf>, usize, bool, bool);
+// height of the date above each section of a dated gallery
+pub const DATE_HEADING_HEIGHT: f64 = 32.;
+
+// a section of a dated gallery, the images in it were taken on the same
+// day, month or year
+#[derive(Debug, Clone, Data)]
+pub struct DateGroup {
+    pub heading: String,
+    pub tiles: Vector<(Thumbnail, usize, bool, bool)>,
+}
+
+// keeps the decode range following the gallery's scroll position
+pub struct GalleryScrollController {
+    // rows of the masonry layout, which don't have a fixed height
+    masonry_rows: Rc<RefCell<JustifiedRows>>,
+    // the scroll offset and size the range was last worked out for
+    last_view: Option<(f64, Size)>,
+    // the images shown in order and how many are in each date group,
+    // filtering and grouping sorts every image so it's only done again
+    // once they change
+    shown: Option<(Vec<usize>, Vec<usize>)>,
 }
 
-impl GridIter<(Thumbnail, usize)> for FolderGalleryState {
-    fn for_each(&self, mut cb: impl FnMut(&(Thumbnail, usize), usize)) {
-        for (i, thumbnail) in self.images.iter().enumerate() {
-            cb(&(thumbnail.clone(), i), i);
+impl GalleryScrollController {
+    pub fn new(masonry_rows: Rc<RefCell<JustifiedRows>>) -> Self {
+        Self {
+            masonry_rows,
+            last_view: None,
+            shown: None,
+        }
+    }
+
+    fn shown(
+        &mut self,
+        data: &FolderGalleryState,
+    ) -> &(Vec<usize>, Vec<usize>) {
+        self.shown.get_or_insert_with(|| {
+            if data.groups_by_date() {
+                let groups = data.date_groups();
+                let lens = groups.iter().map(|(_, images)| images.len());
+                let lens = lens.collect();
+                let images = groups.into_iter().flat_map(|(_, images)| images);
+                (images.collect(), lens)
+            } else {
+                (data.filtered_images(), Vec::new())
+            }
+        })
+    }
+
+    // a new flag or date can move an image in or out of the filter or to
+    // another date group
+    fn shown_changed(
+        &self,
+        old_data: &FolderGalleryState,
+        data: &FolderGalleryState,
+    ) -> bool {
+        if data.flag_filter == FlagFilter::All
+            && !data.recent_only
+            && !data.groups_by_date()
+        {
+            return false;
+        }
+        if data.settings.recent_days != old_data.settings.recent_days
+            || data.settings.list_mode != old_data.settings.list_mode
+        {
+            return true;
+        }
+        !data.images.same(&old_data.images)
+            && data.images.iter().zip(old_data.images.iter()).any(
+                |(image, old_image)| {
+                    image.flag != old_image.flag
+                        || image.modified != old_image.modified
+                        || details_date(image) != details_date(old_image)
+                },
+            )
+    }
+
+    // the masonry layout only lays out the rows near the viewport, so it's
+    // laid out again once scrolling reaches rows further away
+    fn follow_viewport<W: Widget<FolderGalleryState>>(
+        &self,
+        scroll: &Scroll<FolderGalleryState, W>,
+        ctx: &mut druid::EventCtx,
+        data: &FolderGalleryState,
+    ) {
+        if !data.settings.masonry_gallery {
+            return;
+        }
+        let top = scroll.offset().y;
+        let bottom = top + ctx.size().height;
+        if self.masonry_rows.borrow_mut().scroll_to(top, bottom) {
+            ctx.request_layout();
+        }
+    }
+
+    fn update_decode_range<W: Widget<FolderGalleryState>>(
+        &mut self,
+        scroll: &Scroll<FolderGalleryState, W>,
+        viewport: Size,
+        data: &mut FolderGalleryState,
+    ) {
+        self.last_view = Some((scroll.offset().y, viewport));
+        let list_mode = data.settings.list_mode;
+        let (images, lens) = self.shown(data);
+        let len = images.len();
+        let lens = lens.clone();
+        let range = if data.settings.details_gallery {
+            details_decode_range(
+                scroll.offset().y,
+                viewport,
+                len,
+                data.settings.gallery_overscan,
+            )
+        } else if data.groups_by_date() {
+            dated_decode_range(
+                &lens,
+                scroll.offset().y,
+                viewport,
+                data.settings.gallery_overscan,
+            )
+        } else if data.settings.masonry_gallery {
+            masonry_decode_range(
+                &self.masonry_rows.borrow().rows,
+                scroll.offset().y,
+                viewport,
+                data.settings.gallery_overscan,
+            )
+        } else {
+            decode_range(
+                scroll.offset().y,
+                viewport,
+                len,
+                data.settings.gallery_overscan,
+            )
+        };
+        if range == data.decode_range {
+            return;
+        }
+        // the same images decode_indices gives, without filtering again
+        let in_range = |images: &[usize], (start, end): (usize, usize)| {
+            if list_mode {
+                return Vec::new();
+            }
+            let end = end.min(images.len());
+            images[start.min(end)..end].to_vec()
+        };
+        let images = &self.shown.as_ref().unwrap().0;
+        let old_indices = in_range(images, data.decode_range);
+        let indices = in_range(images, range);
+        data.decode_range = range;
+        // thumbnails that are no longer in range give their memory back
+        for idx in old_indices {
+            if !indices.contains(&idx) {
+                data.images[idx].image = ImageBuf::empty();
+            }
+        }
+    }
+}
+
+impl<W: Widget<FolderGalleryState>>
+    Controller<FolderGalleryState, Scroll<FolderGalleryState, W>>
+    for GalleryScrollController
+{
+    fn event(
+        &mut self,
+        child: &mut Scroll<FolderGalleryState, W>,
+        ctx: &mut druid::EventCtx,
+        event: &Event,
+        data: &mut FolderGalleryState,
+        env: &Env,
+    ) {
+        if let Event::Command(cmd) = event {
+            if cmd.is(UPDATE_DECODE_RANGE) {
+                self.shown = None;
+                self.update_decode_range(child, ctx.size(), data);
+                self.follow_viewport(child, ctx, data);
+                ctx.set_handled();
+                return;
+            }
+        }
+        child.event(ctx, event, data, env);
+        // most events don't scroll, the range only moves with the view
+        if self.last_view != Some((child.offset().y, ctx.size())) {
+            self.update_decode_range(child, ctx.size(), data);
+            self.follow_viewport(child, ctx, data);
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
+        child: &mut Scroll<FolderGalleryState, W>,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &FolderGalleryState,
+        env: &Env,
+    ) {
+        if let LifeCycle::Size(_) = event {
+            ctx.submit_command(Command::new(
+                UPDATE_DECODE_RANGE,
+                (),
+                ctx.widget_id(),
+            ));
+        }
+        child.lifecycle(ctx, event, data, env)
+    }
+
+    fn update(
+        &mut self,
+        child: &mut Scroll<FolderGalleryState, W>,
+        ctx: &mut UpdateCtx,
+        old_data: &FolderGalleryState,
+        data: &FolderGalleryState,
+        env: &Env,
+    ) {
+        if !data.name.same(&old_data.name)
+            || data.images.len() != old_data.images.len()
+            || data.flag_filter != old_data.flag_filter
+            || data.settings.masonry_gallery
+                != old_data.settings.masonry_gallery
+            || data.settings.details_gallery
+                != old_data.settings.details_gallery
+            || data.details_sort != old_data.details_sort
+            || data.settings.date_grouping != old_data.settings.date_grouping
+            || data.settings.gallery_overscan
+                != old_data.settings.gallery_overscan
+            || data.recent_only != old_data.recent_only
+            || self.shown_changed(old_data, data)
+        {
+            ctx.submit_command(Command::new(
+                UPDATE_DECODE_RANGE,
+                (),
+                ctx.widget_id(),
+            ));
+        }
+        child.update(ctx, old_data, data, env)
+    }
+}
+
+// the rows intersecting the viewport, widened by the overscan rows on both
+// sides
+fn decode_range(
+    offset: f64,
+    viewport: Size,
+    len: usize,
+    overscan: usize,
+) -> (usize, usize) {
+    let columns = ((viewport.width / GALLERY_TILE_SIZE) as usize).max(1);
+    let first_row = (offset / GALLERY_TILE_SIZE).floor() as usize;
+    let last_row =
+        ((offset + viewport.height) / GALLERY_TILE_SIZE).ceil() as usize;
+    let end = ((last_row + overscan) * columns).min(len);
+    let start = (first_row.saturating_sub(overscan) * columns).min(end);
+    (start, end)
+}
+
+// the details view has a single image in each row
+fn details_decode_range(
+    offset: f64,
+    viewport: Size,
+    len: usize,
+    overscan: usize,
+) -> (usize, usize) {
+    let first_row = (offset / DETAILS_ROW_HEIGHT).floor() as usize;
+    let last_row =
+        ((offset + viewport.height) / DETAILS_ROW_HEIGHT).ceil() as usize;
+    let end = (last_row + overscan).min(len);
+    let start = first_row.saturating_sub(overscan).min(end);
+    (start, end)
+}
+
+// every section of a dated gallery starts a new row of tiles below its
+// heading
+fn dated_decode_range(
+    group_lens: &[usize],
+    offset: f64,
+    viewport: Size,
+    overscan: usize,
+) -> (usize, usize) {
+    let columns = ((viewport.width / GALLERY_TILE_SIZE) as usize).max(1);
+    let margin = overscan as f64 * GALLERY_TILE_SIZE;
+    let (top, bottom) = (offset - margin, offset + viewport.height + margin);
+    let mut range = None;
+    let (mut y, mut first) = (0., 0);
+    for len in group_lens {
+        y += DATE_HEADING_HEIGHT;
+        for row in 0..(len + columns - 1) / columns {
+            let row_top = y + row as f64 * GALLERY_TILE_SIZE;
+            if row_top + GALLERY_TILE_SIZE >= top && row_top <= bottom {
+                let start = first + row * columns;
+                let end = (start + columns).min(first + len);
+                let (start, _) = range.unwrap_or((start, end));
+                range = Some((start, end));
+            }
+        }
+        y += ((len + columns - 1) / columns) as f64 * GALLERY_TILE_SIZE;
+        first += len;
+    }
+    range.unwrap_or((0, 0))
+}
+
+fn masonry_decode_range(
+    rows: &[JustifiedRow],
+    offset: f64,
+    viewport: Size,
+    overscan: usize,
+) -> (usize, usize) {
+    let first = rows.iter().position(|row| row.bottom >= offset);
+    let last = rows
+        .iter()
+        .rposition(|row| row.top <= offset + viewport.height);
+    match (first, last) {
+        (Some(first), Some(last)) => {
+            let first = first.saturating_sub(overscan);
+            let last = (last + overscan).min(rows.len() - 1);
+            (rows[first].start, rows[last].end)
+        }
+        _ => (0, 0),
+    }
+}
+
+// each tile gets its thumbnail, its index and whether it's highlighted and
+// selected
+impl GridIter<(Thumbnail, usize, bool, bool)> for FolderGalleryState {
+    fn for_each(
+        &self,
+        mut cb: impl FnMut(&(Thumbnail, usize, bool, bool), usize),
+    ) {
+        for (child, i) in self.filtered_images().into_iter().enumerate() {
+            let highlighted = self.highlighted_image == Some(i);
+            let selected = self.is_selected(i);
+            cb(&(self.images[i].clone(), i, highlighted, selected), child);
         }
     }
 
     fn for_each_mut(
         &mut self,
-        mut cb: impl FnMut(&mut (Thumbnail, usize), usize),
+        mut cb: impl FnMut(&mut (Thumbnail, usize, bool, bool), usize),
     ) {
-        for (i, thumbnail) in self.images.iter_mut().enumerate() {
-            cb(&mut (thumbnail.clone(), i), i);
+        for (child, i) in self.filtered_images().into_iter().enumerate() {
+            let highlighted = self.highlighted_image == Some(i);
+            let selected = self.is_selected(i);
+            cb(
+                &mut (self.images[i].clone(), i, highlighted, selected),
+                child,
+            );
         }
     }
 
     fn data_len(&self) -> usize {
-        self.images.len()
+        self.filtered_images().len()
     }
 
-    fn child_data(&self) -> Option<(Thumbnail, usize)> {
+    fn child_data(&self) -> Option<(Thumbnail, usize, bool, bool)> {
         match self.images.iter().next() {
-            Some(thumbnail) => Some((thumbnail.clone(), 0)),
+            Some(thumbnail) => Some((thumbnail.clone(), 0, false, false)),
             None => {
                 let thumbnail = Thumbnail {
+                    id: 0,
                     index: 0,
                     image: ImageBuf::empty(),
+                    format: FileFormat::Unknown,
+                    partial: false,
+                    captured: None,
+                    flag: Flag::None,
+                    rating: 0,
+                    caption: None,
+                    dimensions: None,
+                    raw_pair: None,
+                    timed_out: false,
+                    modified: None,
+                    file_size: None,
+                    location: None,
+                    failed: false,
                 };
-                Some((thumbnail, 0))
+                Some((thumbnail, 0, false, false))
             }
         }
     }
 }
 
+// the sections of a dated gallery
+impl ListIter<DateGroup> for FolderGalleryState {
+    fn for_each(&self, mut cb: impl FnMut(&DateGroup, usize)) {
+        for (child, group) in self.date_group_tiles().iter().enumerate() {
+            cb(group, child);
+        }
+    }
+
+    fn for_each_mut(&mut self, mut cb: impl FnMut(&mut DateGroup, usize)) {
+        for (child, mut group) in
+            self.date_group_tiles().into_iter().enumerate()
+        {
+            cb(&mut group, child);
+        }
+    }
+
+    fn data_len(&self) -> usize {
+        self.date_groups().len()
+    }
+}
+
+impl GridIter<(Thumbnail, usize, bool, bool)> for DateGroup {
+    fn for_each(
+        &self,
+        mut cb: impl FnMut(&(Thumbnail, usize, bool, bool), usize),
+    ) {
+        for (child, tile) in self.tiles.iter().enumerate() {
+            cb(tile, child);
+        }
+    }
+
+    fn for_each_mut(
+        &mut self,
+        mut cb: impl FnMut(&mut (Thumbnail, usize, bool, bool), usize),
+    ) {
+        for (child, tile) in self.tiles.iter_mut().enumerate() {
+            cb(tile, child);
+        }
+    }
+
+    fn data_len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    fn child_data(&self) -> Option<(Thumbnail, usize, bool, bool)> {
+        self.tiles.front().cloned()
+    }
+}
+
+// a details row also shows the file's name, so it gets the path along with
+// what a gallery tile gets
+impl ListIter<DetailsRow> for FolderGalleryState {
+    fn for_each(&self, mut cb: impl FnMut(&DetailsRow, usize)) {
+        for (child, i) in self.filtered_images().into_iter().enumerate() {
+            let highlighted = self.highlighted_image == Some(i);
+            let row = (
+                self.images[i].clone(),
+                self.paths[i].clone(),
+                i,
+                highlighted,
+                self.is_selected(i),
+            );
+            cb(&row, child);
+        }
+    }
+
+    fn for_each_mut(&mut self, mut cb: impl FnMut(&mut DetailsRow, usize)) {
+        for (child, i) in self.filtered_images().into_iter().enumerate() {
+            let highlighted = self.highlighted_image == Some(i);
+            let mut row = (
+                self.images[i].clone(),
+                self.paths[i].clone(),
+                i,
+                highlighted,
+                self.is_selected(i),
+            );
+            cb(&mut row, child);
+        }
+    }
+
+    fn data_len(&self) -> usize {
+        self.filtered_images().len()
+    }
+}
+
+// list mode tiles show the name where the details rows do, and open the
+// same context menu
+impl GridIter<DetailsRow> for FolderGalleryState {
+    fn for_each(&self, cb: impl FnMut(&DetailsRow, usize)) {
+        ListIter::<DetailsRow>::for_each(self, cb)
+    }
+
+    fn for_each_mut(&mut self, cb: impl FnMut(&mut DetailsRow, usize)) {
+        ListIter::<DetailsRow>::for_each_mut(self, cb)
+    }
+
+    fn data_len(&self) -> usize {
+        self.filtered_images().len()
+    }
+
+    fn child_data(&self) -> Option<DetailsRow> {
+        let (thumbnail, ..) =
+            GridIter::<(Thumbnail, usize, bool, bool)>::child_data(self)?;
+        let path = self
+            .paths
+            .iter()
+            .next()
+            .cloned()
+            .unwrap_or_else(|| Arc::new(PathBuf::new()));
+        Some((thumbnail, path, 0, false, false))
+    }
+}
+
 pub struct GalleryTransfer;
 
 impl ScopeTransfer for GalleryTransfer {
@@ -166,6 +2208,8 @@ impl ScopeTransfer for GalleryTransfer {
     type State = FolderGalleryState;
 
     fn read_input(&self, state: &mut Self::State, inner: &Self::In) {
+        state.settings = inner.settings.clone();
+        state.folder_count = inner.all_images.len();
         match inner.selected_folder {
             Some(idx) => {
                 if let Some(current_idx) = state.selected_folder {
@@ -176,6 +2220,13 @@ impl ScopeTransfer for GalleryTransfer {
                         state.name = folder.name.clone();
                         state.images = folder.thumbnails.clone();
                         state.paths = folder.paths.clone();
+                        state.highlighted_image = None;
+                        state.decode_range = (0, 0);
+                        state.compare_image = None;
+                        state.flicker = false;
+                        state.requested_folder = None;
+                        state.selected.clear();
+                        state.select_image(0);
                     }
                 } else {
                     let folder = &inner.all_images[idx];
@@ -193,77 +2244,1283 @@ impl ScopeTransfer for GalleryTransfer {
     }
 
     fn write_back_input(&self, state: &Self::State, inner: &mut Self::In) {
+        inner.settings = state.settings.clone();
         if let Some(idx) = state.selected_folder {
             inner.all_images[idx].name = state.name.clone();
             inner.all_images[idx].thumbnails = state.images.clone();
+            inner.all_images[idx].paths = state.paths.clone();
+            if let Some(next) = state.requested_folder {
+                inner.selected_folder = Some(next);
+            }
         } else {
             dbg!("This should do nothing because there is no state to write back.");
         }
     }
 }
+// pixels the eyedropper's loupe shows on each side of the one under the
+// cursor
+const LOUPE_RADIUS: i64 = 7;
+
+// when the image is scaled up past this factor, the displayed image switches
+// to nearest neighbor interpolation so individual pixels stay crisp
+const NEAREST_NEIGHBOR_SCALE_THRESHOLD: f64 = 2.0;
+// scales this close to 1 are treated as showing the image at actual size
+const ACTUAL_SIZE_TOLERANCE: f64 = 0.001;
+// images at least this many times wider than they are tall are treated as
+// panoramas
+const PANORAMA_ASPECT_RATIO: f64 = 2.5;
+// images at least this many times taller than they are wide are read by
+// scrolling down through them
+const TALL_IMAGE_ASPECT_RATIO: f64 = 2.5;
+// how much of the view a page moves by, the rest of the last page stays in
+// view to read on from
+const PAGE_SCROLL_FRACTION: f64 = 0.9;
+
+// incremented by every prefetch so an older one stops once the slideshow
+// has moved on
+static PREFETCH_GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+// decodes full images into the memory cache ahead of them being shown, once
+// nothing the user is waiting on is being decoded
+pub fn prefetch_full_images(paths: Vec<PathBuf>) {
+    let generation =
+        PREFETCH_GENERATION.fetch_add(1, atomic::Ordering::SeqCst) + 1;
+    std::thread::spawn(move || {
+        for path in paths {
+            background::wait_for_idle();
+            if PREFETCH_GENERATION.load(atomic::Ordering::SeqCst) != generation
+            {
+                return;
+            }
+            // getting it also keeps it from being the next one evicted
+            if memory_cache::get(CacheKind::FullImage, &path).is_some() {
+                continue;
+            }
+            match decode_lenient(&path) {
+                // partial images are read again when they're shown
+                Ok((image, false)) => memory_cache::insert(
+                    CacheKind::FullImage,
+                    &path,
+                    Arc::new(image),
+                ),
+                Ok((_image, true)) => (),
+                Err(err) => error!("Error reading {}: {}", path.display(), err),
+            }
+        }
+    });
+}
+
+// a decoded image along with the buffer the image widget draws
+struct DecodedImage {
+    path: PathBuf,
+    image: ImageBuf,
+    source: Arc<RgbImage>,
+    // the size of the image turned upright, the source is smaller when it's
+    // a cached render
+    size: (u32, u32),
+}
+
+impl DecodedImage {
+    // how many pixels of the source there are to one of the image
+    fn source_scale(&self) -> f64 {
+        if self.size.0 == 0 {
+            return 1.;
+        }
+        self.source.width() as f64 / self.size.0 as f64
+    }
+
+    // the pixel of the source that covers a pixel of the image
+    fn source_pixel(&self, (x, y): (u32, u32)) -> (u32, u32) {
+        let (width, height) = self.source.dimensions();
+        let scale = |pixel: u32, source: u32, size: u32| {
+            let scaled = pixel as u64 * source as u64 / size.max(1) as u64;
+            (scaled as u32).min(source.saturating_sub(1))
+        };
+        (scale(x, width, self.size.0), scale(y, height, self.size.1))
+    }
+}
+
+// the read image, its alpha channel and the size of the image it came from
+type ReadImage = (PathBuf, RgbImage, Option<GrayImage>, (u32, u32));
+
 pub struct DisplayImageController {
-    sender: SyncSender<RgbImage>,
-    receiver: Receiver<RgbImage>,
+    // the decoded image, and its alpha channel when it's needed and the
+    // image has one
+    sender: SyncSender<ReadImage>,
+    receiver: Receiver<ReadImage>,
+    image_size: Size,
+    widget_size: Size,
+    last_drag_pos: Option<Point>,
+    // the image being shown, kept for exporting the current view
+    current_image: Option<DecodedImage>,
+    // the image shown before the current one, so flickering between two
+    // images doesn't decode them over and over
+    previous_image: Option<DecodedImage>,
+    // follows the setting, fits portrait images to the height and landscape
+    // ones to the width instead of fitting them whole
+    adaptive_fit: bool,
+    // the straighten angle to save with when the save panel was opened for
+    // a straightened copy rather than the view
+    straighten_save: Option<f64>,
+    zoom_animation: Option<ZoomAnimation>,
+    // how far the wheel has turned towards the next or previous image since
+    // it last moved to one
+    wheel_distance: f64,
 }
+
+// how long an animated zoom takes, in seconds
+const ZOOM_ANIMATION_DURATION: f64 = 0.12;
+// each notch of the wheel zooms in or out by this factor
+const WHEEL_ZOOM_STEP: f64 = 1.25;
+// how far the wheel turns to move on by one image, so touchpads that
+// scroll in small steps don't race through the folder
+const WHEEL_NAVIGATE_DISTANCE: f64 = 100.;
+
+// a zoom on its way from one zoom and pan to another
+struct ZoomAnimation {
+    // the image it was started on, it stops when another one is shown
+    image: usize,
+    from: (f64, Vec2),
+    to: (f64, Vec2),
+    // a point in the image's coordinates and where it stays in the view
+    // while zooming, the pan goes straight to its target without one
+    focus: Option<(Point, Point)>,
+    progress: f64,
+}
+
 impl DisplayImageController {
     pub fn new() -> Self {
         let (sender, receiver) = sync_channel(3);
 
-        DisplayImageController { sender, receiver }
+        DisplayImageController {
+            sender,
+            receiver,
+            image_size: Size::ZERO,
+            widget_size: Size::ZERO,
+            last_drag_pos: None,
+            current_image: None,
+            previous_image: None,
+            adaptive_fit: false,
+            straighten_save: None,
+            zoom_animation: None,
+            wheel_distance: 0.,
+        }
+    }
+
+    // zooms in or out by a step around the cursor
+    fn wheel_zoom(
+        &mut self,
+        ctx: &mut druid::EventCtx,
+        data: &mut FolderGalleryState,
+        delta: Vec2,
+        pos: Point,
+    ) {
+        if delta.y == 0. {
+            return;
+        }
+        let step = if delta.y < 0. {
+            WHEEL_ZOOM_STEP
+        } else {
+            1. / WHEEL_ZOOM_STEP
+        };
+        let zoom = (self.target_zoom(data) * step).max(MIN_ZOOM).min(MAX_ZOOM);
+        self.zoom_around(ctx, data, zoom, pos);
+    }
+
+    // turning the wheel down moves on to the next image and up back to the
+    // previous one. Some platforms turn a wheel held with shift sideways, so
+    // both directions count
+    fn wheel_navigate(&mut self, data: &mut FolderGalleryState, delta: Vec2) {
+        self.wheel_distance += delta.x + delta.y;
+        if self.wheel_distance >= WHEEL_NAVIGATE_DISTANCE {
+            self.wheel_distance = 0.;
+            data.next_image(1);
+        } else if self.wheel_distance <= -WHEEL_NAVIGATE_DISTANCE {
+            self.wheel_distance = 0.;
+            data.previous_image(1);
+        }
+    }
+
+    // pans a zoomed image, panoramas scroll sideways whichever way the
+    // wheel is turned and tall images up and down
+    fn wheel_scroll(&mut self, data: &mut FolderGalleryState, delta: Vec2) {
+        if data.zoom <= 1. {
+            return;
+        }
+        let delta = if self.panorama_zoom().is_some() {
+            Vec2::new(delta.x + delta.y, 0.)
+        } else if self.tall_image_zoom().is_some() {
+            Vec2::new(0., delta.y)
+        } else {
+            delta
+        };
+        self.zoom_animation = None;
+        data.pan = clamp_pan(data.pan - delta, data.zoom, self.widget_size);
+    }
+
+    // the zoom a new zoom step goes from, the target of a zoom that's still
+    // animating so quick steps add up
+    fn target_zoom(&self, data: &FolderGalleryState) -> f64 {
+        self.zoom_animation
+            .as_ref()
+            .map_or(data.zoom, |animation| animation.to.0)
+    }
+
+    // zooms to the zoom and pan, animated unless the setting is off. A zoom
+    // that's still animating is retargeted from where it is
+    fn zoom_to(
+        &mut self,
+        ctx: &mut druid::EventCtx,
+        data: &mut FolderGalleryState,
+        (zoom, pan): (f64, Vec2),
+        focus: Option<(Point, Point)>,
+    ) {
+        if !data.settings.smooth_zoom {
+            self.zoom_animation = None;
+            data.zoom = zoom;
+            data.pan = pan;
+            return;
+        }
+        self.zoom_animation = Some(ZoomAnimation {
+            image: data.selected_image,
+            from: (data.zoom, data.pan),
+            to: (zoom, pan),
+            focus,
+            progress: 0.,
+        });
+        ctx.request_anim_frame();
+    }
+
+    // zooms keeping the point of the image, in its own coordinates, where it
+    // is in the view
+    fn zoom_around(
+        &mut self,
+        ctx: &mut druid::EventCtx,
+        data: &mut FolderGalleryState,
+        zoom: f64,
+        point: Point,
+    ) {
+        let view_point = (data.pan + point.to_vec2() * data.zoom).to_point();
+        let pan = clamp_pan(
+            view_point.to_vec2() - point.to_vec2() * zoom,
+            zoom,
+            self.widget_size,
+        );
+        self.zoom_to(ctx, data, (zoom, pan), Some((point, view_point)));
+    }
+
+    fn step_zoom_animation(
+        &mut self,
+        ctx: &mut druid::EventCtx,
+        data: &mut FolderGalleryState,
+        interval: u64,
+    ) {
+        let moved_on = self
+            .zoom_animation
+            .as_ref()
+            .map_or(false, |animation| animation.image != data.selected_image);
+        if moved_on {
+            self.zoom_animation = None;
+        }
+        let animation = match &mut self.zoom_animation {
+            Some(animation) => animation,
+            None => return,
+        };
+        let elapsed = interval as f64 / 1_000_000_000.;
+        animation.progress =
+            (animation.progress + elapsed / ZOOM_ANIMATION_DURATION).min(1.);
+        // eases out, slowing down into the new zoom
+        let t = 1. - (1. - animation.progress).powi(3);
+        let (from_zoom, from_pan) = animation.from;
+        let (to_zoom, to_pan) = animation.to;
+        // going evenly through the zoom's logarithm feels the same speed at
+        // every zoom
+        let zoom = from_zoom * (to_zoom / from_zoom).powf(t);
+        data.zoom = zoom;
+        data.pan = match animation.focus {
+            Some((point, view_point)) => clamp_pan(
+                view_point.to_vec2() - point.to_vec2() * zoom,
+                zoom,
+                self.widget_size,
+            ),
+            None => from_pan + (to_pan - from_pan) * t,
+        };
+        if animation.progress < 1. {
+            ctx.request_anim_frame();
+        } else {
+            data.zoom = to_zoom;
+            data.pan = to_pan;
+            self.zoom_animation = None;
+        }
+    }
+
+    fn fill_strat(&self) -> FillStrat {
+        if !self.adaptive_fit {
+            FillStrat::Contain
+        } else if self.image_size.height > self.image_size.width {
+            FillStrat::FitHeight
+        } else {
+            FillStrat::FitWidth
+        }
+    }
+
+    fn show_image(
+        &mut self,
+        child: &mut Zoom<FolderGalleryState, Image>,
+        decoded: DecodedImage,
+        data: &FolderGalleryState,
+    ) {
+        let (width, height) = decoded.size;
+        self.image_size = Size::new(width as f64, height as f64);
+        child.child_mut().set_image_data(decoded.image.clone());
+        self.previous_image = self.current_image.replace(decoded);
+        // the orientation decides the fit
+        child.child_mut().set_fill_mode(self.fill_strat());
+        child
+            .child_mut()
+            .set_interpolation_mode(self.interpolation_mode(data));
+    }
+
+    // resizes the window so the image view has the shape of the image,
+    // covering about as much of the screen as before without leaving the
+    // monitor the window is on
+    fn fit_window(&self, window: &WindowHandle) {
+        if self.image_size.area() == 0. || self.widget_size.area() == 0. {
+            return;
+        }
+        let window_size = window.get_size();
+        // whatever is around the image view, like the status bar
+        let extra_width = window_size.width - self.widget_size.width;
+        let extra_height = window_size.height - self.widget_size.height;
+        let center = window.get_position() + window_size.to_vec2() / 2.;
+        let monitors = Screen::get_monitors();
+        let work_area = monitors
+            .iter()
+            .map(|monitor| monitor.virtual_work_rect())
+            .find(|work_area| work_area.contains(center))
+            .or_else(|| {
+                monitors.first().map(|monitor| monitor.virtual_work_rect())
+            });
+        let work_area = match work_area {
+            Some(work_area) => work_area,
+            None => return,
+        };
+        let scale = (self.widget_size.area() / self.image_size.area())
+            .sqrt()
+            .min((work_area.width() - extra_width) / self.image_size.width)
+            .min((work_area.height() - extra_height) / self.image_size.height);
+        if scale <= 0. {
+            return;
+        }
+        let size = Size::new(
+            self.image_size.width * scale + extra_width,
+            self.image_size.height * scale + extra_height,
+        );
+        let origin = Point::new(
+            (center.x - size.width / 2.)
+                .max(work_area.x0)
+                .min(work_area.x1 - size.width),
+            (center.y - size.height / 2.)
+                .max(work_area.y0)
+                .min(work_area.y1 - size.height),
+        );
+        window.set_size(size);
+        window.set_position(origin);
+    }
+
+    // the zoom that fits a panorama to the height of the widget, None when
+    // the image isn't one or already fills the height
+    fn panorama_zoom(&self) -> Option<f64> {
+        if self.image_size.height == 0. || self.widget_size.height == 0. {
+            return None;
+        }
+        if self.image_size.width / self.image_size.height
+            < PANORAMA_ASPECT_RATIO
+        {
+            return None;
+        }
+        let height_scale = self.widget_size.height / self.image_size.height;
+        Some(height_scale / self.fit_scale()).filter(|zoom| *zoom > 1.)
+    }
+
+    // the zoom that fits a tall image to the width of the widget, None when
+    // the image isn't one or already fills the width
+    fn tall_image_zoom(&self) -> Option<f64> {
+        if self.image_size.width == 0. || self.widget_size.width == 0. {
+            return None;
+        }
+        if self.image_size.height / self.image_size.width
+            < TALL_IMAGE_ASPECT_RATIO
+        {
+            return None;
+        }
+        let width_scale = self.widget_size.width / self.image_size.width;
+        Some(width_scale / self.fit_scale()).filter(|zoom| *zoom > 1.)
+    }
+
+    // scale of the image relative to its actual pixel size when it is fit
+    // inside the widget
+    fn fit_scale(&self) -> f64 {
+        if self.image_size.width == 0. || self.image_size.height == 0. {
+            return 1.;
+        }
+        let width_scale = self.widget_size.width / self.image_size.width;
+        let height_scale = self.widget_size.height / self.image_size.height;
+        match self.fill_strat() {
+            FillStrat::FitWidth => width_scale,
+            FillStrat::FitHeight => height_scale,
+            _ => width_scale.min(height_scale),
+        }
+    }
+
+    fn interpolation_mode(
+        &self,
+        data: &FolderGalleryState,
+    ) -> InterpolationMode {
+        // a render has fewer pixels than the image, each covers more of
+        // the screen
+        let source_scale = self
+            .current_image
+            .as_ref()
+            .map_or(1., DecodedImage::source_scale);
+        let scale = self.fit_scale() * data.zoom / source_scale;
+        // at actual size every image pixel lands on a screen pixel, so
+        // there's nothing to smooth
+        if data.force_nearest_interpolation
+            || !data.settings.smooth_display
+            || (scale - 1.).abs() < ACTUAL_SIZE_TOLERANCE
+            || scale > NEAREST_NEIGHBOR_SCALE_THRESHOLD
+        {
+            InterpolationMode::NearestNeighbor
+        } else {
+            InterpolationMode::Bilinear
+        }
+    }
+
+    // the pixel of the shown image under a point of the widget, going back
+    // through the zoom and the letterboxing of the fit
+    fn pixel_at(
+        &self,
+        pos: Point,
+        data: &FolderGalleryState,
+    ) -> Option<(u32, u32)> {
+        let scale = self.fit_scale();
+        if self.current_image.is_none() || scale <= 0. {
+            return None;
+        }
+        let unzoomed = (pos.to_vec2() - data.pan) / data.zoom;
+        let offset = (self.widget_size.to_vec2()
+            - self.image_size.to_vec2() * scale)
+            / 2.;
+        let pixel = (unzoomed - offset) / scale;
+        if pixel.x < 0.
+            || pixel.y < 0.
+            || pixel.x >= self.image_size.width
+            || pixel.y >= self.image_size.height
+        {
+            return None;
+        }
+        Some((pixel.x as u32, pixel.y as u32))
+    }
+
+    // the pixels around one in the shown image, transparent past its edges
+    // so the pixel stays in the middle
+    fn loupe_pixels(&self, pixel: (u32, u32)) -> Option<ImageBuf> {
+        let current = self.current_image.as_ref()?;
+        let source = &current.source;
+        let (x, y) = current.source_pixel(pixel);
+        let (width, height) = source.dimensions();
+        let side = (LOUPE_RADIUS * 2 + 1) as usize;
+        let mut pixels = Vec::with_capacity(side * side * 4);
+        for dy in -LOUPE_RADIUS..=LOUPE_RADIUS {
+            for dx in -LOUPE_RADIUS..=LOUPE_RADIUS {
+                let (px, py) = (x as i64 + dx, y as i64 + dy);
+                if px < 0 || py < 0 || px >= width as i64 || py >= height as i64
+                {
+                    pixels.extend_from_slice(&[0, 0, 0, 0]);
+                } else {
+                    let pixel = source.get_pixel(px as u32, py as u32);
+                    pixels.extend_from_slice(&[
+                        pixel[0], pixel[1], pixel[2], 0xff,
+                    ]);
+                }
+            }
+        }
+        Some(ImageBuf::from_raw(
+            pixels,
+            ImageFormat::RgbaSeparate,
+            side,
+            side,
+        ))
+    }
+
+    // works the colors of the image being shown out on another thread and
+    // sends them back to this widget
+    fn request_palette(&self, sink: ExtEventSink, widget_id: WidgetId) {
+        let (path, source) = match &self.current_image {
+            Some(current) => (current.path.clone(), current.source.clone()),
+            None => return,
+        };
+        std::thread::spawn(move || {
+            let palette = palette::image_palette(&path, &source);
+            sink.submit_command(COMPUTED_PALETTE, (path, palette), widget_id)
+                .unwrap();
+        });
     }
 
     fn read_image(
         &self,
         sink: ExtEventSink,
         path: PathBuf,
+        auto_rotate: bool,
+        read_alpha: bool,
         widget_id: WidgetId,
     ) {
         let sender = self.sender.clone();
+        let render_target = display_size();
         std::thread::spawn(move || {
-            let image = image::io::Reader::open(path)
-                .unwrap()
-                .decode()
-                .unwrap()
-                .into_rgb8();
-            sender.send(image).unwrap();
+            background::wait_for_foreground();
+            let _job = ForegroundJob::start();
+            let cached = memory_cache::get(CacheKind::FullImage, &path)
+                .map(|image| {
+                    let size = image.dimensions();
+                    (image, size)
+                })
+                .or_else(|| {
+                    let (render, size) =
+                        render_cache::load(&path, render_target)?;
+                    Some((Arc::new(render), size))
+                });
+            let (image, size) = match cached {
+                Some(cached) => cached,
+                None => {
+                    let (image, partial) = match decode_lenient(&path) {
+                        Ok(decoded) => decoded,
+                        Err(err) => {
+                            error!("Error reading {}: {}", path.display(), err);
+                            return;
+                        }
+                    };
+                    let image = Arc::new(image);
+                    // partial images are not cached so a fixed file gets
+                    // read again
+                    if !partial {
+                        memory_cache::insert(
+                            CacheKind::FullImage,
+                            &path,
+                            image.clone(),
+                        );
+                    }
+                    if !partial && render_cache::is_huge(&image) {
+                        let path = path.clone();
+                        let image = image.clone();
+                        let job = WriteJob::start();
+                        std::thread::spawn(move || {
+                            let _job = job;
+                            render_cache::store(&path, &image, render_target)
+                        });
+                    }
+                    let size = image.dimensions();
+                    (image, size)
+                }
+            };
+            // kept for the caption, which is painted without reading the file
+            metadata::read_exif_summary(&path);
+            // the cache keeps the image as stored
+            let orientation = if auto_rotate {
+                metadata::orientation(&path)
+            } else {
+                1
+            };
+            let image = apply_orientation(image.as_ref().clone(), orientation);
+            let size = oriented_size(size, orientation);
+            // the alpha isn't cached, it's only read for the checkerboard
+            let alpha = if read_alpha {
+                decode_alpha(&path)
+            } else {
+                None
+            };
+            let alpha = alpha
+                .map(|alpha| apply_orientation(alpha, orientation))
+                .filter(|alpha| alpha.dimensions() == image.dimensions());
+            sender.send((path, image, alpha, size)).unwrap();
             sink.submit_command(FINISHED_READING_IMAGE, (), widget_id)
                 .unwrap();
+            sink.submit_command(
+                CACHE_USAGE,
+                memory_cache::usage_mb(),
+                Target::Auto,
+            )
+            .unwrap();
         });
     }
+
+    // shows an image that finished reading on another thread
+    fn receive_image(
+        &mut self,
+        child: &mut Zoom<FolderGalleryState, Image>,
+        ctx: &mut druid::EventCtx,
+        data: &mut FolderGalleryState,
+    ) {
+        let (path, image, alpha, size) = match self.receiver.try_recv() {
+            Ok(read) => read,
+            Err(_) => return,
+        };
+        // stepping on quickly or deleting the image can leave a decode of
+        // an image that isn't shown anymore
+        if data.displayed_path().as_deref() != Some(&path) {
+            return;
+        }
+        let source = Arc::new(image.clone());
+        let (width, height) = image.dimensions();
+        let image = match alpha {
+            Some(alpha) => ImageBuf::from_raw(
+                with_alpha(&image, &alpha),
+                ImageFormat::RgbaSeparate,
+                width as usize,
+                height as usize,
+            ),
+            None => ImageBuf::from_raw(
+                image.into_raw(),
+                ImageFormat::Rgb,
+                width as usize,
+                height as usize,
+            ),
+        };
+        let decoded = DecodedImage {
+            path,
+            image,
+            source,
+            size,
+        };
+        self.show_image(child, decoded, data);
+        data.palette = None;
+        if data.show_palette {
+            self.request_palette(ctx.get_external_handle(), ctx.widget_id());
+        }
+        if data.settings.fit_window_to_image {
+            self.fit_window(ctx.window());
+        } else if data.zoom == 1. && data.pan == Vec2::ZERO {
+            // panoramas start from the left end with the height filled and
+            // tall images from the top with the width filled
+            let panorama_zoom = self
+                .panorama_zoom()
+                .filter(|_| data.settings.scroll_panoramas);
+            let tall_image_zoom = self
+                .tall_image_zoom()
+                .filter(|_| data.settings.scroll_tall_images);
+            if let Some(zoom) = panorama_zoom {
+                data.zoom = zoom;
+                data.pan =
+                    Vec2::new(0., centered_pan(zoom, self.widget_size).y);
+            } else if let Some(zoom) = tall_image_zoom {
+                data.zoom = zoom;
+                data.pan =
+                    Vec2::new(centered_pan(zoom, self.widget_size).x, 0.);
+            }
+        }
+        ctx.request_layout();
+        ctx.request_paint();
+        // a slideshow transition only starts once the next image is actually
+        // there to show
+        if data.transition < 1. {
+            ctx.request_anim_frame();
+        }
+    }
+}
+
+// the alpha channel of the file turned upright the way the image view shows
+// the image
+fn oriented_alpha(path: &Path, auto_rotate: bool) -> Option<GrayImage> {
+    let alpha = decode_alpha(path)?;
+    let orientation = if auto_rotate {
+        metadata::orientation(path)
+    } else {
+        1
+    };
+    Some(apply_orientation(alpha, orientation))
+}
+
+// the shown image at the size it's stored at, read again from the file when
+// what's shown is a render made for the screen
+fn full_image(
+    path: &Path,
+    source: Arc<RgbImage>,
+    size: (u32, u32),
+    auto_rotate: bool,
+) -> Result<Arc<RgbImage>, ImageError> {
+    if source.dimensions() == size {
+        return Ok(source);
+    }
+    let image = match memory_cache::get(CacheKind::FullImage, path) {
+        Some(image) => image.as_ref().clone(),
+        None => decode_lenient(path)?.0,
+    };
+    let orientation = if auto_rotate {
+        metadata::orientation(path)
+    } else {
+        1
+    };
+    Ok(Arc::new(apply_orientation(image, orientation)))
+}
+
+// the size of the biggest monitor, renders of huge images made at it fill
+// the image view at any window size
+fn display_size() -> (u32, u32) {
+    Screen::get_monitors()
+        .iter()
+        .map(|monitor| monitor.virtual_rect().size())
+        .fold((0, 0), |(width, height), size| {
+            (width.max(size.width as u32), height.max(size.height as u32))
+        })
+}
+
+// crops the part of the image visible in the widget and scales it to the
+// size it's displayed at
+fn render_view(
+    image: &RgbImage,
+    widget_size: Size,
+    fit_scale: f64,
+    zoom: f64,
+    pan: Vec2,
+) -> Option<RgbImage> {
+    let (width, height) = image.dimensions();
+    let image_size = Size::new(width as f64, height as f64);
+    let scale = fit_scale * zoom;
+    // where the unzoomed image sits inside the widget
+    let fit_origin =
+        (widget_size.to_vec2() - image_size.to_vec2() * fit_scale) / 2.;
+    let origin = pan + fit_origin * zoom;
+    let visible = Rect::from_origin_size(Point::ORIGIN, widget_size);
+    let crop = Rect::from_origin_size(
+        (
+            (visible.x0 - origin.x) / scale,
+            (visible.y0 - origin.y) / scale,
+        ),
+        visible.size() / scale,
+    )
+    .intersect(image_size.to_rect())
+    .round();
+    if crop.width() < 1. || crop.height() < 1. {
+        return None;
+    }
+    let cropped = image::imageops::crop_imm(
+        image,
+        crop.x0 as u32,
+        crop.y0 as u32,
+        crop.width() as u32,
+        crop.height() as u32,
+    )
+    .to_image();
+    let output_width = (crop.width() * scale).round().max(1.) as u32;
+    let output_height = (crop.height() * scale).round().max(1.) as u32;
+    Some(image::imageops::resize(
+        &cropped,
+        output_width,
+        output_height,
+        FilterType::Triangle,
+    ))
+}
+
+// draws the caption into the exported image the same way it's painted over
+// the image view
+fn burn_caption(
+    image: RgbImage,
+    caption: &str,
+    position: CaptionPosition,
+) -> RgbImage {
+    let (width, height) = image.dimensions();
+    let render = || -> Result<Vec<u8>, druid::piet::Error> {
+        let mut device = Device::new()?;
+        let mut target =
+            device.bitmap_target(width as usize, height as usize, 1.)?;
+        {
+            let mut rc = target.render_context();
+            let source = rc.make_image(
+                width as usize,
+                height as usize,
+                image.as_raw(),
+                ImageFormat::Rgb,
+            )?;
+            let size = Size::new(width as f64, height as f64);
+            rc.draw_image(
+                &source,
+                size.to_rect(),
+                InterpolationMode::NearestNeighbor,
+            );
+            draw_caption(&mut rc, size, caption, position);
+            rc.finish()?;
+        }
+        target.into_raw_pixels(ImageFormat::RgbaPremul)
+    };
+    match render() {
+        // the image is opaque so premultiplying didn't change anything
+        Ok(pixels) => {
+            let rgb = pixels
+                .chunks_exact(4)
+                .flat_map(|pixel| pixel[..3].to_vec())
+                .collect();
+            RgbImage::from_raw(width, height, rgb).unwrap_or(image)
+        }
+        Err(err) => {
+            error!("Error drawing the caption into the export: {}", err);
+            image
+        }
+    }
+}
+
+// frames of a sequence share everything in their name except the number at
+// the end of the stem
+fn sequence_key(path: &Path) -> Option<(String, Option<String>)> {
+    let stem = path.file_stem()?.to_string_lossy();
+    let prefix = stem.trim_end_matches(|c: char| c.is_ascii_digit());
+    if prefix.len() == stem.len() {
+        return None;
+    }
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    Some((prefix.to_string(), extension))
+}
+
+// the image's pixels with the alpha after them, the layout RgbaSeparate
+// expects
+fn with_alpha(image: &RgbImage, alpha: &GrayImage) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity(alpha.as_raw().len() * 4);
+    for (rgb, alpha) in image.pixels().zip(alpha.pixels()) {
+        pixels.extend_from_slice(&[rgb[0], rgb[1], rgb[2], alpha[0]]);
+    }
+    pixels
+}
+
+// shorter lines are taken as clicks
+const MIN_STRAIGHTEN_LINE: f64 = 10.;
+// straightening is for small corrections, quarter turns are done by EXIF
+// orientation
+pub const MAX_STRAIGHTEN_ANGLE: f64 = FRAC_PI_4;
+
+// the angle that makes a line drawn over the turned image level, or plumb
+// when the line is closer to vertical
+fn level_angle(angle: f64, line: Vec2) -> f64 {
+    let mut tilt = line.y.atan2(line.x);
+    // which way the line was dragged doesn't matter
+    while tilt > FRAC_PI_4 {
+        tilt -= FRAC_PI_2;
+    }
+    while tilt <= -FRAC_PI_4 {
+        tilt += FRAC_PI_2;
+    }
+    (angle - tilt)
+        .max(-MAX_STRAIGHTEN_ANGLE)
+        .min(MAX_STRAIGHTEN_ANGLE)
+}
+
+// the range the zoom slider covers, relative to the fit
+pub const MIN_ZOOM: f64 = 0.1;
+pub const MAX_ZOOM: f64 = 10.;
+
+// keeps the zoomed image covering the widget so it can't be panned out of
+// view, an image zoomed out smaller than the widget stays in its middle
+fn clamp_pan(pan: Vec2, zoom: f64, size: Size) -> Vec2 {
+    if zoom < 1. {
+        return centered_pan(zoom, size);
+    }
+    let min = size.to_vec2() * (1. - zoom);
+    Vec2::new(pan.x.max(min.x).min(0.), pan.y.max(min.y).min(0.))
 }
-impl Controller<FolderGalleryState, Image> for DisplayImageController {
+
+fn centered_pan(zoom: f64, size: Size) -> Vec2 {
+    size.to_vec2() * (1. - zoom) / 2.
+}
+
+impl Controller<FolderGalleryState, Zoom<FolderGalleryState, Image>>
+    for DisplayImageController
+{
     fn event(
         &mut self,
-        child: &mut Image,
+        child: &mut Zoom<FolderGalleryState, Image>,
         ctx: &mut druid::EventCtx,
         event: &Event,
         data: &mut FolderGalleryState,
         env: &Env,
     ) {
+        if let Event::AnimFrame(interval) = event {
+            self.step_zoom_animation(ctx, data, *interval);
+        }
         match event {
             Event::Command(image_selector)
                 if image_selector.is(FINISHED_READING_IMAGE) =>
             {
-                let image = self.receiver.recv().unwrap();
-                let (width, height) = image.dimensions();
-                let image = ImageBuf::from_raw(
-                    image.into_raw(),
-                    ImageFormat::Rgb,
-                    width as usize,
-                    height as usize,
-                );
-                child.set_image_data(image);
-                ctx.request_layout();
+                self.receive_image(child, ctx, data);
+            }
+            // colors that arrive after another image is shown are dropped
+            Event::Command(cmd) if cmd.is(COMPUTED_PALETTE) => {
+                let (path, palette) = cmd.get_unchecked(COMPUTED_PALETTE);
+                let shown = self
+                    .current_image
+                    .as_ref()
+                    .map_or(false, |current| &current.path == path);
+                if shown {
+                    data.palette = Some(palette.clone());
+                }
+            }
+            // keeps the same part of a zoomed image in the middle of the
+            // view, the zoom itself stays as it was
+            Event::Command(cmd) if cmd.is(IMAGE_VIEW_RESIZED) => {
+                let old_size = *cmd.get_unchecked(IMAGE_VIEW_RESIZED);
+                if old_size.width > 0. && old_size.height > 0. {
+                    let size = self.widget_size;
+                    let pan = Vec2::new(
+                        data.pan.x * size.width / old_size.width,
+                        data.pan.y * size.height / old_size.height,
+                    );
+                    data.pan = clamp_pan(pan, data.zoom, size);
+                }
+                ctx.request_paint();
+            }
+            Event::Command(cmd) if cmd.is(SCROLL_IMAGE_PAGE) => {
+                let (pages, advance) = *cmd.get_unchecked(SCROLL_IMAGE_PAGE);
+                let bottom = self.widget_size.height * (1. - data.zoom);
+                if advance && pages > 0. && data.pan.y <= bottom + 0.5 {
+                    data.next_image(1);
+                } else {
+                    let step =
+                        self.widget_size.height * PAGE_SCROLL_FRACTION * pages;
+                    data.pan = clamp_pan(
+                        data.pan - Vec2::new(0., step),
+                        data.zoom,
+                        self.widget_size,
+                    );
+                }
+                ctx.request_paint();
+            }
+            Event::Command(cmd) if cmd.is(CENTER_ZOOMED_IMAGE) => {
+                self.zoom_animation = None;
+                data.pan = centered_pan(data.zoom, self.widget_size);
                 ctx.request_paint();
             }
+            Event::Command(cmd) if cmd.is(EXPORT_VIEW) => {
+                if self.current_image.is_some() {
+                    let options = FileDialogOptions::new()
+                        .allowed_types(vec![FileSpec::PNG, FileSpec::JPG])
+                        .default_type(FileSpec::PNG)
+                        .default_name("view.png");
+                    self.straighten_save = None;
+                    ctx.submit_command(SHOW_SAVE_PANEL.with(options));
+                }
+            }
+            Event::Command(cmd) if cmd.is(SAVE_STRAIGHTENED) => {
+                if let Some(image) = &self.current_image {
+                    let stem = image
+                        .path
+                        .file_stem()
+                        .map(|stem| stem.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    let options = FileDialogOptions::new()
+                        .allowed_types(vec![FileSpec::PNG, FileSpec::JPG])
+                        .default_type(FileSpec::PNG)
+                        .default_name(format!("{}-straightened.png", stem));
+                    self.straighten_save =
+                        Some(*cmd.get_unchecked(SAVE_STRAIGHTENED));
+                    ctx.submit_command(SHOW_SAVE_PANEL.with(options));
+                }
+            }
+            Event::Command(cmd)
+                if cmd.is(SAVE_FILE_AS) && self.straighten_save.is_some() =>
+            {
+                let file_info = cmd.get_unchecked(SAVE_FILE_AS);
+                let angle = self.straighten_save.take().unwrap();
+                if let Some((source, image, size)) =
+                    self.current_image.as_ref().map(|image| {
+                        (image.path.clone(), image.source.clone(), image.size)
+                    })
+                {
+                    let path = file_info.path().to_path_buf();
+                    let auto_rotate = data.settings.auto_rotate;
+                    let handle = ctx.get_external_handle();
+                    // started before the thread so quitting right away still
+                    // waits for it
+                    let job = WriteJob::start();
+                    std::thread::spawn(move || {
+                        let _job = job;
+                        let image =
+                            full_image(&source, image, size, auto_rotate);
+                        let image = match image {
+                            Ok(image) => image,
+                            Err(err) => {
+                                error!(
+                                    "Error reading {}: {}",
+                                    source.display(),
+                                    err
+                                );
+                                let _ = handle.submit_command(
+                                    SHOW_TOAST,
+                                    format!("Couldn't save: {}", err),
+                                    Target::Auto,
+                                );
+                                return;
+                            }
+                        };
+                        // JPGs can't keep the alpha channel
+                        let png = image::ImageFormat::from_path(&path).ok()
+                            == Some(image::ImageFormat::Png);
+                        let alpha = if png {
+                            oriented_alpha(&source, auto_rotate).filter(
+                                |alpha| {
+                                    alpha.dimensions() == image.dimensions()
+                                },
+                            )
+                        } else {
+                            None
+                        };
+                        let straightened = straighten(image.as_ref(), angle);
+                        let saved = match alpha {
+                            Some(alpha) => join_alpha(
+                                &straightened,
+                                &straighten(&alpha, angle),
+                            )
+                            .save(&path),
+                            None => straightened.save(&path),
+                        };
+                        let message = match saved {
+                            Ok(()) => format!("Saved {}", path.display()),
+                            Err(err) => {
+                                error!(
+                                    "Error saving straightened image to {}: {}",
+                                    path.display(),
+                                    err
+                                );
+                                format!("Couldn't save: {}", err)
+                            }
+                        };
+                        let _ = handle.submit_command(
+                            SHOW_TOAST,
+                            message,
+                            Target::Auto,
+                        );
+                    });
+                }
+            }
+            Event::Command(cmd) if cmd.is(SAVE_FILE_AS) => {
+                let file_info = cmd.get_unchecked(SAVE_FILE_AS);
+                if let Some((source, image, size)) =
+                    self.current_image.as_ref().map(|image| {
+                        (image.path.clone(), image.source.clone(), image.size)
+                    })
+                {
+                    let path = file_info.path().to_path_buf();
+                    let auto_rotate = data.settings.auto_rotate;
+                    let widget_size = self.widget_size;
+                    let fit_scale = self.fit_scale();
+                    let (zoom, pan) = (data.zoom, data.pan);
+                    let caption = data.caption_text();
+                    let position = data.settings.caption_position;
+                    let handle = ctx.get_external_handle();
+                    // started before the thread so quitting right away still
+                    // waits for it
+                    let job = WriteJob::start();
+                    std::thread::spawn(move || {
+                        let _job = job;
+                        // the fit scale is for the image as stored
+                        let image =
+                            full_image(&source, image, size, auto_rotate);
+                        let image = match image {
+                            Ok(image) => image,
+                            Err(err) => {
+                                error!(
+                                    "Error reading {}: {}",
+                                    source.display(),
+                                    err
+                                );
+                                let _ = handle.submit_command(
+                                    SHOW_TOAST,
+                                    format!("Couldn't export view: {}", err),
+                                    Target::Auto,
+                                );
+                                return;
+                            }
+                        };
+                        let view = match render_view(
+                            &image,
+                            widget_size,
+                            fit_scale,
+                            zoom,
+                            pan,
+                        ) {
+                            Some(view) => view,
+                            None => return,
+                        };
+                        let view = match caption {
+                            Some(caption) => {
+                                burn_caption(view, &caption, position)
+                            }
+                            None => view,
+                        };
+                        let message = match view.save(&path) {
+                            Ok(()) => {
+                                format!("Exported view to {}", path.display())
+                            }
+                            Err(err) => {
+                                error!(
+                                    "Error exporting view to {}: {}",
+                                    path.display(),
+                                    err
+                                );
+                                format!("Couldn't export view: {}", err)
+                            }
+                        };
+                        let _ = handle.submit_command(
+                            SHOW_TOAST,
+                            message,
+                            Target::Auto,
+                        );
+                    });
+                }
+            }
+            Event::AnimFrame(interval) if data.transition < 1. => {
+                let elapsed = *interval as f64 / 1_000_000_000.;
+                let duration = data.settings.transition_duration;
+                data.transition = if duration > 0. {
+                    (data.transition + elapsed / duration).min(1.)
+                } else {
+                    1.
+                };
+                if data.transition < 1. {
+                    ctx.request_anim_frame();
+                }
+            }
+            // a line dragged along what should be level sets the angle
+            Event::MouseDown(mouse)
+                if data.straighten.is_some()
+                    && mouse.button == MouseButton::Left =>
+            {
+                data.straighten_line = Some((mouse.pos, mouse.pos));
+                ctx.set_active(true);
+            }
+            Event::MouseMove(mouse) if data.straighten.is_some() => {
+                ctx.set_cursor(&Cursor::Crosshair);
+                if let Some((start, _)) = data.straighten_line {
+                    data.straighten_line = Some((start, mouse.pos));
+                }
+            }
+            Event::MouseUp(_) if data.straighten.is_some() => {
+                ctx.set_active(false);
+                if let (Some(angle), Some((start, end))) =
+                    (data.straighten, data.straighten_line.take())
+                {
+                    if start.distance(end) >= MIN_STRAIGHTEN_LINE {
+                        data.straighten = Some(level_angle(angle, end - start));
+                    }
+                }
+            }
+            // samples the pixel and copies its hex value
+            Event::MouseDown(mouse) if data.eyedropper => {
+                let picked = self.pixel_at(mouse.pos, data).and_then(|pixel| {
+                    let current = self.current_image.as_ref()?;
+                    let (x, y) = current.source_pixel(pixel);
+                    let color = current.source.get_pixel(x, y);
+                    Some((pixel.0, pixel.1, (color[0], color[1], color[2])))
+                });
+                if let Some((_, _, (r, g, b))) = picked {
+                    let hex = format!("#{:02X}{:02X}{:02X}", r, g, b);
+                    Application::global().clipboard().put_string(hex);
+                    data.picked_color = picked;
+                }
+            }
+            Event::MouseMove(mouse) if data.eyedropper => {
+                ctx.set_cursor(&Cursor::Crosshair);
+                data.loupe = self
+                    .pixel_at(mouse.pos, data)
+                    .and_then(|pixel| self.loupe_pixels(pixel))
+                    .map(|pixels| (mouse.pos, pixels));
+            }
+            // turns pages like a comic reader, a double click turns two
+            Event::MouseDown(mouse)
+                if data.settings.click_to_advance
+                    && data.zoom <= 1.
+                    && mouse.button == MouseButton::Left =>
+            {
+                if mouse.pos.x >= self.widget_size.width / 2. {
+                    data.next_image(1);
+                } else {
+                    data.previous_image(1);
+                }
+            }
+            // double clicking toggles between fitting the image to the window
+            // and the zoom preset, keeping the clicked point under the cursor
+            Event::MouseDown(mouse) if mouse.count == 2 => {
+                if self.target_zoom(data) > 1. {
+                    self.zoom_to(ctx, data, (1., Vec2::ZERO), None);
+                } else {
+                    let zoom = (data.settings.double_click_zoom
+                        / self.fit_scale())
+                    .max(1.);
+                    self.zoom_around(ctx, data, zoom, mouse.pos);
+                }
+            }
+            // the plain wheel does what the setting says, ctrl always zooms,
+            // shift navigates and alt scrolls
+            Event::Wheel(wheel) => {
+                let action = if wheel.mods.ctrl() {
+                    WheelAction::Zoom
+                } else if wheel.mods.shift() {
+                    WheelAction::Navigate
+                } else if wheel.mods.alt() {
+                    WheelAction::Scroll
+                } else {
+                    data.settings.wheel_action
+                };
+                if action != WheelAction::Navigate {
+                    self.wheel_distance = 0.;
+                }
+                match action {
+                    WheelAction::Zoom => {
+                        self.wheel_zoom(ctx, data, wheel.wheel_delta, wheel.pos)
+                    }
+                    WheelAction::Navigate => {
+                        self.wheel_navigate(data, wheel.wheel_delta)
+                    }
+                    WheelAction::Scroll => {
+                        self.wheel_scroll(data, wheel.wheel_delta)
+                    }
+                }
+                ctx.set_handled();
+            }
+            Event::MouseDown(mouse) if data.zoom > 1. => {
+                self.zoom_animation = None;
+                self.last_drag_pos = Some(mouse.pos);
+                ctx.set_active(true);
+            }
+            Event::MouseMove(mouse) => {
+                ctx.clear_cursor();
+                if let Some(last_pos) = self.last_drag_pos {
+                    data.pan = clamp_pan(
+                        data.pan + (mouse.pos - last_pos),
+                        data.zoom,
+                        self.widget_size,
+                    );
+                    self.last_drag_pos = Some(mouse.pos);
+                }
+            }
+            Event::MouseUp(_) => {
+                self.last_drag_pos = None;
+                ctx.set_active(false);
+            }
             _ => (),
         }
+        let fit_scale = self.fit_scale();
+        if data.displayed_size != self.image_size
+            || !data.fit_scale.same(&fit_scale)
+        {
+            data.displayed_size = self.image_size;
+            data.fit_scale = fit_scale;
+        }
         child.event(ctx, event, data, env)
     }
 
     fn update(
         &mut self,
-        child: &mut Image,
+        child: &mut Zoom<FolderGalleryState, Image>,
         ctx: &mut UpdateCtx,
         old_data: &FolderGalleryState,
         data: &FolderGalleryState,
@@ -272,22 +3529,82 @@ impl Controller<FolderGalleryState, Image> for DisplayImageController {
         if data.paths.is_empty() {
             return;
         }
-        if data.selected_image != old_data.selected_image {
-            let path = data.paths[data.selected_image].as_ref().clone();
-            let sink = ctx.get_external_handle();
-            // only need to send this payload back to itself
-            // after it finishes reading the image on a separate thread
-            // only DisplayImageController needs to see this payload
-            self.read_image(sink, path, ctx.widget_id());
+        if data.force_nearest_interpolation
+            != old_data.force_nearest_interpolation
+            || data.settings.smooth_display != old_data.settings.smooth_display
+            || !data.zoom.same(&old_data.zoom)
+        {
+            child
+                .child_mut()
+                .set_interpolation_mode(self.interpolation_mode(data));
+            ctx.request_paint();
+        }
+        if data.settings.adaptive_fit != self.adaptive_fit {
+            self.adaptive_fit = data.settings.adaptive_fit;
+            child.child_mut().set_fill_mode(self.fill_strat());
+            ctx.request_layout();
+            ctx.request_paint();
+        }
+        if data.settings.fit_window_to_image
+            && !old_data.settings.fit_window_to_image
+        {
+            self.fit_window(ctx.window());
+        }
+        // the image is read again turned the other way, or with or without
+        // its alpha when the checkerboard is toggled
+        let decode_changed = data.settings.auto_rotate
+            != old_data.settings.auto_rotate
+            || data.settings.checkerboard_background
+                != old_data.settings.checkerboard_background;
+        // the other image kept for flickering is the wrong way up now
+        if decode_changed {
+            self.previous_image = None;
+        }
+        // compares paths since the index stays the same when the shown image
+        // is deleted
+        let displayed = data.displayed_path();
+        if displayed != old_data.displayed_path() || decode_changed {
+            let path = displayed.unwrap().as_ref().clone();
+            let previous_matches = self
+                .previous_image
+                .as_ref()
+                .map_or(false, |previous| previous.path == path);
+            if previous_matches {
+                let previous = self.previous_image.take().unwrap();
+                self.show_image(child, previous, data);
+                if data.show_palette {
+                    self.request_palette(
+                        ctx.get_external_handle(),
+                        ctx.widget_id(),
+                    );
+                }
+                if data.settings.fit_window_to_image {
+                    self.fit_window(ctx.window());
+                }
+            } else {
+                let sink = ctx.get_external_handle();
+                // only need to send this payload back to itself
+                // after it finishes reading the image on a separate thread
+                // only DisplayImageController needs to see this payload
+                self.read_image(
+                    sink,
+                    path,
+                    data.settings.auto_rotate,
+                    data.settings.checkerboard_background,
+                    ctx.widget_id(),
+                );
+            }
             ctx.request_layout();
             ctx.request_paint();
+        } else if data.show_palette && !old_data.show_palette {
+            self.request_palette(ctx.get_external_handle(), ctx.widget_id());
         }
         child.update(ctx, old_data, data, env)
     }
 
     fn lifecycle(
         &mut self,
-        child: &mut Image,
+        child: &mut Zoom<FolderGalleryState, Image>,
         ctx: &mut LifeCycleCtx,
         event: &LifeCycle,
         data: &FolderGalleryState,
@@ -297,25 +3614,59 @@ impl Controller<FolderGalleryState, Image> for DisplayImageController {
         // not problematic. Druid warns because this might send an event
         // back here, to read the image, before it gets laid out
         if let LifeCycle::WidgetAdded = event {
-            let path = data.paths[data.selected_image].as_ref().clone();
+            self.adaptive_fit = data.settings.adaptive_fit;
+            let path = data.displayed_path().unwrap().as_ref().clone();
             let sink = ctx.get_external_handle();
             // only need to send this payload back to itself
             // after it finishes reading the image on a separate thread
             // only DisplayImage needs to see this payload
-            self.read_image(sink, path, ctx.widget_id());
+            self.read_image(
+                sink,
+                path,
+                data.settings.auto_rotate,
+                data.settings.checkerboard_background,
+                ctx.widget_id(),
+            );
+        }
+        if let LifeCycle::Size(size) = event {
+            // the fit scale and pan kept in the data are only updated while
+            // handling an event
+            if self.widget_size != *size {
+                ctx.submit_command(
+                    IMAGE_VIEW_RESIZED
+                        .with(self.widget_size)
+                        .to(ctx.widget_id()),
+                );
+            }
+            self.widget_size = *size;
+            child
+                .child_mut()
+                .set_interpolation_mode(self.interpolation_mode(data));
         }
         child.lifecycle(ctx, event, data, env)
     }
 }
 
-pub struct FolderThumbnailController;
-impl Controller<(Thumbnail, usize), Image> for FolderThumbnailController {
+pub struct FolderThumbnailController {
+    // the gallery's scroll, so a highlighted tile can be scrolled into view
+    scroll_id: WidgetId,
+}
+
+impl FolderThumbnailController {
+    pub fn new(scroll_id: WidgetId) -> Self {
+        Self { scroll_id }
+    }
+}
+
+impl Controller<(Thumbnail, usize, bool, bool), Image>
+    for FolderThumbnailController
+{
     fn event(
         &mut self,
         child: &mut Image,
         ctx: &mut druid::EventCtx,
         event: &Event,
-        data: &mut (Thumbnail, usize),
+        data: &mut (Thumbnail, usize, bool, bool),
         env: &Env,
     ) {
         child.event(ctx, event, data, env)
@@ -325,11 +3676,12 @@ impl Controller<(Thumbnail, usize), Image> for FolderThumbnailController {
         child: &mut Image,
         ctx: &mut LifeCycleCtx,
         event: &LifeCycle,
-        data: &(Thumbnail, usize),
+        data: &(Thumbnail, usize, bool, bool),
         env: &Env,
     ) {
         if let LifeCycle::WidgetAdded = event {
             child.set_image_data(data.0.image.clone());
+            child.set_fill_mode(thumbnail_fill(env));
             ctx.request_layout();
             ctx.request_paint();
         }
@@ -340,15 +3692,27 @@ impl Controller<(Thumbnail, usize), Image> for FolderThumbnailController {
         &mut self,
         child: &mut Image,
         ctx: &mut UpdateCtx,
-        old_data: &(Thumbnail, usize),
-        data: &(Thumbnail, usize),
+        old_data: &(Thumbnail, usize, bool, bool),
+        data: &(Thumbnail, usize, bool, bool),
         env: &Env,
     ) {
-        if !data.same(old_data) {
+        if !data.0.same(&old_data.0) {
             child.set_image_data(data.0.image.clone());
             ctx.request_layout();
             ctx.request_paint();
         }
+        if data.2 && !old_data.2 {
+            let rect = ctx.size().to_rect() + ctx.window_origin().to_vec2();
+            ctx.submit_command(Command::new(
+                SCROLL_TO_WINDOW_RECT,
+                rect,
+                Target::Widget(self.scroll_id),
+            ));
+        }
+        if ctx.env_key_changed(&COVER_THUMBNAILS) {
+            child.set_fill_mode(thumbnail_fill(env));
+            ctx.request_paint();
+        }
         child.update(ctx, old_data, data, env)
     }
 }
@@ -1,30 +1,67 @@
-use std::{path::Path, thread};
+use std::{
+    cell::RefCell,
+    f64::consts::PI,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::Arc,
+    thread,
+    time::Duration,
+};
 
 use druid::{
-    piet::{ImageFormat, InterpolationMode},
+    kurbo::{BezPath, Line, Shape},
+    lens,
+    piet::{
+        ImageFormat, InterpolationMode, Text, TextLayout, TextLayoutBuilder,
+    },
     widget::{
-        Container, Controller, CrossAxisAlignment, FillStrat, Flex, FlexParams,
-        Image, Label, MainAxisAlignment, Painter, Scope,
+        Container, Controller, CrossAxisAlignment, Either, FillStrat, Flex,
+        FlexParams, Image, Label, LineBreaking, List, MainAxisAlignment,
+        Painter, Scope, SizedBox, Slider,
     },
-    Color, Command, Env, Event, ImageBuf, LensExt, RenderContext, Target,
-    Widget, WidgetExt,
+    Affine, Application, Color, Command, ContextMenu, Data, Env, Event,
+    EventCtx, ExtEventSink, FontDescriptor, FontFamily, ImageBuf, KbKey,
+    KeyEvent, LensExt, LifeCycle, LifeCycleCtx, LocalizedString, MenuDesc,
+    MenuItem, MouseButton, PaintCtx, Rect, RenderContext, Size, Target,
+    TimerToken, Vec2, Widget, WidgetExt, WidgetId,
 };
 use druid_gridview::GridView;
 use druid_navigator::navigator::Navigator;
 use druid_widget_nursery::DynamicSizedBox;
-use image::{imageops::thumbnail, io::Reader, ImageError, RgbImage};
+use image::{ImageError, RgbImage};
+use log::error;
 
 use crate::{
     app_commands::{
-        CREATED_THUMBNAIL, POP_FOLDER_VIEW, POP_VIEW,
-        PUSH_VIEW_WITH_SELECTED_IMAGE, SELECT_IMAGE_SELECTOR,
+        CACHE_USAGE, CENTER_ZOOMED_IMAGE, COPY_IMAGE_DETAIL, CREATED_THUMBNAIL,
+        EXPORT_VIEW, FAILED_THUMBNAIL, OPEN_CONTAINING_FOLDER, OPEN_LOCATION,
+        POP_FOLDER_VIEW, POP_VIEW, PUSH_VIEW_WITH_SELECTED_IMAGE,
+        REGENERATE_THUMBNAIL, RETRY_TIMED_OUT_IMAGE, SAVE_STRAIGHTENED,
+        SCROLL_IMAGE_PAGE, SELECT_IMAGE_SELECTOR, TOGGLE_IMAGE_SELECTED,
     },
-    app_data::{AppState, Thumbnail},
+    app_data::{AppState, FileFormat, Flag, Thumbnail},
+    background::{self, ForegroundJob},
+    decode::{apply_orientation, decode_lenient, downscale, inscribed_size},
     folder_view::{
-        DisplayImageController, FolderGalleryState, FolderView,
-        FolderViewController, GalleryTransfer,
+        details_date, end_of_library, prefetch_full_images, CopyDetail,
+        DateGroup, DetailsColumn, DetailsRow, DisplayImageController,
+        FolderGalleryState, FolderView, FolderViewController,
+        GalleryScrollController, GalleryTransfer, DATE_HEADING_HEIGHT,
+        DETAILS_ROW_HEIGHT, MAX_STRAIGHTEN_ANGLE, MAX_ZOOM, MIN_ZOOM,
+    },
+    format,
+    memory_cache::{self, CacheKind},
+    metadata, palette,
+    preferences::open_preferences,
+    settings::{
+        number_locale, CaptionPosition, ImageFrame, SlideshowTransition,
+        SHOW_FORMAT_BADGES, SHOW_TILE_DATES,
+    },
+    thumbnail_cache,
+    widgets::{
+        paint_toast_message, Button, Justified, JustifiedRows, Overlay, Scroll,
+        Zoom, TOAST_DURATION,
     },
-    widgets::{Button, Scroll},
 };
 
 use super::FolderThumbnailController;
@@ -68,72 +105,620 @@ pub fn folder_view_main() -> Box<dyn Widget<FolderGalleryState>> {
             |_path, _data_path| (),
         ));
 
+    let flag_filter_button = Button::new(
+        |data: &FolderGalleryState, _env: &Env| {
+            format!("Show: {}", data.flag_filter.label())
+        },
+        Color::BLACK,
+        Color::rgb8(0xff, 0xff, 0xff),
+        Color::rgb8(0xcc, 0xcc, 0xcc),
+        Color::rgb8(0x90, 0x90, 0x90),
+        16.,
+    )
+    .on_click(|_ctx, data: &mut FolderGalleryState, _env| {
+        data.flag_filter = data.flag_filter.next();
+    });
+
+    let recent_filter_button = Button::new(
+        |data: &FolderGalleryState, _env: &Env| {
+            if data.recent_only {
+                format!("Recent: Last {} days", data.settings.recent_days)
+            } else {
+                "Recent: Off".to_string()
+            }
+        },
+        Color::BLACK,
+        Color::rgb8(0xff, 0xff, 0xff),
+        Color::rgb8(0xcc, 0xcc, 0xcc),
+        Color::rgb8(0x90, 0x90, 0x90),
+        16.,
+    )
+    .on_click(|_ctx, data: &mut FolderGalleryState, _env| {
+        data.recent_only = !data.recent_only;
+    });
+
+    let list_mode_button = Button::new(
+        |data: &FolderGalleryState, _env: &Env| {
+            if data.settings.list_mode {
+                "Tiles: Names".to_string()
+            } else {
+                "Tiles: Thumbnails".to_string()
+            }
+        },
+        Color::BLACK,
+        Color::rgb8(0xff, 0xff, 0xff),
+        Color::rgb8(0xcc, 0xcc, 0xcc),
+        Color::rgb8(0x90, 0x90, 0x90),
+        16.,
+    )
+    .on_click(|_ctx, data: &mut FolderGalleryState, _env| {
+        data.settings.list_mode = !data.settings.list_mode;
+    });
+
+    let date_grouping_button = Button::new(
+        |data: &FolderGalleryState, _env: &Env| {
+            format!("Group: {}", data.settings.date_grouping.label())
+        },
+        Color::BLACK,
+        Color::rgb8(0xff, 0xff, 0xff),
+        Color::rgb8(0xcc, 0xcc, 0xcc),
+        Color::rgb8(0x90, 0x90, 0x90),
+        16.,
+    )
+    .on_click(|_ctx, data: &mut FolderGalleryState, _env| {
+        data.settings.date_grouping = data.settings.date_grouping.next();
+    });
+
     let header = Flex::row()
         .with_child(back_button)
         .with_spacer(10.)
         .with_flex_child(title, 1.0)
+        .with_child(date_grouping_button)
+        .with_child(list_mode_button)
+        .with_child(recent_filter_button)
+        .with_child(flag_filter_button)
         .main_axis_alignment(MainAxisAlignment::Start);
 
-    let gallery = GridView::new(|| {
-        Image::new(ImageBuf::empty())
-            .interpolation_mode(InterpolationMode::NearestNeighbor)
-            .controller(FolderThumbnailController)
-            .fix_size(150., 150.)
-            .padding(5.)
-            .background(Painter::new(|ctx, (_thumbnail, _selected), _env| {
-                let is_hot = ctx.is_hot();
-                let is_active = ctx.is_active();
-                let background_color = if is_active {
-                    Color::rgb8(0x90, 0x90, 0x90)
-                } else if is_hot {
-                    Color::rgb8(0xcc, 0xcc, 0xcc)
-                } else {
-                    Color::rgb8(0xff, 0xff, 0xff)
-                };
-                let rect = ctx.size().to_rect();
-                ctx.stroke(rect, &background_color, 0.0);
-                ctx.fill(rect, &background_color);
-            }))
-            .on_click(|ctx, data, _env| {
-                ctx.submit_command(Command::new(
-                    PUSH_VIEW_WITH_SELECTED_IMAGE,
-                    (FolderView::SingleImage, data.1),
-                    Target::Auto,
-                ));
-            })
-    })
-    .wrap();
+    let scroll_id = WidgetId::next();
+    let grid = GridView::new(move || gallery_tile(scroll_id, true))
+        .wrap()
+        .align_left();
+    let masonry_rows = Rc::new(RefCell::new(JustifiedRows::default()));
+    let masonry = Justified::new(
+        move || gallery_tile(scroll_id, false),
+        |tile: &(Thumbnail, usize, bool, bool)| match tile.0.dimensions {
+            Some((width, height)) if height > 0 => width as f64 / height as f64,
+            _ => 1.,
+        },
+    )
+    .with_rows(masonry_rows.clone());
 
-    let gallery = gallery.align_left();
-    let gallery =
-        DynamicSizedBox::new(Scroll::new(gallery).vertical().expand_width())
-            .with_width(0.95);
+    let gallery = Either::new(
+        |data: &FolderGalleryState, _env| data.settings.masonry_gallery,
+        masonry,
+        grid,
+    );
+    let dated = List::new(move || date_group(scroll_id));
+    let gallery = Either::new(
+        |data: &FolderGalleryState, _env| data.groups_by_date(),
+        dated,
+        gallery,
+    );
+    let details = List::new(move || details_row(scroll_id));
+    let gallery = Either::new(
+        |data: &FolderGalleryState, _env| data.settings.details_gallery,
+        details,
+        gallery,
+    );
+    let names = GridView::new(name_tile).wrap().align_left();
+    let gallery = Either::new(
+        |data: &FolderGalleryState, _env| data.settings.list_mode,
+        names,
+        gallery,
+    )
+    .env_scope(|env, data: &FolderGalleryState| data.settings.set_env(env));
+    let gallery = DynamicSizedBox::new(
+        Scroll::new(gallery)
+            .vertical()
+            .controller(GalleryScrollController::new(masonry_rows))
+            .with_id(scroll_id)
+            .expand_width(),
+    )
+    .with_width(0.95);
+
+    // the column titles stay in place while the rows scroll
+    let details_header = Either::new(
+        |data: &FolderGalleryState, _env| {
+            data.settings.details_gallery && !data.settings.list_mode
+        },
+        DynamicSizedBox::new(details_header()).with_width(0.95),
+        SizedBox::empty(),
+    );
 
     let layout = Flex::column()
         .with_child(header)
+        .with_child(details_header)
         .with_flex_child(gallery, 1.0)
-        .expand_width()
+        .expand_width();
+    let layout = Overlay::new(layout, paint_toast)
         .background(Color::WHITE)
-        .controller(FolderViewController)
-        // TODO: this shouldn't do any work if this folder was loaded previously
-        .on_added(|_self, ctx, data, _env| {
-            let handle = ctx.get_external_handle();
-            let image_paths = data.paths.clone();
-            thread::spawn(move || {
-                for (i, path) in image_paths.iter().enumerate() {
-                    let thumbnail =
-                        create_thumbnail_from_path(&path, i).unwrap();
+        .controller(FolderViewController::new());
+    Box::new(layout)
+}
+
+// a section of a dated gallery, its date above a grid of its tiles
+fn date_group(scroll_id: WidgetId) -> impl Widget<DateGroup> {
+    let heading =
+        Label::dynamic(|group: &DateGroup, _env| group.heading.clone())
+            .with_text_color(Color::BLACK)
+            .with_text_size(16.)
+            .padding((5., 8., 5., 0.))
+            .fix_height(DATE_HEADING_HEIGHT);
+    let grid = GridView::new(move || gallery_tile(scroll_id, true))
+        .wrap()
+        .align_left();
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(heading)
+        .with_child(grid)
+}
+
+// a gallery tile is either a fixed square or fills the size the masonry
+// layout gives it
+fn gallery_tile(
+    scroll_id: WidgetId,
+    square: bool,
+) -> Box<dyn Widget<(Thumbnail, usize, bool, bool)>> {
+    let thumbnail = Image::new(ImageBuf::empty())
+        .interpolation_mode(InterpolationMode::NearestNeighbor)
+        .controller(FolderThumbnailController::new(scroll_id));
+    let thumbnail: Box<dyn Widget<(Thumbnail, usize, bool, bool)>> = if square {
+        Box::new(thumbnail.fix_size(150., 150.))
+    } else {
+        Box::new(thumbnail.expand())
+    };
+    let tile = tile(
+        Overlay::new(thumbnail, paint_badges).padding(5.),
+        tile_parts,
+    );
+    Box::new(tile)
+}
+
+// the thumbnail and index of the image a tile shows, and whether it's
+// highlighted and selected
+type TileParts<T> = fn(&T) -> (&Thumbnail, usize, bool, bool);
+
+fn tile_parts(
+    (thumbnail, idx, highlighted, selected): &(Thumbnail, usize, bool, bool),
+) -> (&Thumbnail, usize, bool, bool) {
+    (thumbnail, *idx, *highlighted, *selected)
+}
+
+// the background, clicks and context menu that the tiles of every gallery
+// layout share
+fn tile<T: Data>(
+    child: impl Widget<T> + 'static,
+    parts: TileParts<T>,
+) -> impl Widget<T> {
+    child
+        .background(Painter::new(move |ctx, data: &T, _env| {
+            let (_thumbnail, _idx, highlighted, selected) = parts(data);
+            let background_color = if ctx.is_active() {
+                Color::rgb8(0x90, 0x90, 0x90)
+            } else if highlighted {
+                Color::rgb8(0x2a, 0x82, 0xfc)
+            } else if selected {
+                Color::rgb8(0xa8, 0xcb, 0xfe)
+            } else if ctx.is_hot() {
+                Color::rgb8(0xcc, 0xcc, 0xcc)
+            } else {
+                Color::rgb8(0xff, 0xff, 0xff)
+            };
+            let rect = ctx.size().to_rect();
+            ctx.fill(rect, &background_color);
+        }))
+        .on_click(move |ctx, data: &mut T, _env| {
+            let (thumbnail, idx, ..) = parts(data);
+            if thumbnail.timed_out {
+                ctx.submit_command(RETRY_TIMED_OUT_IMAGE.with(thumbnail.id));
+                return;
+            }
+            // failures on network drives often go away on their own
+            if thumbnail.failed {
+                ctx.submit_command(REGENERATE_THUMBNAIL.with(thumbnail.id));
+                return;
+            }
+            ctx.submit_command(Command::new(
+                PUSH_VIEW_WITH_SELECTED_IMAGE,
+                (FolderView::SingleImage, idx),
+                Target::Auto,
+            ));
+        })
+        .controller(TileMenuController { parts })
+}
+
+// dragging the zoom slider keeps the image centered rather than where the
+// last pan left it
+struct ZoomSliderController;
+
+impl<W: Widget<FolderGalleryState>> Controller<FolderGalleryState, W>
+    for ZoomSliderController
+{
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut FolderGalleryState,
+        env: &Env,
+    ) {
+        let zoom = data.zoom;
+        child.event(ctx, event, data, env);
+        if data.zoom != zoom {
+            ctx.submit_command(CENTER_ZOOMED_IMAGE);
+        }
+    }
+}
+
+// right clicking a tile offers to decode its thumbnail again, for when the
+// cached one is wrong. Ctrl+clicking it adds it to the selection instead of
+// opening it
+struct TileMenuController<T> {
+    parts: TileParts<T>,
+}
+
+impl<T: Data, W: Widget<T>> Controller<T, W> for TileMenuController<T> {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut T,
+        env: &Env,
+    ) {
+        match event {
+            Event::MouseDown(mouse) if mouse.button == MouseButton::Right => {
+                let (thumbnail, idx, ..) = (self.parts)(data);
+                let mut menu = MenuDesc::<AppState>::empty()
+                    .append(copy_menu(idx, thumbnail))
+                    .append(MenuItem::new(
+                        LocalizedString::new("regenerate-thumbnail")
+                            .with_placeholder("Regenerate Thumbnail"),
+                        REGENERATE_THUMBNAIL.with(thumbnail.id),
+                    ));
+                if let Some(location) = thumbnail.location {
+                    menu = menu.append(MenuItem::new(
+                        LocalizedString::new("open-location")
+                            .with_placeholder("Show Location in Maps"),
+                        OPEN_LOCATION.with(location),
+                    ));
+                }
+                ctx.show_context_menu(ContextMenu::new(menu, mouse.window_pos));
+                ctx.set_handled();
+            }
+            Event::MouseDown(mouse)
+                if mouse.button == MouseButton::Left && mouse.mods.ctrl() =>
+            {
+                let (_, idx, ..) = (self.parts)(data);
+                ctx.submit_command(TOGGLE_IMAGE_SELECTED.with(idx));
+                ctx.set_handled();
+            }
+            _ => child.event(ctx, event, data, env),
+        }
+    }
+}
+
+// the copy submenu of the tile and image view context menus, leaving out
+// what the image doesn't have
+fn copy_menu(idx: usize, thumbnail: &Thumbnail) -> MenuDesc<AppState> {
+    let item = |id: &'static str, title: &'static str, detail| {
+        MenuItem::new(
+            LocalizedString::new(id).with_placeholder(title),
+            COPY_IMAGE_DETAIL.with((idx, detail)),
+        )
+    };
+    let mut menu = MenuDesc::new(
+        LocalizedString::new("copy-menu").with_placeholder("Copy"),
+    )
+    .append(item("copy-path", "Path", CopyDetail::Path))
+    .append(item("copy-file-name", "File Name", CopyDetail::FileName));
+    if thumbnail.dimensions.is_some() {
+        menu = menu.append(item(
+            "copy-dimensions",
+            "Dimensions",
+            CopyDetail::Dimensions,
+        ));
+    }
+    menu = menu.append(item("copy-exif", "EXIF Data", CopyDetail::Exif));
+    if thumbnail.location.is_some() {
+        menu = menu.append(item(
+            "copy-location",
+            "GPS Coordinates",
+            CopyDetail::Location,
+        ));
+    }
+    menu
+}
+
+const DETAILS_THUMBNAIL_WIDTH: f64 = 50.;
+const DETAILS_DIMENSIONS_WIDTH: f64 = 120.;
+const DETAILS_SIZE_WIDTH: f64 = 90.;
+const DETAILS_DATE_WIDTH: f64 = 100.;
+
+// the details view's column titles, clicking one sorts the rows by it
+fn details_header() -> impl Widget<FolderGalleryState> {
+    let column = |column: DetailsColumn| {
+        Button::new(
+            move |data: &FolderGalleryState, _env: &Env| match data.details_sort
+            {
+                Some((sorted, descending)) if sorted == column => {
+                    let arrow = if descending { "▼" } else { "▲" };
+                    format!("{} {}", column.label(), arrow)
+                }
+                _ => column.label().to_string(),
+            },
+            Color::BLACK,
+            Color::rgb8(0xff, 0xff, 0xff),
+            Color::rgb8(0xcc, 0xcc, 0xcc),
+            Color::rgb8(0x90, 0x90, 0x90),
+            14.,
+        )
+        .on_click(move |_ctx, data: &mut FolderGalleryState, _env| {
+            data.sort_details_by(column);
+        })
+    };
+    Flex::row()
+        .with_spacer(DETAILS_THUMBNAIL_WIDTH + 10.)
+        .with_flex_child(column(DetailsColumn::Name).align_left(), 1.)
+        .with_child(
+            column(DetailsColumn::Dimensions)
+                .fix_width(DETAILS_DIMENSIONS_WIDTH),
+        )
+        .with_child(column(DetailsColumn::Size).fix_width(DETAILS_SIZE_WIDTH))
+        .with_child(column(DetailsColumn::Date).fix_width(DETAILS_DATE_WIDTH))
+        .padding((5., 0.))
+        .border(Color::rgb8(0xcc, 0xcc, 0xcc), 1.)
+}
+
+// a small thumbnail followed by the file's name, dimensions, size and date
+fn details_row(scroll_id: WidgetId) -> impl Widget<DetailsRow> {
+    let thumbnail = Image::new(ImageBuf::empty())
+        .controller(FolderThumbnailController::new(scroll_id))
+        .lens(lens::Map::new(
+            |(thumbnail, _path, idx, highlighted, selected): &DetailsRow| {
+                (thumbnail.clone(), *idx, *highlighted, *selected)
+            },
+            |_row, _tile| (),
+        ))
+        .fix_size(DETAILS_THUMBNAIL_WIDTH, DETAILS_ROW_HEIGHT - 10.);
+    let name = Label::dynamic(|(_, path, ..): &DetailsRow, _env| {
+        path.file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    })
+    .with_text_color(Color::BLACK)
+    .with_line_break_mode(LineBreaking::Clip)
+    .expand_width();
+    let dimensions = Label::dynamic(|(thumbnail, ..): &DetailsRow, env| {
+        let locale = number_locale(env);
+        match thumbnail.dimensions {
+            Some((width, height)) => format!(
+                "{} × {}",
+                format::count(width as usize, locale),
+                format::count(height as usize, locale)
+            ),
+            None => String::new(),
+        }
+    })
+    .with_text_color(Color::BLACK)
+    .fix_width(DETAILS_DIMENSIONS_WIDTH);
+    let size = Label::dynamic(|(thumbnail, ..): &DetailsRow, env| {
+        thumbnail
+            .file_size
+            .map(|size| format::bytes(size, number_locale(env)))
+            .unwrap_or_default()
+    })
+    .with_text_color(Color::BLACK)
+    .fix_width(DETAILS_SIZE_WIDTH);
+    let date = Label::dynamic(|(thumbnail, ..): &DetailsRow, env| {
+        details_date(thumbnail)
+            .map(|date| format::date(date, number_locale(env)))
+            .unwrap_or_default()
+    })
+    .with_text_color(Color::BLACK)
+    .fix_width(DETAILS_DATE_WIDTH);
+
+    let row = Flex::row()
+        .with_child(thumbnail)
+        .with_spacer(10.)
+        .with_flex_child(name, 1.)
+        .with_child(dimensions)
+        .with_child(size)
+        .with_child(date)
+        .padding(5.)
+        .fix_height(DETAILS_ROW_HEIGHT);
+    tile(row, row_parts)
+}
+
+fn row_parts(
+    (thumbnail, _path, idx, highlighted, selected): &DetailsRow,
+) -> (&Thumbnail, usize, bool, bool) {
+    (thumbnail, *idx, *highlighted, *selected)
+}
+
+// a tile with just the file's name, for list mode
+fn name_tile() -> Box<dyn Widget<DetailsRow>> {
+    let name = Label::dynamic(|(_, path, ..): &DetailsRow, _env| {
+        path.file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    })
+    .with_text_color(Color::BLACK)
+    .with_line_break_mode(LineBreaking::Clip)
+    .fix_size(230., 24.);
+    Box::new(tile(name.padding(5.), row_parts))
+}
+
+// decodes the thumbnails of the images with these ids
+pub fn load_thumbnails(
+    handle: ExtEventSink,
+    image_paths: Vec<(u64, usize, Arc<PathBuf>)>,
+    auto_rotate: bool,
+    linear_light: bool,
+) {
+    thread::spawn(move || {
+        background::wait_for_foreground();
+        let _job = ForegroundJob::start();
+        for (id, i, path) in image_paths {
+            // the image keeps its empty placeholder when nothing could be
+            // decoded
+            let thumbnail = match create_thumbnail_from_path(
+                &path,
+                i,
+                auto_rotate,
+                linear_light,
+            ) {
+                Ok(thumbnail) => Thumbnail { id, ..thumbnail },
+                Err(err) => {
+                    error!("Error creating thumbnail: {}", err);
                     handle
-                        .submit_command(
-                            CREATED_THUMBNAIL,
-                            thumbnail,
-                            Target::Auto,
-                        )
+                        .submit_command(FAILED_THUMBNAIL, id, Target::Auto)
                         .unwrap();
+                    continue;
                 }
-            });
-        });
-    Box::new(layout)
+            };
+            handle
+                .submit_command(CREATED_THUMBNAIL, thumbnail, Target::Auto)
+                .unwrap();
+        }
+        handle
+            .submit_command(CACHE_USAGE, memory_cache::usage_mb(), Target::Auto)
+            .unwrap();
+    });
+}
+
+fn paint_badges(
+    ctx: &mut PaintCtx,
+    (thumbnail, ..): &(Thumbnail, usize, bool, bool),
+    env: &Env,
+) {
+    let label = thumbnail.format.label();
+    if env.get(SHOW_FORMAT_BADGES) && !label.is_empty() {
+        paint_badge(ctx, label, false, &Color::rgba8(0, 0, 0, 0xaa));
+    }
+    let flag_color = match thumbnail.flag {
+        Flag::Pick => Some(Color::rgb8(0x2e, 0xb8, 0x4b)),
+        Flag::Reject => Some(Color::rgb8(0xd0, 0x20, 0x20)),
+        Flag::None => None,
+    };
+    if let Some(color) = flag_color {
+        paint_flag_marker(ctx, &color);
+    }
+    let stars = "★".repeat(thumbnail.rating as usize);
+    let date = thumbnail
+        .captured
+        .or(thumbnail.modified)
+        .filter(|_| env.get(SHOW_TILE_DATES))
+        .map(|date| format::date_time(date, number_locale(env)));
+    let label = match date {
+        Some(date) if !stars.is_empty() => format!("{}  {}", stars, date),
+        Some(date) => date,
+        None => stars,
+    };
+    if !label.is_empty() {
+        paint_corner_label(ctx, &label);
+    }
+    // the partial badge takes the same corner
+    if thumbnail.raw_pair.is_some() && !thumbnail.partial {
+        paint_badge(ctx, "RAW+JPG", true, &Color::rgba8(0, 0, 0, 0xaa));
+    }
+    if thumbnail.timed_out {
+        paint_badge(
+            ctx,
+            "TIMED OUT",
+            true,
+            &Color::rgba8(0xc0, 0x80, 0x00, 0xdd),
+        );
+    }
+    if thumbnail.failed {
+        paint_badge(
+            ctx,
+            "FAILED, CLICK TO RETRY",
+            true,
+            &Color::rgba8(0xd0, 0x20, 0x20, 0xdd),
+        );
+    }
+    // always shown so broken files are easy to find
+    if thumbnail.partial {
+        paint_badge(
+            ctx,
+            "PARTIAL",
+            true,
+            &Color::rgba8(0xd0, 0x20, 0x20, 0xdd),
+        );
+    }
+}
+
+// a triangle filling the top right corner
+fn paint_flag_marker(ctx: &mut PaintCtx, color: &Color) {
+    let width = ctx.size().width;
+    let marker_size = 20.;
+    let mut marker = BezPath::new();
+    marker.move_to((width - marker_size, 0.));
+    marker.line_to((width, 0.));
+    marker.line_to((width, marker_size));
+    marker.close_path();
+    ctx.fill(marker, color);
+}
+
+// draws the tile's rating and date in the bottom right corner
+fn paint_corner_label(ctx: &mut PaintCtx, label: &str) {
+    let layout = match ctx
+        .text()
+        .new_text_layout(label.to_string())
+        .font(FontFamily::SYSTEM_UI, 10.)
+        .text_color(Color::WHITE)
+        .build()
+    {
+        Ok(layout) => layout,
+        Err(_) => return,
+    };
+    let padding = 3.;
+    let label_size = Size::new(
+        layout.size().width + padding * 2.,
+        layout.size().height + padding * 2.,
+    );
+    let origin = (
+        ctx.size().width - label_size.width - padding,
+        ctx.size().height - label_size.height - padding,
+    );
+    let label_rect = Rect::from_origin_size(origin, label_size);
+    ctx.fill(label_rect.to_rounded_rect(3.), &Color::rgba8(0, 0, 0, 0xaa));
+    ctx.draw_text(&layout, (origin.0 + padding, origin.1 + padding));
+}
+
+// draws a small label in the top left corner, or the bottom left one
+fn paint_badge(ctx: &mut PaintCtx, label: &str, bottom: bool, color: &Color) {
+    let layout = ctx
+        .text()
+        .new_text_layout(label.to_string())
+        .font(FontFamily::SYSTEM_UI, 10.)
+        .text_color(Color::WHITE)
+        .build()
+        .unwrap();
+    let padding = 3.;
+    let badge_size = Size::new(
+        layout.size().width + padding * 2.,
+        layout.size().height + padding * 2.,
+    );
+    let y = if bottom {
+        ctx.size().height - badge_size.height - padding
+    } else {
+        padding
+    };
+    let badge = Rect::from_origin_size((padding, y), badge_size);
+    ctx.fill(badge.to_rounded_rect(3.), color);
+    ctx.draw_text(&layout, (padding * 2., y + padding));
 }
 
 pub fn image_view_builder() -> Box<dyn Widget<FolderGalleryState>> {
@@ -146,8 +731,12 @@ pub fn image_view_builder() -> Box<dyn Widget<FolderGalleryState>> {
         Color::rgb8(0x90, 0x90, 0x90),
         16.,
     )
-    .on_click(|ctx, _data, _env| {
+    .on_click(|ctx, data: &mut FolderGalleryState, _env| {
         ctx.submit_command(Command::new(POP_FOLDER_VIEW, (), Target::Auto));
+        // the gallery is left right after it comes back
+        if data.settings.back_to_library {
+            ctx.submit_command(Command::new(POP_VIEW, (), Target::Auto));
+        }
     })
     .align_left()
     .fix_width(button_width);
@@ -165,33 +754,41 @@ pub fn image_view_builder() -> Box<dyn Widget<FolderGalleryState>> {
         active_color.clone(),
         16.,
     )
+    .disabled_if(FolderGalleryState::is_first_image)
     .on_click(|_ctx, data: &mut FolderGalleryState, _env| {
-        if data.paths.is_empty() || data.selected_image == 0 {
-            return;
-        }
-
-        data.selected_image -= 1;
+        data.previous_image(1);
     })
     .fix_width(button_width)
     .expand_height();
 
     let right_button =
         Button::new("❯", font_color, bg_color, hover_color, active_color, 16.)
+            .disabled_if(|data: &FolderGalleryState| !data.has_next_image())
             .on_click(|_ctx, data: &mut FolderGalleryState, _env| {
-                if data.paths.is_empty()
-                    || data.selected_image == data.paths.len() - 1
-                {
-                    return;
-                }
-                data.selected_image += 1;
+                data.next_image(1);
             })
             .fix_width(button_width)
             .expand_height();
 
     let image = Image::new(ImageBuf::empty())
         .interpolation_mode(InterpolationMode::Bilinear)
-        .fill_mode(FillStrat::Contain)
-        .controller(DisplayImageController::new());
+        .fill_mode(FillStrat::Contain);
+    let image = Zoom::new(image, |data: &FolderGalleryState, size| {
+        data.zoom_transform(size)
+    })
+    .controller(DisplayImageController::new())
+    .background(Painter::new(|ctx, data: &FolderGalleryState, env| {
+        let rect = ctx.size().to_rect();
+        ctx.fill(rect, &data.settings.letterbox_color.color());
+        paint_image_frame(ctx, data, env);
+        paint_checkerboard(ctx, data, env);
+    }));
+    let image = Overlay::new(image, paint_straighten);
+    let image = Overlay::new(image, paint_fade_transition);
+    let image = Overlay::new(image, paint_pair_badge);
+    let image = Overlay::new(image, paint_playback_badge);
+    let image = Overlay::new(image, paint_caption);
+    let image = Overlay::new(image, paint_loupe);
 
     let left_side_buttons = Flex::column()
         .with_child(back_button)
@@ -204,17 +801,598 @@ pub fn image_view_builder() -> Box<dyn Widget<FolderGalleryState>> {
         .cross_axis_alignment(CrossAxisAlignment::Center)
         .main_axis_alignment(MainAxisAlignment::SpaceBetween);
 
+    // log scaled, so zooming in and out by the same factor moves the slider
+    // the same distance
+    let zoom_slider = Slider::new()
+        .with_range(MIN_ZOOM.log10(), MAX_ZOOM.log10())
+        .lens(lens::Map::new(
+            |data: &FolderGalleryState| data.zoom.log10(),
+            |data: &mut FolderGalleryState, value: f64| {
+                // the slider writes back on every event, and going through
+                // the log would nudge a zoom it didn't change
+                if (value - data.zoom.log10()).abs() > f64::EPSILON {
+                    data.zoom = 10f64.powf(value).max(MIN_ZOOM).min(MAX_ZOOM);
+                }
+            },
+        ))
+        .controller(ZoomSliderController)
+        .expand_width();
+    let zoom_label = Label::dynamic(|data: &FolderGalleryState, _env| {
+        format!("{:.1}×", data.zoom)
+    })
+    .with_text_color(Color::rgb8(0x40, 0x40, 0x40))
+    .with_text_size(13.)
+    .fix_width(50.);
+    let zoom_bar = Flex::row()
+        .with_flex_child(zoom_slider, 1.)
+        .with_child(zoom_label)
+        .padding((10., 2.));
+
+    let status_bar =
+        Label::dynamic(|data: &FolderGalleryState, _env| data.status_text())
+            .with_text_color(Color::rgb8(0x40, 0x40, 0x40))
+            .with_text_size(13.)
+            .padding((10., 4.))
+            .expand_width()
+            .background(Color::rgb8(0xee, 0xee, 0xee));
+    let status_bar = Either::new(
+        |data: &FolderGalleryState, _env| data.settings.show_status_bar,
+        status_bar,
+        SizedBox::empty(),
+    );
+
+    let palette_panel = Either::new(
+        |data: &FolderGalleryState, _env| data.show_palette,
+        palette_panel(),
+        SizedBox::empty(),
+    );
+
     let layout = Flex::column()
         .must_fill_main_axis(true)
-        .with_flex_child(image_view, FlexParams::new(1.0, None));
+        .with_flex_child(image_view, FlexParams::new(1.0, None))
+        .with_child(palette_panel)
+        .with_child(zoom_bar)
+        .with_child(status_bar);
 
-    let container = Container::new(layout)
+    let layout = Overlay::new(layout, paint_blank);
+    let layout = Overlay::new(layout, paint_toast);
+    let layout = Overlay::new(layout, paint_goto_input);
+    let layout = Overlay::new(layout, paint_caption_input);
+    let container = Container::new(Overlay::new(layout, paint_confirm_trash))
         .background(druid::Color::rgb8(255, 255, 255))
-        .controller(ImageViewController);
+        .controller(ImageViewController::new());
 
     Box::new(container)
 }
 
+// the average and dominant colors of the shown image, clicking one puts its
+// hex value on the clipboard
+fn palette_panel() -> impl Widget<FolderGalleryState> {
+    let heading = |text: &'static str| {
+        Label::new(text)
+            .with_text_color(Color::rgb8(0x40, 0x40, 0x40))
+            .with_text_size(13.)
+    };
+    let colors = Flex::row()
+        .with_child(heading("Average"))
+        .with_spacer(6.)
+        .with_child(color_swatch().lens(lens::Map::new(
+            |palette: &palette::ImagePalette| palette.average,
+            |_, _| (),
+        )))
+        .with_spacer(16.)
+        .with_child(heading("Dominant"))
+        .with_spacer(6.)
+        .with_child(List::new(color_swatch).horizontal().lens(lens::Map::new(
+            |palette: &palette::ImagePalette| palette.dominant.clone(),
+            |_, _| (),
+        )))
+        .lens(lens::Map::new(
+            |data: &FolderGalleryState| {
+                data.palette.as_deref().cloned().unwrap_or_default()
+            },
+            |_, _| (),
+        ));
+    let working = heading("Working out colors…");
+    Either::new(
+        |data: &FolderGalleryState, _env| data.palette.is_some(),
+        colors,
+        working,
+    )
+    .padding((10., 4.))
+    .expand_width()
+    .background(Color::rgb8(0xee, 0xee, 0xee))
+}
+
+fn color_swatch() -> impl Widget<(u8, u8, u8)> {
+    let swatch = Painter::new(|ctx, (r, g, b): &(u8, u8, u8), _env| {
+        let rect = ctx.size().to_rect();
+        ctx.fill(rect, &Color::rgb8(*r, *g, *b));
+        ctx.stroke(rect, &Color::rgb8(0x90, 0x90, 0x90), 1.);
+    })
+    .fix_size(16., 16.);
+    let label =
+        Label::dynamic(|color: &(u8, u8, u8), _env| palette::hex(*color))
+            .with_font(FontDescriptor::new(FontFamily::MONOSPACE))
+            .with_text_color(Color::rgb8(0x40, 0x40, 0x40))
+            .with_text_size(12.);
+    Flex::row()
+        .with_child(swatch)
+        .with_spacer(4.)
+        .with_child(label)
+        .padding((0., 0., 8., 0.))
+        .on_click(|_ctx, color: &mut (u8, u8, u8), _env| {
+            Application::global()
+                .clipboard()
+                .put_string(palette::hex(*color));
+        })
+}
+
+// how far the arrow keys turn the image while straightening
+const STRAIGHTEN_STEP: f64 = 0.1 * PI / 180.;
+
+// while straightening, Enter saves a straightened copy and Escape or h
+// leaves without saving
+fn straighten_key_down(
+    ctx: &mut EventCtx,
+    key_event: &KeyEvent,
+    data: &mut FolderGalleryState,
+) {
+    let angle = data.straighten.unwrap_or(0.);
+    match &key_event.key {
+        KbKey::Enter => {
+            ctx.submit_command(SAVE_STRAIGHTENED.with(angle));
+            data.straighten = None;
+        }
+        KbKey::Escape => data.straighten = None,
+        KbKey::Character(key) if key.eq_ignore_ascii_case("h") => {
+            data.straighten = None;
+        }
+        KbKey::ArrowLeft => {
+            data.straighten =
+                Some((angle - STRAIGHTEN_STEP).max(-MAX_STRAIGHTEN_ANGLE));
+        }
+        KbKey::ArrowRight => {
+            data.straighten =
+                Some((angle + STRAIGHTEN_STEP).min(MAX_STRAIGHTEN_ANGLE));
+        }
+        _ => (),
+    }
+    data.straighten_line = None;
+    ctx.set_handled();
+}
+
+// while editing the caption Enter saves it and Escape leaves it as it was
+fn caption_key_down(
+    ctx: &mut EventCtx,
+    key_event: &KeyEvent,
+    data: &mut FolderGalleryState,
+) {
+    let input = match data.caption_input.as_mut() {
+        Some(input) => input,
+        None => return,
+    };
+    match &key_event.key {
+        KbKey::Enter => {
+            let caption = data.caption_input.take().unwrap();
+            data.caption_displayed_image(&caption);
+        }
+        KbKey::Escape => data.caption_input = None,
+        KbKey::Backspace => {
+            input.pop();
+        }
+        KbKey::Character(text) => input.push_str(text),
+        _ => (),
+    }
+    ctx.set_handled();
+}
+
+// while asking before trashing an image, Enter or y moves it to the trash
+// and Escape or n leaves it be, other keys do nothing until then
+fn confirm_trash_key_down(
+    ctx: &mut EventCtx,
+    key_event: &KeyEvent,
+    data: &mut FolderGalleryState,
+) {
+    match &key_event.key {
+        KbKey::Enter => confirm_trash(data),
+        KbKey::Character(key) if key.eq_ignore_ascii_case("y") => {
+            confirm_trash(data)
+        }
+        KbKey::Escape => data.confirm_trash = None,
+        KbKey::Character(key) if key.eq_ignore_ascii_case("n") => {
+            data.confirm_trash = None;
+        }
+        _ => (),
+    }
+    ctx.set_handled();
+}
+
+fn confirm_trash(data: &mut FolderGalleryState) {
+    let path = data.confirm_trash.take();
+    // the image could have been taken out of the folder in the meantime,
+    // like by a rescan
+    if path.as_ref() == data.paths.get(data.selected_image) {
+        data.trash_selected_image();
+    }
+}
+
+// the line being dragged, and the part of the view the saved copy keeps
+// with everything around it darkened
+fn paint_straighten(ctx: &mut PaintCtx, data: &FolderGalleryState, _env: &Env) {
+    let angle = match data.straighten {
+        Some(angle) => angle,
+        None => return,
+    };
+    let size = ctx.size();
+    let fitted = data.displayed_size * data.fit_scale;
+    let crop = inscribed_size(fitted.width, fitted.height, angle);
+    let crop = (Affine::translate(data.pan) * Affine::scale(data.zoom))
+        .transform_rect_bbox(Rect::from_center_size(
+            size.to_rect().center(),
+            crop,
+        ));
+    let shade = Color::rgba8(0, 0, 0, 0x99);
+    let view = size.to_rect();
+    for rect in &[
+        Rect::new(view.x0, view.y0, view.x1, crop.y0),
+        Rect::new(view.x0, crop.y1, view.x1, view.y1),
+        Rect::new(view.x0, crop.y0, crop.x0, crop.y1),
+        Rect::new(crop.x1, crop.y0, view.x1, crop.y1),
+    ] {
+        ctx.fill(rect.intersect(view), &shade);
+    }
+    ctx.stroke(crop, &Color::WHITE, 1.);
+    if let Some((start, end)) = data.straighten_line {
+        ctx.stroke(Line::new(start, end), &Color::rgb8(0xff, 0xd0, 0x00), 2.);
+    }
+    let label = format!(
+        "STRAIGHTEN {:+.1}°  drag along the horizon, Enter saves, Esc cancels",
+        angle.to_degrees()
+    );
+    paint_badge(ctx, &label, false, &Color::rgba8(0, 0, 0, 0xaa));
+}
+
+// where the shown image is drawn in the view, through the zoom and the
+// letterboxing of the fit
+fn displayed_rect(data: &FolderGalleryState, size: Size) -> Option<Rect> {
+    if data.displayed_size.width == 0. || data.displayed_size.height == 0. {
+        return None;
+    }
+    let fitted = data.displayed_size * data.fit_scale;
+    let origin = (
+        (size.width - fitted.width) / 2.,
+        (size.height - fitted.height) / 2.,
+    );
+    Some(
+        data.zoom_transform(size)
+            .transform_rect_bbox(Rect::from_origin_size(origin, fitted)),
+    )
+}
+
+// how far the shadow spreads out from the image's edges
+const FRAME_SHADOW_RADIUS: f64 = 8.;
+
+// a border or shadow drawn just outside the image, so a photo with a white
+// edge doesn't run into the view's background
+fn paint_image_frame(
+    ctx: &mut PaintCtx,
+    data: &FolderGalleryState,
+    _env: &Env,
+) {
+    // the turned image's corners wouldn't line up with the frame
+    if data.straighten.is_some() {
+        return;
+    }
+    let rect = match displayed_rect(data, ctx.size()) {
+        Some(rect) => rect,
+        None => return,
+    };
+    match data.settings.image_frame {
+        ImageFrame::None => (),
+        ImageFrame::LightBorder => {
+            ctx.stroke(rect.inset(0.5), &Color::rgb8(0xcc, 0xcc, 0xcc), 1.)
+        }
+        ImageFrame::DarkBorder => {
+            ctx.stroke(rect.inset(0.5), &Color::rgb8(0x40, 0x40, 0x40), 1.)
+        }
+        ImageFrame::Shadow => ctx.blurred_rect(
+            rect + Vec2::new(0., 2.),
+            FRAME_SHADOW_RADIUS,
+            &Color::rgba8(0, 0, 0, 0x60),
+        ),
+    }
+}
+
+// size of a checkerboard square on screen, whatever the zoom
+const CHECKER_SIZE: f64 = 8.;
+
+// drawn under the image where it's shown, so it shows through the image's
+// transparent parts
+fn paint_checkerboard(
+    ctx: &mut PaintCtx,
+    data: &FolderGalleryState,
+    _env: &Env,
+) {
+    if !data.settings.checkerboard_background {
+        return;
+    }
+    let bounds = match displayed_rect(data, ctx.size()) {
+        Some(rect) => rect.intersect(ctx.size().to_rect()),
+        None => return,
+    };
+    if bounds.area() <= 0. {
+        return;
+    }
+    ctx.fill(bounds, &Color::WHITE);
+    let columns = (bounds.width() / CHECKER_SIZE).ceil() as usize;
+    let rows = (bounds.height() / CHECKER_SIZE).ceil() as usize;
+    let mut squares = BezPath::new();
+    for row in 0..rows {
+        for column in (row % 2..columns).step_by(2) {
+            let square = Rect::from_origin_size(
+                (
+                    bounds.x0 + column as f64 * CHECKER_SIZE,
+                    bounds.y0 + row as f64 * CHECKER_SIZE,
+                ),
+                (CHECKER_SIZE, CHECKER_SIZE),
+            )
+            .intersect(bounds);
+            squares.extend(square.path_elements(0.1));
+        }
+    }
+    ctx.fill(squares, &Color::rgb8(0xcc, 0xcc, 0xcc));
+}
+
+// covers the whole image view while it's blanked
+fn paint_blank(ctx: &mut PaintCtx, data: &FolderGalleryState, _env: &Env) {
+    if data.blanked {
+        let rect = ctx.size().to_rect();
+        ctx.fill(rect, &Color::BLACK);
+    }
+}
+
+// the go to image input, or its error, in a box at the top of the view
+fn paint_goto_input(ctx: &mut PaintCtx, data: &FolderGalleryState, _env: &Env) {
+    let (text, color) = match (&data.goto_input, &data.goto_error) {
+        (Some(input), _) => (
+            format!("Go to image number, name or caption: {}_", input),
+            Color::WHITE,
+        ),
+        (None, Some(error)) => (error.clone(), Color::rgb8(0xff, 0x80, 0x80)),
+        (None, None) => return,
+    };
+    paint_input_box(ctx, text, color);
+}
+
+// the caption being written for the shown image, in the same box as the go
+// to input
+fn paint_caption_input(
+    ctx: &mut PaintCtx,
+    data: &FolderGalleryState,
+    _env: &Env,
+) {
+    if let Some(input) = &data.caption_input {
+        paint_input_box(ctx, format!("Caption: {}_", input), Color::WHITE);
+    }
+}
+
+fn paint_input_box(ctx: &mut PaintCtx, text: String, color: Color) {
+    let layout = ctx
+        .text()
+        .new_text_layout(text)
+        .font(FontFamily::SYSTEM_UI, 14.)
+        .text_color(color)
+        .build()
+        .unwrap();
+    let padding = 8.;
+    let size = Size::new(
+        layout.size().width + padding * 2.,
+        layout.size().height + padding * 2.,
+    );
+    let origin = ((ctx.size().width - size.width) / 2., padding);
+    let rect = Rect::from_origin_size(origin, size);
+    ctx.fill(rect.to_rounded_rect(4.), &Color::rgba8(0, 0, 0, 0xcc));
+    ctx.draw_text(&layout, (origin.0 + padding, origin.1 + padding));
+}
+
+// asks whether to move the image to the trash, in a box in the middle of
+// the view
+fn paint_confirm_trash(
+    ctx: &mut PaintCtx,
+    data: &FolderGalleryState,
+    _env: &Env,
+) {
+    let path = match &data.confirm_trash {
+        Some(path) => path,
+        None => return,
+    };
+    let name = path
+        .file_name()
+        .map_or_else(|| path.to_string_lossy(), |name| name.to_string_lossy());
+    let text = format!(
+        "Move {} to the trash?\nEnter to confirm, Escape to cancel",
+        name
+    );
+    let layout = ctx
+        .text()
+        .new_text_layout(text)
+        .font(FontFamily::SYSTEM_UI, 14.)
+        .text_color(Color::WHITE)
+        .build()
+        .unwrap();
+    let padding = 12.;
+    let size = Size::new(
+        layout.size().width + padding * 2.,
+        layout.size().height + padding * 2.,
+    );
+    let origin = (
+        (ctx.size().width - size.width) / 2.,
+        (ctx.size().height - size.height) / 2.,
+    );
+    let rect = Rect::from_origin_size(origin, size);
+    ctx.fill(rect.to_rounded_rect(4.), &Color::rgba8(0, 0, 0, 0xdd));
+    ctx.draw_text(&layout, (origin.0 + padding, origin.1 + padding));
+}
+
+// a short message in a box at the bottom of the view
+fn paint_toast(ctx: &mut PaintCtx, data: &FolderGalleryState, _env: &Env) {
+    if let Some(text) = &data.toast {
+        paint_toast_message(ctx, text);
+    }
+}
+
+// says which half of a RAW+JPEG pair is on screen
+fn paint_pair_badge(ctx: &mut PaintCtx, data: &FolderGalleryState, _env: &Env) {
+    let has_pair = data
+        .images
+        .get(data.displayed_image())
+        .map_or(false, |thumbnail| thumbnail.raw_pair.is_some());
+    if !has_pair {
+        return;
+    }
+    let label = if data.show_raw {
+        "RAW (r for JPEG)"
+    } else {
+        "JPEG (r for RAW)"
+    };
+    paint_badge(ctx, label, false, &Color::rgba8(0, 0, 0, 0xaa));
+}
+
+fn paint_caption(ctx: &mut PaintCtx, data: &FolderGalleryState, _env: &Env) {
+    if let Some(caption) = data.caption_text() {
+        let size = ctx.size();
+        draw_caption(
+            ctx.render_ctx,
+            size,
+            &caption,
+            data.settings.caption_position,
+        );
+    }
+}
+
+// a semi-transparent box with the caption in a corner of an area this size,
+// shared by the image view and exported views so both look the same
+pub fn draw_caption(
+    rc: &mut impl RenderContext,
+    size: Size,
+    caption: &str,
+    position: CaptionPosition,
+) {
+    let layout = match rc
+        .text()
+        .new_text_layout(caption.to_string())
+        .font(FontFamily::SYSTEM_UI, 13.)
+        .text_color(Color::WHITE)
+        .build()
+    {
+        Ok(layout) => layout,
+        Err(_) => return,
+    };
+    let padding = 8.;
+    let margin = 12.;
+    let box_size = Size::new(
+        layout.size().width + padding * 2.,
+        layout.size().height + padding * 2.,
+    );
+    let x = match position {
+        CaptionPosition::TopLeft | CaptionPosition::BottomLeft => margin,
+        _ => size.width - box_size.width - margin,
+    };
+    let y = match position {
+        CaptionPosition::TopLeft | CaptionPosition::TopRight => margin,
+        _ => size.height - box_size.height - margin,
+    };
+    let rect = Rect::from_origin_size((x, y), box_size);
+    rc.fill(rect.to_rounded_rect(4.), &Color::rgba8(0, 0, 0, 0x99));
+    rc.draw_text(&layout, (x + padding, y + padding));
+}
+
+// the pixels around the cursor magnified next to it, with the one under the
+// cursor outlined and its value below
+fn paint_loupe(ctx: &mut PaintCtx, data: &FolderGalleryState, _env: &Env) {
+    let (pos, pixels) = match &data.loupe {
+        Some(loupe) => loupe,
+        None => return,
+    };
+    let side = 120.;
+    let cell = side / pixels.width() as f64;
+    // flips to the other side of the cursor near the right and bottom edges
+    let x = if pos.x + 16. + side > ctx.size().width {
+        pos.x - 16. - side
+    } else {
+        pos.x + 16.
+    };
+    let y = if pos.y + 16. + side + 20. > ctx.size().height {
+        pos.y - 16. - side - 20.
+    } else {
+        pos.y + 16.
+    };
+    let rect = Rect::from_origin_size((x, y), (side, side));
+    let image = pixels.to_image(ctx.render_ctx);
+    ctx.fill(rect, &Color::rgb8(0x20, 0x20, 0x20));
+    ctx.draw_image(&image, rect, InterpolationMode::NearestNeighbor);
+    ctx.stroke(rect, &Color::WHITE, 2.);
+    let center = (pixels.width() / 2) as f64 * cell;
+    let pixel = Rect::from_origin_size((x + center, y + center), (cell, cell));
+    ctx.stroke(pixel, &Color::WHITE, 1.);
+
+    let raw = pixels.raw_pixels();
+    let offset = (pixels.width() * pixels.height() / 2) * 4;
+    let label = format!(
+        "#{:02X}{:02X}{:02X}",
+        raw[offset],
+        raw[offset + 1],
+        raw[offset + 2]
+    );
+    let layout = ctx
+        .text()
+        .new_text_layout(label)
+        .font(FontFamily::MONOSPACE, 12.)
+        .text_color(Color::WHITE)
+        .build()
+        .unwrap();
+    let label_rect = Rect::from_origin_size((x, y + side), (side, 20.));
+    ctx.fill(label_rect, &Color::rgba8(0, 0, 0, 0xcc));
+    ctx.draw_text(&layout, (x + 4., y + side + 3.));
+}
+
+// the frame of the sequence being played back
+fn paint_playback_badge(
+    ctx: &mut PaintCtx,
+    data: &FolderGalleryState,
+    _env: &Env,
+) {
+    if !data.playing {
+        return;
+    }
+    let (start, end) = data.sequence_range();
+    let label = format!(
+        "Frame {}/{} at {} fps (a to pause)",
+        data.selected_image - start + 1,
+        end - start + 1,
+        data.settings.capped_playback_fps()
+    );
+    paint_badge(ctx, &label, true, &Color::rgba8(0, 0, 0, 0xaa));
+}
+
+// fades the image in from the background during a slideshow transition
+fn paint_fade_transition(
+    ctx: &mut PaintCtx,
+    data: &FolderGalleryState,
+    _env: &Env,
+) {
+    if data.settings.slideshow_transition != SlideshowTransition::Fade
+        || data.transition >= 1.
+    {
+        return;
+    }
+    let rect = ctx.size().to_rect();
+    // fades in from the letterbox, which is what's around the image
+    let color = data.settings.letterbox_color.color();
+    ctx.fill(rect, &color.with_alpha(1. - data.transition));
+}
+
 // TODO: this will eventually be an alternative view for the folder view
 // pub fn filmstrip_view_builder() -> Box<dyn Widget<AppState>> {
 //     let button_width = 50.0;
@@ -342,7 +1520,7 @@ pub fn image_view_builder() -> Box<dyn Widget<FolderGalleryState>> {
 //     )
 // }
 
-fn create_thumbnail(index: usize, image: RgbImage) -> Thumbnail {
+fn resize_to_thumbnail(image: &RgbImage, linear_light: bool) -> RgbImage {
     let (width, height) = image.dimensions();
     let (new_width, new_height) = {
         let max_height = 150.0;
@@ -351,7 +1529,15 @@ fn create_thumbnail(index: usize, image: RgbImage) -> Thumbnail {
         let scaled_height = height as f64 * scale;
         (scaled_width.trunc() as u32, scaled_height.trunc() as u32)
     };
-    let image = thumbnail(&image, new_width, new_height);
+    downscale(image, new_width, new_height, linear_light)
+}
+
+fn create_thumbnail(
+    index: usize,
+    image: RgbImage,
+    format: FileFormat,
+    partial: bool,
+) -> Thumbnail {
     let (width, height) = image.dimensions();
     let image = ImageBuf::from_raw(
         image.into_raw(),
@@ -359,19 +1545,155 @@ fn create_thumbnail(index: usize, image: RgbImage) -> Thumbnail {
         width as usize,
         height as usize,
     );
-    Thumbnail { index, image }
+    Thumbnail {
+        id: 0,
+        index,
+        image,
+        format,
+        partial,
+        captured: None,
+        flag: Flag::None,
+        rating: 0,
+        caption: None,
+        dimensions: None,
+        raw_pair: None,
+        timed_out: false,
+        modified: None,
+        file_size: None,
+        location: None,
+        failed: false,
+    }
 }
 
+// the caches keep thumbnails as stored, they get turned upright on the way
+// out when auto_rotate is set
 pub fn create_thumbnail_from_path(
     path: &Path,
     idx: usize,
+    auto_rotate: bool,
+    linear_light: bool,
 ) -> Result<Thumbnail, ImageError> {
-    let image = Reader::open(path)?;
-    let image = image.decode()?.to_rgb8();
-    Ok(create_thumbnail(idx, image))
+    let format = FileFormat::from_path(path);
+    let kind = if linear_light {
+        CacheKind::LinearThumbnail
+    } else {
+        CacheKind::Thumbnail
+    };
+    let orient = |image| {
+        if auto_rotate {
+            apply_orientation(image, metadata::orientation(path))
+        } else {
+            image
+        }
+    };
+    if let Some(image) = memory_cache::get(kind, path) {
+        let image = orient(image.as_ref().clone());
+        return Ok(create_thumbnail(idx, image, format, false));
+    }
+    if let Some(image) = thumbnail_cache::load(path, linear_light) {
+        memory_cache::insert(kind, path, Arc::new(image.clone()));
+        return Ok(create_thumbnail(idx, orient(image), format, false));
+    }
+    let (image, partial) = decode_lenient(path)?;
+    let image = resize_to_thumbnail(&image, linear_light);
+    // partial thumbnails are never cached so they keep being flagged, and a
+    // repaired file gets a proper thumbnail
+    if !partial {
+        thumbnail_cache::store(path, &image, linear_light);
+        memory_cache::insert(kind, path, Arc::new(image.clone()));
+    }
+    Ok(create_thumbnail(idx, orient(image), format, partial))
+}
+
+// how long an unmatched go to input keeps showing its error
+const GOTO_ERROR_DURATION: Duration = Duration::from_secs(2);
+
+struct ImageViewController {
+    slideshow_timer: TimerToken,
+    flicker_timer: TimerToken,
+    goto_error_timer: TimerToken,
+    playback_timer: TimerToken,
+    toast_timer: TimerToken,
 }
 
-struct ImageViewController;
+impl ImageViewController {
+    fn new() -> Self {
+        Self {
+            slideshow_timer: TimerToken::INVALID,
+            flicker_timer: TimerToken::INVALID,
+            goto_error_timer: TimerToken::INVALID,
+            playback_timer: TimerToken::INVALID,
+            toast_timer: TimerToken::INVALID,
+        }
+    }
+
+    // while the go to input is open it takes every key press
+    fn goto_key_down(
+        &mut self,
+        ctx: &mut EventCtx,
+        key_event: &KeyEvent,
+        data: &mut FolderGalleryState,
+    ) {
+        let input = match data.goto_input.as_mut() {
+            Some(input) => input,
+            None => return,
+        };
+        match &key_event.key {
+            KbKey::Enter => {
+                let query = data.goto_input.take().unwrap();
+                match data.find_image(&query) {
+                    Some(idx) => data.select_image(idx),
+                    None => {
+                        data.goto_error =
+                            Some(format!("No image matches \"{}\"", query));
+                        self.goto_error_timer =
+                            ctx.request_timer(GOTO_ERROR_DURATION);
+                    }
+                }
+            }
+            KbKey::Escape => data.goto_input = None,
+            KbKey::Backspace => {
+                input.pop();
+            }
+            KbKey::Character(text) => input.push_str(text),
+            _ => (),
+        }
+        ctx.set_handled();
+    }
+
+    fn schedule_flicker(
+        &mut self,
+        ctx: &mut EventCtx,
+        data: &FolderGalleryState,
+    ) {
+        let interval = Duration::from_secs_f64(data.settings.flicker_interval);
+        self.flicker_timer = ctx.request_timer(interval);
+    }
+
+    fn schedule_slide(
+        &mut self,
+        ctx: &mut EventCtx,
+        data: &FolderGalleryState,
+    ) {
+        let interval = if data.slideshow_in_sequence() {
+            data.settings.frame_interval()
+        } else {
+            Duration::from_secs_f64(data.settings.slideshow_interval)
+        };
+        self.slideshow_timer = ctx.request_timer(interval);
+        if data.settings.slideshow_prefetch > 0 {
+            prefetch_full_images(data.slideshow_prefetch_paths());
+        }
+    }
+
+    fn schedule_frame(
+        &mut self,
+        ctx: &mut EventCtx,
+        data: &FolderGalleryState,
+    ) {
+        self.playback_timer = ctx.request_timer(data.settings.frame_interval());
+    }
+}
 
 impl Controller<FolderGalleryState, Container<FolderGalleryState>>
     for ImageViewController
@@ -389,10 +1711,361 @@ impl Controller<FolderGalleryState, Container<FolderGalleryState>>
                 if select_image.is(SELECT_IMAGE_SELECTOR) =>
             {
                 let index = select_image.get_unchecked(SELECT_IMAGE_SELECTOR);
-                data.selected_image = *index;
+                data.select_image(*index);
+            }
+            Event::Timer(token) if *token == self.slideshow_timer => {
+                if data.slideshow {
+                    data.advance_slideshow();
+                    self.schedule_slide(ctx, data);
+                }
+            }
+            Event::Timer(token) if *token == self.playback_timer => {
+                if data.playing {
+                    data.advance_playback();
+                    self.schedule_frame(ctx, data);
+                }
+            }
+            Event::Timer(token) if *token == self.goto_error_timer => {
+                data.goto_error = None;
+            }
+            Event::Timer(token) if *token == self.toast_timer => {
+                data.toast = None;
             }
+            Event::KeyDown(key_event) if data.goto_input.is_some() => {
+                self.goto_key_down(ctx, key_event, data);
+                return;
+            }
+            Event::KeyDown(key_event) if data.caption_input.is_some() => {
+                caption_key_down(ctx, key_event, data);
+                return;
+            }
+            Event::KeyDown(key_event) if data.straighten.is_some() => {
+                straighten_key_down(ctx, key_event, data);
+                return;
+            }
+            Event::KeyDown(key_event) if data.confirm_trash.is_some() => {
+                confirm_trash_key_down(ctx, key_event, data);
+                return;
+            }
+            // clicking anywhere while asking about the trash cancels it
+            Event::MouseDown(_) if data.confirm_trash.is_some() => {
+                data.confirm_trash = None;
+                ctx.set_handled();
+                return;
+            }
+            Event::Timer(token) if *token == self.flicker_timer => {
+                if data.flicker {
+                    data.flicker_showing_compare =
+                        !data.flicker_showing_compare;
+                    self.schedule_flicker(ctx, data);
+                }
+            }
+            // the side buttons go back and forward like in a browser, going
+            // back from the first image leaves the image view
+            Event::MouseDown(mouse) if mouse.button == MouseButton::X1 => {
+                if data.is_first_image() {
+                    ctx.submit_command(Command::new(
+                        POP_FOLDER_VIEW,
+                        (),
+                        Target::Auto,
+                    ));
+                } else {
+                    data.previous_image(1);
+                }
+                ctx.set_handled();
+                return;
+            }
+            Event::MouseDown(mouse) if mouse.button == MouseButton::X2 => {
+                data.next_image(1);
+                ctx.set_handled();
+                return;
+            }
+            Event::MouseDown(mouse) if mouse.button == MouseButton::Right => {
+                let idx = data.displayed_image();
+                if let Some(thumbnail) = data.images.get(idx) {
+                    let menu = MenuDesc::<AppState>::empty()
+                        .append(copy_menu(idx, thumbnail));
+                    ctx.show_context_menu(ContextMenu::new(
+                        menu,
+                        mouse.window_pos,
+                    ));
+                }
+                ctx.set_handled();
+                return;
+            }
+            Event::Command(cmd) if cmd.is(COPY_IMAGE_DETAIL) => {
+                let (idx, detail) = *cmd.get_unchecked(COPY_IMAGE_DETAIL);
+                data.toast = Some(data.copy_image_detail(idx, detail));
+                self.toast_timer = ctx.request_timer(TOAST_DURATION);
+                ctx.set_handled();
+                return;
+            }
+            // letting go of the peek key goes back to the selected image
+            Event::KeyUp(KeyEvent {
+                key: KbKey::Character(key),
+                ..
+            }) if data.peeking && data.settings.peek_key.matches(key) => {
+                data.peeking = false;
+            }
+            // going to another folder lands in its gallery
+            Event::KeyDown(key_event)
+                if data.settings.folder_keys.direction(key_event).is_some() =>
+            {
+                let forward =
+                    data.settings.folder_keys.direction(key_event).unwrap();
+                if data.open_adjacent_folder(forward) {
+                    ctx.submit_command(Command::new(
+                        POP_FOLDER_VIEW,
+                        (),
+                        Target::Auto,
+                    ));
+                } else {
+                    data.toast = Some(end_of_library(forward));
+                    self.toast_timer = ctx.request_timer(TOAST_DURATION);
+                }
+            }
+            Event::KeyDown(key_event) => match &key_event.key {
+                // a tall image that's being read pages down with space and
+                // the down keys, and up with the up keys or shift+space.
+                // Space goes on to the next image from the bottom
+                KbKey::Character(key)
+                    if key == " " && data.reads_tall_image() =>
+                {
+                    let pages = if key_event.mods.shift() { -1. } else { 1. };
+                    ctx.submit_command(SCROLL_IMAGE_PAGE.with((pages, true)));
+                }
+                KbKey::ArrowDown | KbKey::PageDown
+                    if data.reads_tall_image() =>
+                {
+                    ctx.submit_command(SCROLL_IMAGE_PAGE.with((1., false)));
+                }
+                KbKey::ArrowUp | KbKey::PageUp if data.reads_tall_image() => {
+                    ctx.submit_command(SCROLL_IMAGE_PAGE.with((-1., false)));
+                }
+                KbKey::ArrowLeft if key_event.mods.shift() => {
+                    data.previous_image(data.settings.navigation_step);
+                }
+                KbKey::ArrowRight if key_event.mods.shift() => {
+                    data.next_image(data.settings.navigation_step);
+                }
+                KbKey::ArrowLeft => data.previous_image(1),
+                KbKey::ArrowRight => data.next_image(1),
+                KbKey::PageUp => {
+                    data.previous_image(data.settings.navigation_step);
+                }
+                KbKey::PageDown => {
+                    data.next_image(data.settings.navigation_step);
+                }
+                // forces nearest neighbor interpolation regardless of how
+                // much the image is scaled
+                KbKey::Character(key) if key.eq_ignore_ascii_case("n") => {
+                    data.force_nearest_interpolation =
+                        !data.force_nearest_interpolation;
+                }
+                KbKey::Character(key) if key.eq_ignore_ascii_case("s") => {
+                    data.slideshow = !data.slideshow;
+                    if data.slideshow {
+                        data.playing = false;
+                        self.schedule_slide(ctx, data);
+                    }
+                }
+                KbKey::Character(key)
+                    if key.eq_ignore_ascii_case("z")
+                        && (key_event.mods.ctrl() || key_event.mods.meta()) =>
+                {
+                    data.undo();
+                }
+                KbKey::Delete if data.shows_download() => {
+                    data.toast = Some(
+                        "Downloaded images can't be moved to the trash"
+                            .to_string(),
+                    );
+                    self.toast_timer = ctx.request_timer(TOAST_DURATION);
+                }
+                KbKey::Delete if data.settings.confirm_trash => {
+                    data.confirm_trash =
+                        data.paths.get(data.selected_image).cloned();
+                }
+                KbKey::Delete => data.trash_selected_image(),
+                KbKey::Character(key)
+                    if key == ":"
+                        || (key.eq_ignore_ascii_case("g")
+                            && key_event.mods.ctrl()) =>
+                {
+                    data.goto_input = Some(String::new());
+                    data.goto_error = None;
+                }
+                KbKey::Character(key) if key.eq_ignore_ascii_case("p") => {
+                    data.toggle_flag(Flag::Pick);
+                }
+                KbKey::Character(key) if key.eq_ignore_ascii_case("x") => {
+                    data.toggle_flag(Flag::Reject);
+                }
+                // 1 to 5 rate the image and 0 clears its rating
+                KbKey::Character(key)
+                    if matches!(
+                        key.as_str(),
+                        "0" | "1" | "2" | "3" | "4" | "5"
+                    ) && !key_event.mods.ctrl() =>
+                {
+                    data.rate_selected_image(key.parse().unwrap_or(0));
+                }
+                // copies the previous image's flag and rating onto this one
+                KbKey::Character(key) if key.eq_ignore_ascii_case("v") => {
+                    data.toast = Some(data.copy_previous_flag());
+                    self.toast_timer = ctx.request_timer(TOAST_DURATION);
+                }
+                KbKey::Character(key)
+                    if key.eq_ignore_ascii_case("c")
+                        && key_event.mods.ctrl()
+                        && key_event.mods.shift() =>
+                {
+                    data.toast = Some(data.copy_folder_path());
+                    self.toast_timer = ctx.request_timer(TOAST_DURATION);
+                }
+                // marks the current image as the one to compare against
+                KbKey::Character(key) if key.eq_ignore_ascii_case("c") => {
+                    data.compare_image =
+                        if data.compare_image == Some(data.selected_image) {
+                            None
+                        } else {
+                            Some(data.selected_image)
+                        };
+                    if data.compare_image.is_none() {
+                        data.flicker = false;
+                    }
+                }
+                // blinks between the current image and the compare image at
+                // the same zoom and pan, so differences stand out
+                KbKey::Character(key) if key.eq_ignore_ascii_case("f") => {
+                    if data.compare_image.is_some() {
+                        data.flicker = !data.flicker;
+                        data.flicker_showing_compare = false;
+                        if data.flicker {
+                            self.schedule_flicker(ctx, data);
+                        }
+                    }
+                }
+                // shows the previously viewed image for as long as the key
+                // is held, at the same zoom and pan
+                KbKey::Character(key)
+                    if data.settings.peek_key.matches(key)
+                        && data.last_viewed.is_some() =>
+                {
+                    data.peeking = true;
+                }
+                KbKey::Character(key) if key.eq_ignore_ascii_case("e") => {
+                    ctx.submit_command(EXPORT_VIEW);
+                }
+                // plays or pauses the numbered sequence, the arrow keys
+                // step through it a frame at a time
+                KbKey::Character(key) if key.eq_ignore_ascii_case("a") => {
+                    data.playing = !data.playing;
+                    if data.playing {
+                        data.slideshow = false;
+                        self.schedule_frame(ctx, data);
+                    }
+                }
+                KbKey::Character(key) if key.eq_ignore_ascii_case("m") => {
+                    data.settings.show_caption = !data.settings.show_caption;
+                }
+                // edits the caption the user wrote for the shown image
+                KbKey::Character(key) if key.eq_ignore_ascii_case("l") => {
+                    let caption = data
+                        .images
+                        .get(data.displayed_image())
+                        .and_then(|thumbnail| thumbnail.caption.as_ref())
+                        .map_or_else(String::new, |caption| {
+                            caption.to_string()
+                        });
+                    data.caption_input = Some(caption);
+                }
+                KbKey::Character(key) if key.eq_ignore_ascii_case("k") => {
+                    data.show_palette = !data.show_palette;
+                }
+                KbKey::Character(key) if key.eq_ignore_ascii_case("d") => {
+                    data.eyedropper = !data.eyedropper;
+                    data.loupe = None;
+                }
+                KbKey::Character(key) if key.eq_ignore_ascii_case("b") => {
+                    data.blanked = !data.blanked;
+                }
+                // flips back to the gallery, which comes back scrolled to
+                // this image
+                KbKey::Tab => {
+                    ctx.submit_command(Command::new(
+                        POP_FOLDER_VIEW,
+                        (),
+                        Target::Auto,
+                    ));
+                }
+                // keeps a copy of the image in the favorites folder, without
+                // asking where
+                KbKey::Enter => data.copy_to_favorites(ctx),
+                KbKey::Character(key) if key == "*" => {
+                    data.copy_to_favorites(ctx)
+                }
+                KbKey::Character(key) if key.eq_ignore_ascii_case("t") => {
+                    data.settings.click_to_advance =
+                        !data.settings.click_to_advance;
+                }
+                // turns the image so a line dragged over it ends up level
+                KbKey::Character(key) if key.eq_ignore_ascii_case("h") => {
+                    data.straighten = Some(0.);
+                    data.straighten_line = None;
+                    data.eyedropper = false;
+                    data.loupe = None;
+                    data.slideshow = false;
+                    data.playing = false;
+                }
+                KbKey::Character(key) if key.eq_ignore_ascii_case("w") => {
+                    data.settings.fit_window_to_image =
+                        !data.settings.fit_window_to_image;
+                }
+                KbKey::Character(key)
+                    if key == "," && key_event.mods.ctrl() =>
+                {
+                    open_preferences(ctx);
+                }
+                KbKey::Character(key) if key.eq_ignore_ascii_case("i") => {
+                    data.settings.show_status_bar =
+                        !data.settings.show_status_bar;
+                }
+                // switches between the halves of a RAW+JPEG pair
+                KbKey::Character(key) if key.eq_ignore_ascii_case("r") => {
+                    data.show_raw = !data.show_raw;
+                }
+                // jumps to the image in the folder it actually lives in,
+                // useful when subfolders are combined into one gallery
+                KbKey::Character(key) if key.eq_ignore_ascii_case("o") => {
+                    let path = data.paths[data.selected_image].clone();
+                    if path.parent() != Some(data.name.as_path()) {
+                        ctx.submit_command(Command::new(
+                            OPEN_CONTAINING_FOLDER,
+                            path,
+                            Target::Auto,
+                        ));
+                    }
+                }
+                _ => (),
+            },
             _ => (),
         }
         child.event(ctx, event, data, env);
     }
+
+    fn lifecycle(
+        &mut self,
+        child: &mut Container<FolderGalleryState>,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &FolderGalleryState,
+        env: &Env,
+    ) {
+        // the image view needs focus to receive key events
+        if let LifeCycle::WidgetAdded = event {
+            ctx.request_focus();
+        }
+        child.lifecycle(ctx, event, data, env)
+    }
 }
@@ -0,0 +1,152 @@
+use crate::settings::{DateGrouping, NumberLocale};
+
+// separates groups of thousands, 1234567 becomes 1,234,567 in the US
+pub fn count(count: usize, locale: NumberLocale) -> String {
+    let digits = count.to_string();
+    let mut formatted = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            formatted.push(locale.resolve().thousands_separator());
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+// a byte size in the largest unit that keeps it above 1, like 1.5 MB
+pub fn bytes(bytes: u64, locale: NumberLocale) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024. && unit < UNITS.len() - 1 {
+        size /= 1024.;
+        unit += 1;
+    }
+    if unit == 0 {
+        return format!("{} {}", count(bytes as usize, locale), UNITS[0]);
+    }
+    let size = format!("{:.1}", size)
+        .replace('.', &locale.resolve().decimal_separator().to_string());
+    format!("{} {}", size, UNITS[unit])
+}
+
+// a date given in seconds since the unix epoch, in the locale's usual order
+pub fn date(seconds: i64, locale: NumberLocale) -> String {
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86400));
+    match locale.resolve() {
+        NumberLocale::German => format!("{:02}.{:02}.{}", day, month, year),
+        NumberLocale::French => format!("{:02}/{:02}/{}", day, month, year),
+        _ => format!("{:02}/{:02}/{}", month, day, year),
+    }
+}
+
+// the date along with the hour and minute, like 05/14/2021 18:32
+pub fn date_time(seconds: i64, locale: NumberLocale) -> String {
+    let minutes = seconds.div_euclid(60).rem_euclid(24 * 60);
+    format!(
+        "{} {:02}:{:02}",
+        date(seconds, locale),
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+// the heading of a gallery section, the date for a day, like "June 2021"
+// for a month and just the year for a year
+pub fn date_heading(
+    seconds: i64,
+    grouping: DateGrouping,
+    locale: NumberLocale,
+) -> String {
+    let (year, month, _day) = civil_from_days(seconds.div_euclid(86400));
+    match grouping {
+        DateGrouping::Off | DateGrouping::Day => date(seconds, locale),
+        DateGrouping::Month => {
+            format!("{} {}", MONTHS[month as usize - 1], year)
+        }
+        DateGrouping::Year => year.to_string(),
+    }
+}
+
+// images with the same key go in the same gallery section
+pub fn date_group_key(seconds: i64, grouping: DateGrouping) -> (i64, i64, i64) {
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86400));
+    match grouping {
+        DateGrouping::Off | DateGrouping::Day => (year, month, day),
+        DateGrouping::Month => (year, month, 0),
+        DateGrouping::Year => (year, 0, 0),
+    }
+}
+
+// the proleptic gregorian date a number of days after 1970-01-01 falls on
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = if days >= 0 { days } else { days - 146096 } / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524
+        - day_of_era / 146096)
+        / 365;
+    let day_of_year =
+        day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_thousands() {
+        let us = NumberLocale::UnitedStates;
+        assert_eq!(count(0, us), "0");
+        assert_eq!(count(999, us), "999");
+        assert_eq!(count(1000, us), "1,000");
+        assert_eq!(count(1234567, us), "1,234,567");
+        assert_eq!(count(1234567, NumberLocale::German), "1.234.567");
+        assert_eq!(
+            count(1234567, NumberLocale::French),
+            "1\u{202f}234\u{202f}567"
+        );
+    }
+
+    #[test]
+    fn formats_byte_sizes() {
+        let us = NumberLocale::UnitedStates;
+        assert_eq!(bytes(0, us), "0 B");
+        assert_eq!(bytes(1023, us), "1,023 B");
+        assert_eq!(bytes(1024, us), "1.0 KB");
+        assert_eq!(bytes(1536, us), "1.5 KB");
+        assert_eq!(bytes(1572864, us), "1.5 MB");
+        assert_eq!(bytes(u64::MAX, us), "16777216.0 TB");
+    }
+
+    #[test]
+    fn byte_sizes_use_the_decimal_separator() {
+        assert_eq!(bytes(1536, NumberLocale::German), "1,5 KB");
+        assert_eq!(bytes(1536, NumberLocale::French), "1,5 KB");
+        assert_eq!(bytes(1023, NumberLocale::German), "1.023 B");
+    }
+}
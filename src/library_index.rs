@@ -0,0 +1,158 @@
+use std::{
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+    thread,
+};
+
+use log::error;
+
+use crate::{background::WriteJob, thumbnail_cache};
+
+// the folders the last finished scan found and their images, so the next
+// start can show them from the thumbnail cache before it's scanned again. A
+// "folder <path>" line is followed by an "image <path>" line for each image
+fn index_path() -> PathBuf {
+    thumbnail_cache::cache_dir().join("library-index")
+}
+
+pub struct IndexedFolder {
+    pub name: PathBuf,
+    pub paths: Vec<PathBuf>,
+}
+
+// the indexed folders below the root, an unreadable index has none
+pub fn load(root: &Path) -> Vec<IndexedFolder> {
+    match fs::read_to_string(index_path()) {
+        Ok(contents) => parse(&contents, root),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn parse(contents: &str, root: &Path) -> Vec<IndexedFolder> {
+    let mut folders: Vec<IndexedFolder> = Vec::new();
+    for line in contents.lines() {
+        let mut parts = line.splitn(2, ' ');
+        match (parts.next(), parts.next()) {
+            (Some("folder"), Some(path)) => folders.push(IndexedFolder {
+                name: PathBuf::from(path),
+                paths: Vec::new(),
+            }),
+            (Some("image"), Some(path)) => {
+                if let Some(folder) = folders.last_mut() {
+                    folder.paths.push(PathBuf::from(path));
+                }
+            }
+            _ => (),
+        }
+    }
+    folders.retain(|folder| {
+        folder.name.starts_with(root) && !folder.paths.is_empty()
+    });
+    folders
+}
+
+// replaces the index on another thread
+pub fn save(folders: Vec<(PathBuf, Vec<PathBuf>)>) {
+    // started before the thread so quitting right away still waits for it
+    let job = WriteJob::start();
+    thread::spawn(move || {
+        let _job = job;
+        let contents = write(folders);
+        if let Err(err) = fs::create_dir_all(thumbnail_cache::cache_dir()) {
+            error!("Error creating thumbnail cache directory: {}", err);
+            return;
+        }
+        if let Err(err) = fs::write(index_path(), contents) {
+            error!("Error writing the library index: {}", err);
+        }
+    });
+}
+
+// a path is kept as it is on its line, so ones that aren't unicode or that
+// have a line break in them are left out and get scanned instead
+fn line_path(path: &Path) -> Option<&str> {
+    path.to_str()
+        .filter(|path| !path.contains(&['\n', '\r'][..]))
+}
+
+fn write(folders: Vec<(PathBuf, Vec<PathBuf>)>) -> String {
+    let mut contents = String::new();
+    for (name, paths) in folders {
+        let name = match line_path(&name) {
+            Some(name) => name,
+            None => continue,
+        };
+        let _ = writeln!(contents, "folder {}", name);
+        for path in paths.iter().filter_map(|path| line_path(path)) {
+            let _ = writeln!(contents, "image {}", path);
+        }
+    }
+    contents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_paths_with_spaces() {
+        let folders = vec![
+            (
+                PathBuf::from("/photos/summer trip"),
+                vec![
+                    PathBuf::from("/photos/summer trip/day 1.jpg"),
+                    PathBuf::from("/photos/summer trip/day 2.jpg"),
+                ],
+            ),
+            (
+                PathBuf::from("/photos/winter"),
+                vec![PathBuf::from("/photos/winter/snow.png")],
+            ),
+        ];
+        let loaded = parse(&write(folders.clone()), Path::new("/photos"));
+        let loaded: Vec<_> = loaded
+            .into_iter()
+            .map(|folder| (folder.name, folder.paths))
+            .collect();
+        assert_eq!(loaded, folders);
+    }
+
+    #[test]
+    fn leaves_out_paths_with_line_breaks() {
+        let folders = vec![
+            (
+                PathBuf::from("/photos/a"),
+                vec![
+                    PathBuf::from("/photos/a/line\nbreak.jpg"),
+                    PathBuf::from("/photos/a/kept.jpg"),
+                ],
+            ),
+            (
+                PathBuf::from("/photos/b\nfolder image /photos/b.jpg"),
+                vec![PathBuf::from("/photos/b\nfolder/c.jpg")],
+            ),
+        ];
+        let loaded = parse(&write(folders), Path::new("/photos"));
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].name, PathBuf::from("/photos/a"));
+        assert_eq!(loaded[0].paths, vec![PathBuf::from("/photos/a/kept.jpg")]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn leaves_out_paths_that_arent_unicode() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+        let name = PathBuf::from(OsStr::from_bytes(b"/photos/\xff"));
+        let folders = vec![
+            (name.clone(), vec![name.join("a.jpg")]),
+            (
+                PathBuf::from("/photos/a"),
+                vec![
+                    Path::new("/photos/a").join(OsStr::from_bytes(b"\xfe.jpg"))
+                ],
+            ),
+        ];
+        assert!(parse(&write(folders), Path::new("/photos")).is_empty());
+    }
+}
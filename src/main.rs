@@ -1,7 +1,11 @@
+use std::{env, path::PathBuf, sync::Arc, time::Duration};
+
 use app_data::AppState;
 use druid::{
     im::{vector, HashSet, Vector},
-    AppLauncher, Widget, WindowDesc,
+    widget::Controller,
+    AppLauncher, Data, Env, Event, EventCtx, LifeCycle, LifeCycleCtx, Widget,
+    WidgetExt, WindowDesc,
 };
 
 // use druid_widget_nursery::navigator::{Navigator, View, ViewController};
@@ -9,27 +13,93 @@ use druid_navigator::navigator::Navigator;
 use folder_view::folder_navigator;
 use log::error;
 use main_view::{main_view, AppView};
+use settings::Settings;
+use widgets::Toast;
 
 mod app_commands;
 mod app_data;
+mod background;
+mod decode;
+mod diagnostics;
+mod file_ops;
 mod folder_view;
+mod format;
+mod library_index;
 mod main_view;
+mod memory_cache;
+mod metadata;
+mod natural_order;
+mod palette;
+mod preferences;
+mod remote;
+mod render_cache;
+mod settings;
+mod sidecar;
+mod thumbnail_cache;
+mod thumbnailer;
 pub mod widgets;
+mod window;
+
+// how long quitting waits on files that are still being written
+const SHUTDOWN_WRITE_TIMEOUT: Duration = Duration::from_secs(5);
 
 fn main() {
+    diagnostics::init();
     let window = WindowDesc::new(navigator).title("Gallery");
+    let settings = Settings::load();
+    memory_cache::set_budget_mb(settings.cache_budget_mb);
+    memory_cache::set_compact_thumbnails(settings.compact_thumbnails);
+    render_cache::set_budget_mb(settings.cache_budget_mb);
+    render_cache::set_min_megapixels(settings.render_cache_megapixels);
+    thumbnailer::set_command(&settings.external_thumbnailer);
+    background::set_paused(settings.pause_background);
+    background::set_pause_foreground(settings.pause_foreground);
+    remote::clear_downloads();
+    // "open with" in file managers passes the image as the first argument,
+    // an image's url can be passed too
+    let opened_path = env::args_os().nth(1).map(|arg| {
+        let is_url = arg.to_str().map_or(false, remote::is_url);
+        let path = PathBuf::from(arg);
+        let path = match env::current_dir() {
+            Ok(current_dir) if path.is_relative() && !is_url => {
+                current_dir.join(path)
+            }
+            _ => path,
+        };
+        Arc::new(path)
+    });
 
-    match AppLauncher::with_window(window).use_simple_logger().launch(
-        AppState {
-            folder_paths: HashSet::new(),
-            current_image_idx: 0,
-            // this will back the Navigator, so it always has to be initialized with something
-            views: vector![AppView::MainView],
-            all_images: Vector::new(),
-            selected_folder: None,
-        },
-    ) {
-        Ok(_) => {}
+    let launcher = AppLauncher::with_window(window)
+        .configure_env(|env, data: &AppState| data.settings.set_env(env));
+    diagnostics::set_sink(launcher.get_external_handle());
+    match launcher.launch(AppState {
+        folder_paths: HashSet::new(),
+        current_image_idx: 0,
+        // this will back the Navigator, so it always has to be initialized with something
+        views: vector![AppView::MainView],
+        all_images: Vector::new(),
+        selected_folder: None,
+        thumbnail_cache_progress: None,
+        cache_usage: 0,
+        settings,
+        root_folders: Vector::new(),
+        focused_folder: None,
+        log: Vector::new(),
+        scan_generation: 0,
+        rescan_collapsed: None,
+        indexed_folders: HashSet::new(),
+        opened_path,
+    }) {
+        // the window is gone, but a file written in the background would be
+        // cut off when the process exits
+        Ok(_) => {
+            let unfinished =
+                background::wait_for_writes(SHUTDOWN_WRITE_TIMEOUT);
+            if unfinished > 0 {
+                error!("Quit with {} files still being written", unfinished);
+            }
+            remote::clear_downloads();
+        }
         Err(err) => {
             error!("There was an error launching the application: {}", err);
         }
@@ -37,6 +107,50 @@ fn main() {
 }
 
 fn navigator() -> impl Widget<AppState> {
-    Navigator::new(AppView::MainView, main_view)
-        .with_view_builder(AppView::FolderView, folder_navigator)
+    let navigator = Navigator::new(AppView::MainView, main_view)
+        .with_view_builder(AppView::FolderView, folder_navigator);
+    Toast::new(navigator).controller(SettingsController::default())
+}
+
+// hotkeys change settings without going through the preferences, so they're
+// saved when the window closes if they changed while it was open
+#[derive(Default)]
+struct SettingsController {
+    saved: Option<Settings>,
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for SettingsController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        if let Event::WindowDisconnected = event {
+            let changed = self
+                .saved
+                .as_ref()
+                .map_or(true, |saved| !saved.same(&data.settings));
+            if changed {
+                data.settings.save();
+            }
+        }
+        child.event(ctx, event, data, env)
+    }
+
+    fn lifecycle(
+        &mut self,
+        child: &mut W,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &AppState,
+        env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            self.saved = Some(data.settings.clone());
+        }
+        child.lifecycle(ctx, event, data, env)
+    }
 }
@@ -1,47 +1,82 @@
 use std::{
+    cmp,
+    collections::HashMap,
     fs::{self, read_dir},
-    path::PathBuf,
-    sync::Arc,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        mpsc::RecvTimeoutError,
+        Arc,
+    },
     thread,
+    time::{Duration, Instant, SystemTime},
 };
 
 use druid::{
-    commands::OPEN_FILE,
+    commands::{OPEN_FILE, SHOW_OPEN_PANEL},
     im::{HashSet, Vector},
     piet::ImageFormat,
     widget::{Container, Controller},
-    Data, Env, Event, ExtEventSink, ImageBuf, Target, Widget,
+    Application, Command, Data, Env, Event, EventCtx, ExtEventSink,
+    FileDialogOptions, ImageBuf, KbKey, KeyEvent, Target, Widget,
 };
 use druid_gridview::GridIter;
 use druid_navigator::navigator::{View, ViewController};
-use image::{
-    imageops::thumbnail, io::Reader, GenericImageView, ImageError, RgbImage,
-    SubImage,
-};
+use image::{GenericImageView, ImageError, RgbImage, SubImage};
 use log::error;
-use walkdir::{DirEntry, WalkDir};
+use walkdir::WalkDir;
 
 use crate::{
     app_commands::{
-        CREATED_FIRST_IMAGE_THUMBNAIL, FINISHED_READING_ALL_PATHS,
-        FINISHED_READING_FOLDER_IMAGE, POP_VIEW, SELECTED_FOLDER,
+        CACHE_USAGE, CREATED_FIRST_IMAGE_THUMBNAIL, FINISHED_READING_ALL_PATHS,
+        FINISHED_READING_FOLDER_IMAGE, HIGHLIGHT_IMAGE, OPEN_CONTAINING_FOLDER,
+        OPEN_PATH, OPEN_ROOT_FOLDER, POP_VIEW, PUSH_VIEW_WITH_SELECTED_IMAGE,
+        RESCANNED_FOLDER, RESCAN_ALL_FOLDERS, SCANNED_CONTAINING_FOLDER,
+        SELECTED_FOLDER, THUMBNAIL_CACHE_PROGRESS, TOGGLE_FOLDER_COLLAPSED,
     },
-    app_data::{AppState, ImageFolder, Thumbnail},
+    app_data::{next_id, AppState, FileFormat, Flag, ImageFolder, Thumbnail},
+    background::{self, ScanJob},
+    decode::{apply_orientation, decode_lenient, downscale, is_raw},
+    folder_view::{create_thumbnail_from_path, FolderView},
+    format, library_index,
+    metadata::{self, ImageProbe, ProbeWorker},
+    natural_order, remote,
+    settings::{FolderSort, NumberLocale, Settings, SortOrder, StartupView},
+    sidecar, thumbnail_cache,
+    widgets::SHOW_TOAST,
+    window::open_window,
 };
 
-impl GridIter<(ImageFolder, usize)> for AppState {
-    fn for_each(&self, mut cb: impl FnMut(&(ImageFolder, usize), usize)) {
+// incremented every time thumbnail pre-generation starts so an older run
+// stops once a newer one takes over
+static THUMBNAIL_PREGENERATION: AtomicUsize = AtomicUsize::new(0);
+// the generation of the last scan of the whole library that was started
+static STARTED_SCAN: AtomicU64 = AtomicU64::new(0);
+// files that are empty or were modified this recently might still be being
+// written
+const RECENT_WRITE: Duration = Duration::from_secs(2);
+// how often the size of a file that's being written is checked
+const WRITE_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+// a rescan doesn't wait any longer than this for files to be written
+const MAX_WRITE_WAIT: Duration = Duration::from_secs(30);
+
+// the bool is whether the folder has keyboard focus
+impl GridIter<(ImageFolder, usize, bool)> for AppState {
+    fn for_each(&self, mut cb: impl FnMut(&(ImageFolder, usize, bool), usize)) {
         for (i, image_folder) in self.all_images.iter().enumerate() {
-            cb(&(image_folder.clone(), i), i)
+            let focused = self.focused_folder == Some(i);
+            cb(&(image_folder.clone(), i, focused), i)
         }
     }
 
     fn for_each_mut(
         &mut self,
-        mut cb: impl FnMut(&mut (ImageFolder, usize), usize),
+        mut cb: impl FnMut(&mut (ImageFolder, usize, bool), usize),
     ) {
+        let focused_folder = self.focused_folder;
         for (i, image_folder) in self.all_images.iter_mut().enumerate() {
-            cb(&mut (image_folder.clone(), i), i)
+            let focused = focused_folder == Some(i);
+            cb(&mut (image_folder.clone(), i, focused), i)
         }
     }
 
@@ -49,19 +84,35 @@ impl GridIter<(ImageFolder, usize)> for AppState {
         self.all_images.len()
     }
 
-    fn child_data(&self) -> Option<(ImageFolder, usize)> {
+    fn child_data(&self) -> Option<(ImageFolder, usize, bool)> {
         match self.all_images.iter().next() {
-            Some(folder) => Some((folder.clone(), 0)),
+            Some(folder) => Some((folder.clone(), 0, false)),
             None => Some((
                 ImageFolder {
+                    id: 0,
                     name: Arc::new(PathBuf::from("".to_owned())),
                     folder_thumbnail: Thumbnail {
+                        id: 0,
                         index: 0,
                         image: ImageBuf::empty(),
+                        format: FileFormat::Unknown,
+                        partial: false,
+                        captured: None,
+                        flag: Flag::None,
+                        rating: 0,
+                        caption: None,
+                        dimensions: None,
+                        raw_pair: None,
+                        timed_out: false,
+                        modified: None,
+                        file_size: None,
+                        location: None,
+                        failed: false,
                     },
                     paths: Vector::new(),
                     selected: None,
                     thumbnails: Vector::new(),
+                    collapsed: false,
                 },
                 0,
             )),
@@ -114,159 +165,1297 @@ impl Controller<AppState, Container<AppState>> for MainViewController {
             Event::Command(selector)
                 if selector.is(FINISHED_READING_FOLDER_IMAGE) =>
             {
-                let image_folder =
+                let (generation, image_folder) =
                     selector.get_unchecked(FINISHED_READING_FOLDER_IMAGE);
-                data.all_images.push_back(image_folder.clone());
+                if *generation != data.scan_generation {
+                    return;
+                }
+                let mut image_folder = image_folder.clone();
+                if let Some(collapsed) = &data.rescan_collapsed {
+                    image_folder.collapsed =
+                        collapsed.contains(&image_folder.name);
+                }
+                // the folder was shown from the library index, an open
+                // gallery of it takes in what the scan found, otherwise the
+                // scanned folder takes its place
+                if data.indexed_folders.remove(&image_folder.name).is_some() {
+                    let idx = data
+                        .all_images
+                        .iter()
+                        .position(|folder| folder.name == image_folder.name);
+                    if let Some(idx) = idx {
+                        if data.selected_folder == Some(idx)
+                            && data.views.contains(&AppView::FolderView)
+                        {
+                            ctx.submit_command(RESCANNED_FOLDER.with((
+                                image_folder.name,
+                                image_folder.thumbnails,
+                                image_folder.paths,
+                            )));
+                            return;
+                        }
+                        image_folder.collapsed = data.all_images[idx].collapsed;
+                        remove_folder(data, idx);
+                    }
+                }
+                insert_folder(data, image_folder);
                 ctx.request_layout();
                 ctx.request_paint();
             }
             Event::Command(selector) if selector.is(SELECTED_FOLDER) => {
                 let selected = selector.get_unchecked(SELECTED_FOLDER);
-                data.selected_folder = Some(*selected);
-                data.add_view(AppView::FolderView);
+                // the folder is gone by the time the selection arrives
+                match data
+                    .all_images
+                    .iter()
+                    .position(|folder| folder.id == *selected)
+                {
+                    Some(idx) => {
+                        data.selected_folder = Some(idx);
+                        data.add_view(AppView::FolderView);
+                    }
+                    None => error!("Selected folder no longer exists"),
+                }
+            }
+            Event::Command(cmd) if cmd.is(TOGGLE_FOLDER_COLLAPSED) => {
+                let id = *cmd.get_unchecked(TOGGLE_FOLDER_COLLAPSED);
+                if let Some(folder) =
+                    data.all_images.iter_mut().find(|folder| folder.id == id)
+                {
+                    folder.collapsed = !folder.collapsed;
+                }
+            }
+            // pasting an image's url or a path opens it
+            Event::KeyDown(KeyEvent {
+                key: KbKey::Character(key),
+                mods,
+                ..
+            }) if mods.ctrl() && key.eq_ignore_ascii_case("v") => {
+                if let Some(text) =
+                    Application::global().clipboard().get_string()
+                {
+                    let path = Arc::new(PathBuf::from(text.trim()));
+                    ctx.submit_command(OPEN_PATH.with(path));
+                }
+            }
+            // arrows move between folders, space collapses or expands the
+            // focused one and enter opens it, ctrl+r scans the library again
+            // and ctrl+n opens another window
+            Event::KeyDown(key_event) if !data.all_images.is_empty() => {
+                let last = data.all_images.len() - 1;
+                match &key_event.key {
+                    KbKey::ArrowLeft | KbKey::ArrowUp => {
+                        data.focused_folder = Some(
+                            data.focused_folder
+                                .map_or(0, |idx| idx.saturating_sub(1)),
+                        );
+                    }
+                    KbKey::ArrowRight | KbKey::ArrowDown => {
+                        data.focused_folder = Some(
+                            data.focused_folder
+                                .map_or(0, |idx| (idx + 1).min(last)),
+                        );
+                    }
+                    // every window's library is cleared along with this one's
+                    KbKey::Character(key)
+                        if key.eq_ignore_ascii_case("r")
+                            && key_event.mods.ctrl() =>
+                    {
+                        ctx.submit_command(Command::new(
+                            RESCAN_ALL_FOLDERS,
+                            (),
+                            Target::Global,
+                        ));
+                    }
+                    KbKey::Character(key)
+                        if key.eq_ignore_ascii_case("n")
+                            && key_event.mods.ctrl() =>
+                    {
+                        open_window(ctx);
+                    }
+                    KbKey::Character(key) if key == " " => {
+                        if let Some(folder) = data
+                            .focused_folder
+                            .and_then(|idx| data.all_images.get(idx))
+                        {
+                            ctx.submit_command(
+                                TOGGLE_FOLDER_COLLAPSED.with(folder.id),
+                            );
+                        }
+                    }
+                    KbKey::Enter => {
+                        if let Some(folder) = data
+                            .focused_folder
+                            .and_then(|idx| data.all_images.get(idx))
+                        {
+                            ctx.submit_command(SELECTED_FOLDER.with(folder.id));
+                        }
+                    }
+                    _ => (),
+                }
             }
             Event::Command(selector) if selector.is(POP_VIEW) => {
                 data.pop_view();
             }
             Event::Command(cmd) if cmd.is(FINISHED_READING_ALL_PATHS) => {
-                let current_folders =
+                let (generation, roots, current_folders) =
                     cmd.get_unchecked(FINISHED_READING_ALL_PATHS);
+                if *generation != data.scan_generation {
+                    return;
+                }
+                data.rescan_collapsed = None;
                 data.folder_paths = current_folders.clone();
-                let handle = ctx.get_external_handle();
-                let folders = data.all_images.clone();
-                thread::spawn(move || {
-                    for (folder_idx, folder) in folders.iter().enumerate() {
-                        let thumbnail =
-                            create_first_image_thumbnail(folder).unwrap();
-                        handle
-                            .submit_command(
-                                CREATED_FIRST_IMAGE_THUMBNAIL,
-                                (thumbnail, folder_idx),
-                                Target::Auto,
-                            )
-                            .unwrap();
-                    }
-                });
+                prune_indexed_folders(ctx, data, roots);
+                library_index::save(
+                    data.all_images
+                        .iter()
+                        .map(|folder| {
+                            let paths = folder
+                                .paths
+                                .iter()
+                                .map(|path| path.as_ref().clone())
+                                .collect();
+                            (folder.name.as_ref().clone(), paths)
+                        })
+                        .collect(),
+                );
+                // list mode doesn't decode anything ahead of time
+                if !data.settings.list_mode {
+                    let handle = ctx.get_external_handle();
+                    let folders = data.all_images.clone();
+                    let auto_rotate = data.settings.auto_rotate;
+                    let linear_thumbnails = data.settings.linear_thumbnails;
+                    thread::spawn(move || {
+                        for folder in folders.iter() {
+                            // every image of the folder might have been deleted
+                            if folder.paths.is_empty() {
+                                continue;
+                            }
+                            let thumbnail = match create_first_image_thumbnail(
+                                folder,
+                                auto_rotate,
+                                linear_thumbnails,
+                            ) {
+                                Ok(thumbnail) => thumbnail,
+                                Err(err) => {
+                                    error!(
+                                        "Error creating folder thumbnail: {}",
+                                        err
+                                    );
+                                    continue;
+                                }
+                            };
+                            handle
+                                .submit_command(
+                                    CREATED_FIRST_IMAGE_THUMBNAIL,
+                                    (thumbnail, folder.id),
+                                    Target::Auto,
+                                )
+                                .unwrap();
+                        }
+                        let paths = folders
+                            .iter()
+                            .flat_map(|folder| folder.paths.iter().cloned())
+                            .collect();
+                        pregenerate_thumbnails(
+                            paths,
+                            linear_thumbnails,
+                            &handle,
+                        );
+                    });
+                }
+            }
+            Event::Command(cmd) if cmd.is(CACHE_USAGE) => {
+                data.cache_usage = *cmd.get_unchecked(CACHE_USAGE);
+            }
+            Event::Command(cmd) if cmd.is(THUMBNAIL_CACHE_PROGRESS) => {
+                let progress = cmd.get_unchecked(THUMBNAIL_CACHE_PROGRESS);
+                data.thumbnail_cache_progress = *progress;
             }
             Event::Command(cmd) if cmd.is(CREATED_FIRST_IMAGE_THUMBNAIL) => {
-                let (thumbnail, folder_idx) =
+                let (thumbnail, id) =
                     cmd.get_unchecked(CREATED_FIRST_IMAGE_THUMBNAIL);
-                data.all_images[*folder_idx].folder_thumbnail =
-                    thumbnail.clone();
+                // the folders might have been rescanned since the thumbnail
+                // was requested
+                if let Some(folder) =
+                    data.all_images.iter_mut().find(|folder| folder.id == *id)
+                {
+                    folder.folder_thumbnail = thumbnail.clone();
+                }
             }
             Event::Command(cmd) if cmd.is(OPEN_FILE) => {
                 let file_info = cmd.get_unchecked(OPEN_FILE);
-                let handle = ctx.get_external_handle();
-                let folders = data.folder_paths.clone();
+                let root = Arc::new(file_info.path().to_path_buf());
+                data.settings.last_folder = Some(root.clone());
+                data.settings.save();
+                add_root_folder(ctx, data, root);
+            }
+            Event::Command(cmd) if cmd.is(OPEN_ROOT_FOLDER) => {
+                let root = cmd.get_unchecked(OPEN_ROOT_FOLDER);
+                add_root_folder(ctx, data, root.clone());
+            }
+            Event::Command(cmd) if cmd.is(OPEN_PATH) => {
+                let path = cmd.get_unchecked(OPEN_PATH).clone();
+                data.opened_path = None;
+                if path.is_dir() {
+                    add_root_folder(ctx, data, path);
+                } else if path.is_file() {
+                    open_image_file(ctx, data, &path);
+                } else if let Some(url) =
+                    path.to_str().filter(|path| remote::is_url(path))
+                {
+                    open_url(ctx, url.to_string());
+                } else {
+                    error!("{} doesn't exist", path.display());
+                }
+            }
+            Event::Command(cmd) if cmd.is(OPEN_CONTAINING_FOLDER) => {
+                let path = cmd.get_unchecked(OPEN_CONTAINING_FOLDER);
+                match library_folder(data, path) {
+                    Some(folder_idx) => {
+                        highlight_image(ctx, data, folder_idx, path)
+                    }
+                    None => scan_containing_folder(ctx, data, path, false),
+                }
+            }
+            Event::Command(cmd) if cmd.is(SCANNED_CONTAINING_FOLDER) => {
+                let (path, open, folder) =
+                    cmd.get_unchecked(SCANNED_CONTAINING_FOLDER);
+                if folder.paths.is_empty() {
+                    if *open {
+                        error!(
+                            "{} isn't an image that can be shown",
+                            path.display()
+                        );
+                    }
+                    return;
+                }
+                // the folder could have been added while it was read
+                let folder_idx = match library_folder(data, path) {
+                    Some(folder_idx) => Some(folder_idx),
+                    None => add_containing_folder(ctx, data, folder.clone()),
+                };
+                let folder_idx = match folder_idx {
+                    Some(folder_idx) => folder_idx,
+                    None => return,
+                };
+                if *open {
+                    show_image_file(ctx, data, folder_idx, path);
+                } else {
+                    highlight_image(ctx, data, folder_idx, path);
+                }
+            }
+            Event::Command(cmd) if cmd.is(RESCAN_ALL_FOLDERS) => {
+                // a scan that's still running would add its folders on top
+                // of the new ones
+                data.scan_generation += 1;
+                // a rescan that's cut short by another one keeps what was
+                // collapsed before either
+                if data.rescan_collapsed.is_none() {
+                    data.rescan_collapsed = Some(
+                        data.all_images
+                            .iter()
+                            .filter(|folder| folder.collapsed)
+                            .map(|folder| folder.name.clone())
+                            .collect(),
+                    );
+                }
+                data.all_images.clear();
+                data.folder_paths.clear();
+                data.indexed_folders.clear();
+                data.selected_folder = None;
+                // every window gets the command, but only one of them
+                // starts the scan
+                if STARTED_SCAN
+                    .fetch_max(data.scan_generation, Ordering::SeqCst)
+                    >= data.scan_generation
+                {
+                    return;
+                }
+                let roots = data
+                    .root_folders
+                    .iter()
+                    .map(|root| root.as_ref().clone())
+                    .collect();
                 flatten_and_add_paths(
-                    file_info.path().to_path_buf(),
-                    folders,
-                    handle,
+                    roots,
+                    HashSet::new(),
+                    ctx.get_external_handle(),
+                    data.settings.clone(),
+                    data.scan_generation,
                 );
             }
             _ => {}
         }
         child.event(ctx, event, data, env)
     }
+
+    fn lifecycle(
+        &mut self,
+        child: &mut Container<AppState>,
+        ctx: &mut druid::LifeCycleCtx,
+        event: &druid::LifeCycle,
+        data: &AppState,
+        env: &Env,
+    ) {
+        // the main view needs focus to receive key events
+        if let druid::LifeCycle::WidgetAdded = event {
+            ctx.request_focus();
+            // the main view is built again when coming back to it, only
+            // an empty library means the app just started
+            if data.root_folders.is_empty() {
+                match &data.opened_path {
+                    Some(path) => {
+                        ctx.submit_command(OPEN_PATH.with(path.clone()))
+                    }
+                    None => open_startup_view(ctx, &data.settings),
+                }
+            }
+        }
+        child.lifecycle(ctx, event, data, env)
+    }
+}
+
+fn add_root_folder(
+    ctx: &mut EventCtx,
+    data: &mut AppState,
+    root: Arc<PathBuf>,
+) {
+    insert_root_folder(data, root.clone());
+    show_indexed_folders(ctx, data, &root);
+    let handle = ctx.get_external_handle();
+    let folders = data.folder_paths.clone();
+    flatten_and_add_paths(
+        vec![root.as_ref().clone()],
+        folders,
+        handle,
+        data.settings.clone(),
+        data.scan_generation,
+    );
+}
+
+// shows the folders the library index has below the root, with the covers
+// the thumbnail cache has for them, until the scan finds them again
+fn show_indexed_folders(ctx: &mut EventCtx, data: &mut AppState, root: &Path) {
+    let mut covers = Vec::new();
+    for indexed in library_index::load(root) {
+        let name = Arc::new(indexed.name);
+        if data.folder_paths.contains(&name)
+            || data.all_images.iter().any(|folder| folder.name == name)
+        {
+            continue;
+        }
+        let thumbnails = indexed
+            .paths
+            .iter()
+            .enumerate()
+            .map(|(index, path)| Thumbnail {
+                id: next_id(),
+                index,
+                image: ImageBuf::empty(),
+                format: FileFormat::from_path(path),
+                partial: false,
+                captured: None,
+                flag: Flag::None,
+                rating: 0,
+                caption: None,
+                dimensions: None,
+                raw_pair: None,
+                timed_out: false,
+                modified: None,
+                file_size: None,
+                location: None,
+                failed: false,
+            })
+            .collect();
+        let paths = indexed.paths.into_iter().map(Arc::new).collect();
+        let image_folder = new_image_folder(
+            name.as_ref().clone(),
+            thumbnails,
+            paths,
+            data.settings.sort_order,
+        );
+        covers.push((
+            image_folder.id,
+            image_folder.paths[0].clone(),
+            image_folder.thumbnails[0].clone(),
+        ));
+        data.indexed_folders.insert(name);
+        insert_folder(data, image_folder);
+    }
+    // list mode doesn't show covers
+    if covers.is_empty() || data.settings.list_mode {
+        return;
+    }
+    let handle = ctx.get_external_handle();
+    let linear_light = data.settings.linear_thumbnails;
+    thread::spawn(move || {
+        for (id, path, thumbnail) in covers {
+            // folders without a cached cover get one once they're scanned
+            let image = match thumbnail_cache::load(&path, linear_light) {
+                Some(image) => image,
+                None => continue,
+            };
+            let image = find_largest_square_crop(&image).to_image();
+            let (width, height) = image.dimensions();
+            let image = ImageBuf::from_raw(
+                image.into_raw(),
+                ImageFormat::Rgb,
+                width as usize,
+                height as usize,
+            );
+            let cover = Thumbnail {
+                id: 0,
+                index: 0,
+                image,
+                ..thumbnail
+            };
+            let _ = handle.submit_command(
+                CREATED_FIRST_IMAGE_THUMBNAIL,
+                (cover, id),
+                Target::Auto,
+            );
+        }
+    });
+}
+
+// takes out the folders shown from the library index that the scan of the
+// roots didn't find, an open gallery of one is emptied instead
+fn prune_indexed_folders(
+    ctx: &mut EventCtx,
+    data: &mut AppState,
+    roots: &[PathBuf],
+) {
+    let missing: Vec<Arc<PathBuf>> = data
+        .indexed_folders
+        .iter()
+        .filter(|name| roots.iter().any(|root| name.starts_with(root)))
+        .cloned()
+        .collect();
+    for name in missing {
+        data.indexed_folders.remove(&name);
+        let idx = match data
+            .all_images
+            .iter()
+            .position(|folder| folder.name == name)
+        {
+            Some(idx) => idx,
+            None => continue,
+        };
+        if data.selected_folder == Some(idx)
+            && data.views.contains(&AppView::FolderView)
+        {
+            ctx.submit_command(RESCANNED_FOLDER.with((
+                name,
+                Vector::new(),
+                Vector::new(),
+            )));
+        } else {
+            remove_folder(data, idx);
+        }
+    }
+}
+
+// puts the folder where the folder sort has it, after the folders it ties
+// with. The selection and the keyboard focus stay on their folders
+fn insert_folder(data: &mut AppState, image_folder: ImageFolder) {
+    let sort = data.settings.folder_sort;
+    let idx = data
+        .all_images
+        .iter()
+        .position(|folder| {
+            compare_folders(&data.root_folders, sort, folder, &image_folder)
+                == cmp::Ordering::Greater
+        })
+        .unwrap_or_else(|| data.all_images.len());
+    data.all_images.insert(idx, image_folder);
+    for folder_idx in data
+        .selected_folder
+        .iter_mut()
+        .chain(data.focused_folder.iter_mut())
+    {
+        if *folder_idx >= idx {
+            *folder_idx += 1;
+        }
+    }
+}
+
+// the selection and the keyboard focus stay on their folders, and are
+// cleared when it's theirs that's removed
+fn remove_folder(data: &mut AppState, idx: usize) {
+    data.all_images.remove(idx);
+    let shift = |folder_idx: Option<usize>| match folder_idx {
+        Some(folder_idx) if folder_idx == idx => None,
+        Some(folder_idx) if folder_idx > idx => Some(folder_idx - 1),
+        folder_idx => folder_idx,
+    };
+    data.selected_folder = shift(data.selected_folder);
+    data.focused_folder = shift(data.focused_folder);
+}
+
+// keeps the roots in the order saved in the settings, new ones go last
+fn insert_root_folder(data: &mut AppState, root: Arc<PathBuf>) {
+    if data.root_folders.contains(&root) {
+        return;
+    }
+    let order = &data.settings.root_order;
+    let rank = |root: &Arc<PathBuf>| order.index_of(root).unwrap_or(usize::MAX);
+    let idx = data
+        .root_folders
+        .iter()
+        .position(|other| rank(other) > rank(&root))
+        .unwrap_or_else(|| data.root_folders.len());
+    data.root_folders.insert(idx, root);
+}
+
+// position of the root folder the folder was found under
+fn root_rank(roots: &Vector<Arc<PathBuf>>, folder: &Path) -> usize {
+    roots
+        .iter()
+        .position(|root| folder.starts_with(root.as_ref()))
+        .unwrap_or_else(|| roots.len())
+}
+
+// moves the root folder at idx one place up or down, its folders follow it
+// in the main view and the order is kept in the settings
+pub fn move_root_folder(data: &mut AppState, idx: usize, up: bool) {
+    let other = if up {
+        idx.checked_sub(1)
+    } else {
+        Some(idx + 1)
+    };
+    let other = match other.filter(|other| *other < data.root_folders.len()) {
+        Some(other) => other,
+        None => return,
+    };
+    data.root_folders.swap(idx, other);
+    data.settings.root_order = data.root_folders.clone();
+    sort_folders(data);
+    data.focused_folder = None;
+}
+
+// where a folder goes in the main view. Folders with the same count or size
+// are ordered by their names, and ones under the same root keep the order
+// they were found in
+fn compare_folders(
+    roots: &Vector<Arc<PathBuf>>,
+    sort: FolderSort,
+    a: &ImageFolder,
+    b: &ImageFolder,
+) -> cmp::Ordering {
+    let by_name = || natural_order::compare_paths(&a.name, &b.name);
+    match sort {
+        FolderSort::Library => {
+            root_rank(roots, &a.name).cmp(&root_rank(roots, &b.name))
+        }
+        FolderSort::ImageCount => {
+            b.paths.len().cmp(&a.paths.len()).then_with(by_name)
+        }
+        FolderSort::TotalSize => {
+            b.total_size().cmp(&a.total_size()).then_with(by_name)
+        }
+    }
+}
+
+// puts the folders in the order of the folder sort, the selection and the
+// keyboard focus follow their folders
+pub fn sort_folders(data: &mut AppState) {
+    let id_at = |idx: Option<usize>| {
+        idx.and_then(|idx| data.all_images.get(idx))
+            .map(|folder| folder.id)
+    };
+    let selected = id_at(data.selected_folder);
+    let focused = id_at(data.focused_folder);
+    let sort = data.settings.folder_sort;
+    let mut folders: Vec<ImageFolder> =
+        data.all_images.iter().cloned().collect();
+    folders.sort_by(|a, b| compare_folders(&data.root_folders, sort, a, b));
+    data.all_images = folders.into_iter().collect();
+    let all_images = &data.all_images;
+    let position = |id: Option<u64>| {
+        id.and_then(|id| all_images.iter().position(|folder| folder.id == id))
+    };
+    let (selected, focused) = (position(selected), position(focused));
+    data.selected_folder = selected;
+    data.focused_folder = focused;
+}
+
+// shows the image in the image view of its folder, so the rest of the
+// folder is a step away
+fn open_image_file(
+    ctx: &mut EventCtx,
+    data: &mut AppState,
+    path: &Arc<PathBuf>,
+) {
+    match library_folder(data, path) {
+        Some(folder_idx) => show_image_file(ctx, data, folder_idx, path),
+        None => scan_containing_folder(ctx, data, path, true),
+    }
+}
+
+fn show_image_file(
+    ctx: &mut EventCtx,
+    data: &mut AppState,
+    folder_idx: usize,
+    path: &Arc<PathBuf>,
+) {
+    // the library isn't empty anymore, so coming back to the main view
+    // doesn't open the startup view
+    let parent = Arc::new(path.parent().unwrap().to_path_buf());
+    insert_root_folder(data, parent);
+    let image_idx = data.all_images[folder_idx].paths.index_of(path);
+    data.selected_folder = Some(folder_idx);
+    data.add_view(AppView::FolderView);
+    if let Some(image_idx) = image_idx {
+        ctx.submit_command(Command::new(
+            PUSH_VIEW_WITH_SELECTED_IMAGE,
+            (FolderView::SingleImage, image_idx),
+            Target::Auto,
+        ));
+    }
+}
+
+// downloads the image on another thread and opens it once it's saved
+fn open_url(ctx: &mut EventCtx, url: String) {
+    ctx.submit_command(SHOW_TOAST.with(format!("Downloading {}", url)));
+    let handle = ctx.get_external_handle();
+    thread::spawn(move || match remote::download_image(&url) {
+        Ok(path) => handle
+            .submit_command(OPEN_PATH, Arc::new(path), Target::Auto)
+            .unwrap(),
+        Err(err) => {
+            error!("Error downloading {}: {}", url, err);
+            handle
+                .submit_command(
+                    SHOW_TOAST,
+                    format!("Couldn't download {}: {}", url, err),
+                    Target::Auto,
+                )
+                .unwrap()
+        }
+    });
+}
+
+fn highlight_image(
+    ctx: &mut EventCtx,
+    data: &mut AppState,
+    folder_idx: usize,
+    path: &Arc<PathBuf>,
+) {
+    data.selected_folder = Some(folder_idx);
+    ctx.submit_command(Command::new(
+        HIGHLIGHT_IMAGE,
+        path.clone(),
+        Target::Auto,
+    ));
+}
+
+// the folder the image is in, when it's in the library
+fn library_folder(data: &AppState, path: &Path) -> Option<usize> {
+    let parent = path.parent()?;
+    data.all_images
+        .iter()
+        .position(|folder| folder.name.as_path() == parent)
+}
+
+// the folder was folded into a combined gallery, or was never added, so it's
+// read on another thread and added on its own with SCANNED_CONTAINING_FOLDER.
+// The answer goes to the window that asked, that's the one it's shown in
+fn scan_containing_folder(
+    ctx: &mut EventCtx,
+    data: &AppState,
+    path: &Arc<PathBuf>,
+    open: bool,
+) {
+    let parent = match path.parent() {
+        Some(parent) => parent.to_path_buf(),
+        None => return,
+    };
+    let path = path.clone();
+    let handle = ctx.get_external_handle();
+    let window = Target::Window(ctx.window_id());
+    let settings = data.settings.clone();
+    let scan = ScanJob::start();
+    thread::spawn(move || {
+        let mut probes = ProbeWorker::new(settings.open_retries);
+        let (thumbnails, paths, _skipped) =
+            check_folder_has_images(&parent, &settings, &mut probes);
+        let folder =
+            new_image_folder(parent, thumbnails, paths, settings.sort_order);
+        drop(scan);
+        let _ = handle.submit_command(
+            SCANNED_CONTAINING_FOLDER,
+            (path, open, folder),
+            window,
+        );
+    });
+}
+
+fn add_containing_folder(
+    ctx: &mut EventCtx,
+    data: &mut AppState,
+    folder: ImageFolder,
+) -> Option<usize> {
+    let id = folder.id;
+    data.folder_paths.insert(folder.name.clone());
+    data.all_images.push_back(folder.clone());
+    sort_folders(data);
+    let folder_idx =
+        data.all_images.iter().position(|folder| folder.id == id)?;
+    let handle = ctx.get_external_handle();
+    let auto_rotate = data.settings.auto_rotate;
+    let linear_thumbnails = data.settings.linear_thumbnails;
+    thread::spawn(move || {
+        match create_first_image_thumbnail(
+            &folder,
+            auto_rotate,
+            linear_thumbnails,
+        ) {
+            Ok(thumbnail) => handle
+                .submit_command(
+                    CREATED_FIRST_IMAGE_THUMBNAIL,
+                    (thumbnail, id),
+                    Target::Auto,
+                )
+                .unwrap(),
+            Err(err) => {
+                error!("Error creating folder thumbnail: {}", err)
+            }
+        }
+    });
+    Some(folder_idx)
+}
+
+fn open_startup_view(ctx: &mut druid::LifeCycleCtx, settings: &Settings) {
+    if settings.startup_view == StartupView::ChooseFolder {
+        let file_dialog = FileDialogOptions::new().select_directories();
+        ctx.submit_command(SHOW_OPEN_PANEL.with(file_dialog));
+        return;
+    }
+    match settings.startup_folder() {
+        Some(folder) if folder.is_dir() => {
+            ctx.submit_command(OPEN_ROOT_FOLDER.with(folder))
+        }
+        // starts out empty instead
+        Some(folder) => {
+            error!("Startup folder {} no longer exists", folder.display())
+        }
+        // nothing was opened before, so it starts with the user's pictures
+        None => {
+            if let Some(folder) = default_folder() {
+                ctx.submit_command(OPEN_ROOT_FOLDER.with(Arc::new(folder)))
+            }
+        }
+    }
+}
+
+// the standard pictures folder, or the home folder on systems that don't
+// have one
+fn default_folder() -> Option<PathBuf> {
+    dirs::picture_dir()
+        .filter(|folder| folder.is_dir())
+        .or_else(|| dirs::home_dir().filter(|folder| folder.is_dir()))
 }
 
 fn flatten_and_add_paths(
-    path: PathBuf,
+    roots: Vec<PathBuf>,
     mut current_folders: HashSet<Arc<PathBuf>>,
     handle: ExtEventSink,
+    settings: Settings,
+    generation: u64,
 ) {
+    // started before the thread so background work can't slip in first
+    let scan = ScanJob::start();
     thread::spawn(move || {
-        let entries = WalkDir::new(path).into_iter().filter_entry(|entry| {
-            // only walks directories, not files, and only keeps directories
-            // that don't fail to read and are not empty
-            if entry.path().is_dir() {
-                match read_dir(entry.path()) {
-                    Ok(mut dir) => dir.next().is_some(),
-                    Err(_) => false,
+        let started = Instant::now();
+        let mut summary = ScanSummary::default();
+        let mut probes = ProbeWorker::new(settings.open_retries);
+        for root in roots.clone() {
+            // when subfolders are combined, every image found below the root
+            // ends up in a single folder named after the root
+            let mut combined_thumbnails = Vector::new();
+            let mut combined_paths = Vector::new();
+            let entries =
+                WalkDir::new(&root).into_iter().filter_entry(|entry| {
+                    // only walks directories, not files, and only keeps directories
+                    // that don't fail to read and are not empty. Excluded
+                    // ones are skipped along with their subfolders
+                    if entry.path().is_dir()
+                        && !settings.is_excluded(entry.path())
+                    {
+                        match read_dir(entry.path()) {
+                            Ok(mut dir) => dir.next().is_some(),
+                            Err(_) => false,
+                        }
+                    } else {
+                        false
+                    }
+                });
+            for (_i, entry) in entries.enumerate() {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(err) => {
+                        error!("Error scanning {}: {}", root.display(), err);
+                        summary.skipped += 1;
+                        continue;
+                    }
+                };
+                // a pause stops the scan between folders
+                background::wait_while_paused();
+                let current_folder = entry.path().to_path_buf();
+                // checks if this directory has already been added previously
+                // mostly dealing with if you add a directory that was the child of another directory
+                // you've added
+                if current_folders.contains(&current_folder) {
+                    continue;
                 }
-            } else {
-                false
-            }
-        });
-        for (_i, entry) in entries.enumerate() {
-            let entry = entry.unwrap();
-            let current_folder = entry.path().to_path_buf();
-            // checks if this directory has already been added previously
-            // mostly dealing with if you add a directory that was the child of another directory
-            // you've added
-            if current_folders.contains(&current_folder) {
-                continue;
-            }
-            let (thumbnails, paths) = check_folder_has_images(&entry);
-            if !thumbnails.is_empty() {
+                let (thumbnails, paths, skipped) = check_folder_has_images(
+                    entry.path(),
+                    &settings,
+                    &mut probes,
+                );
+                summary.skipped += skipped;
+                if thumbnails.is_empty() {
+                    continue;
+                }
+                summary.folders += 1;
+                summary.images += thumbnails.len();
                 current_folders.insert(Arc::new(current_folder.clone()));
-                let image_folder = ImageFolder {
-                    paths,
-                    folder_thumbnail: Thumbnail {
-                        index: 0,
-                        image: ImageBuf::empty(),
-                    },
-                    thumbnails,
-                    name: Arc::new(current_folder),
-                    selected: None,
-                };
-                handle
-                    .submit_command(
-                        FINISHED_READING_FOLDER_IMAGE,
-                        image_folder,
-                        Target::Auto,
-                    )
-                    .unwrap();
+                if settings.combine_subfolders {
+                    for thumbnail in thumbnails {
+                        combined_thumbnails.push_back(Thumbnail {
+                            index: combined_thumbnails.len(),
+                            ..thumbnail
+                        });
+                    }
+                    combined_paths.append(paths);
+                } else {
+                    submit_image_folder(
+                        &handle,
+                        generation,
+                        current_folder,
+                        thumbnails,
+                        paths,
+                        settings.sort_order,
+                    );
+                }
+            }
+            if !combined_paths.is_empty() {
+                submit_image_folder(
+                    &handle,
+                    generation,
+                    root,
+                    combined_thumbnails,
+                    combined_paths,
+                    settings.sort_order,
+                );
             }
         }
+        drop(scan);
         handle
             .submit_command(
                 FINISHED_READING_ALL_PATHS,
-                current_folders,
+                (generation, roots, current_folders),
                 Target::Auto,
             )
             .unwrap();
+        if settings.scan_summary {
+            let message =
+                summary.message(started.elapsed(), settings.number_locale);
+            let _ = handle.submit_command(SHOW_TOAST, message, Target::Auto);
+        }
     });
 }
 
-fn check_folder_has_images(
-    entry: &DirEntry,
+// reads a single folder again on another thread, a combined gallery reads
+// its subfolders too. What's found is sent back with RESCANNED_FOLDER
+pub fn rescan_folder(
+    folder: Arc<PathBuf>,
+    handle: ExtEventSink,
+    settings: Settings,
+) {
+    let scan = ScanJob::start();
+    thread::spawn(move || {
+        let _scan = scan;
+        let mut thumbnails = Vector::new();
+        let mut paths = Vector::new();
+        let mut probes = ProbeWorker::new(settings.open_retries);
+        let depth = if settings.combine_subfolders {
+            usize::MAX
+        } else {
+            0
+        };
+        wait_for_writes(folder.as_path(), depth, &handle);
+        // a folder that was deleted since comes back empty
+        let entries = WalkDir::new(folder.as_path())
+            .max_depth(depth)
+            .into_iter()
+            .filter_entry(|entry| {
+                entry.path().is_dir()
+                    && read_dir(entry.path()).is_ok()
+                    && !settings.is_excluded(entry.path())
+            })
+            .filter_map(|entry| entry.ok());
+        for entry in entries {
+            let (found, found_paths, _skipped) =
+                check_folder_has_images(entry.path(), &settings, &mut probes);
+            thumbnails.append(found);
+            paths.append(found_paths);
+        }
+        let (thumbnails, paths) =
+            sort_images(thumbnails, paths, settings.sort_order);
+        let _ = handle.submit_command(
+            RESCANNED_FOLDER,
+            (folder, thumbnails, paths),
+            Target::Auto,
+        );
+    });
+}
+
+// files that are still being copied into the folder would get broken tiles,
+// so this waits until the size of every file that looks like it's being
+// written stays the same across two checks
+fn wait_for_writes(folder: &Path, depth: usize, handle: &ExtEventSink) {
+    let now = SystemTime::now();
+    let size =
+        |path: &Path| path.metadata().ok().map(|metadata| metadata.len());
+    let mut writing: Vec<(PathBuf, Option<u64>)> = WalkDir::new(folder)
+        .max_depth(depth.saturating_add(1))
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| {
+            entry.metadata().map_or(false, |metadata| {
+                let recent = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|modified| now.duration_since(modified).ok())
+                    .map_or(false, |age| age < RECENT_WRITE);
+                metadata.len() == 0 || recent
+            })
+        })
+        .map(|entry| {
+            let path = entry.into_path();
+            let size = size(&path);
+            (path, size)
+        })
+        .collect();
+    if writing.is_empty() {
+        return;
+    }
+    let message = format!(
+        "Waiting for {} to finish being written",
+        if writing.len() == 1 {
+            "a file".to_string()
+        } else {
+            format!("{} files", writing.len())
+        }
+    );
+    let _ = handle.submit_command(SHOW_TOAST, message, Target::Auto);
+    let started = Instant::now();
+    while started.elapsed() < MAX_WRITE_WAIT {
+        thread::sleep(WRITE_CHECK_INTERVAL);
+        let mut settled = true;
+        for (path, last_size) in writing.iter_mut() {
+            let current = size(path);
+            if current != *last_size {
+                settled = false;
+                *last_size = current;
+            }
+        }
+        if settled {
+            return;
+        }
+    }
+    error!(
+        "Gave up waiting for files in {} to be written",
+        folder.display()
+    );
+}
+
+// what a scan found, told to the user once it finishes
+#[derive(Debug, Default)]
+struct ScanSummary {
+    folders: usize,
+    images: usize,
+    // files that aren't images the viewer shows or couldn't be read
+    skipped: usize,
+}
+
+impl ScanSummary {
+    fn message(&self, elapsed: Duration, locale: NumberLocale) -> String {
+        let plural = |count: usize, one: &str, many: &str| {
+            let word = if count == 1 { one } else { many };
+            format!("{} {}", format::count(count, locale), word)
+        };
+        format!(
+            "Found {} in {}, skipped {} ({:.1} s)",
+            plural(self.images, "image", "images"),
+            plural(self.folders, "folder", "folders"),
+            plural(self.skipped, "file", "files"),
+            elapsed.as_secs_f64()
+        )
+    }
+}
+
+fn new_image_folder(
+    name: PathBuf,
+    thumbnails: Vector<Thumbnail>,
+    paths: Vector<Arc<PathBuf>>,
+    sort_order: SortOrder,
+) -> ImageFolder {
+    let (thumbnails, paths) = sort_images(thumbnails, paths, sort_order);
+    ImageFolder {
+        id: next_id(),
+        paths,
+        folder_thumbnail: Thumbnail {
+            id: 0,
+            index: 0,
+            image: ImageBuf::empty(),
+            format: FileFormat::Unknown,
+            partial: false,
+            captured: None,
+            flag: Flag::None,
+            rating: 0,
+            caption: None,
+            dimensions: None,
+            raw_pair: None,
+            timed_out: false,
+            modified: None,
+            file_size: None,
+            location: None,
+            failed: false,
+        },
+        thumbnails,
+        name: Arc::new(name),
+        selected: None,
+        collapsed: false,
+    }
+}
+
+fn submit_image_folder(
+    handle: &ExtEventSink,
+    generation: u64,
+    name: PathBuf,
+    thumbnails: Vector<Thumbnail>,
+    paths: Vector<Arc<PathBuf>>,
+    sort_order: SortOrder,
+) {
+    let image_folder = new_image_folder(name, thumbnails, paths, sort_order);
+    handle
+        .submit_command(
+            FINISHED_READING_FOLDER_IMAGE,
+            (generation, image_folder),
+            Target::Auto,
+        )
+        .unwrap();
+}
+
+fn sort_images(
+    thumbnails: Vector<Thumbnail>,
+    paths: Vector<Arc<PathBuf>>,
+    sort_order: SortOrder,
 ) -> (Vector<Thumbnail>, Vector<Arc<PathBuf>>) {
+    let mut images: Vec<(Thumbnail, Arc<PathBuf>)> =
+        thumbnails.into_iter().zip(paths).collect();
+    images.sort_by(|(_, a), (_, b)| natural_order::compare_paths(a, b));
+    if sort_order == SortOrder::CaptureTime {
+        // images without a capture time go last, staying sorted by name
+        images.sort_by_key(|(thumbnail, _)| {
+            (thumbnail.captured.is_none(), thumbnail.captured)
+        });
+    }
+    images
+        .into_iter()
+        .enumerate()
+        .map(|(index, (thumbnail, path))| {
+            (Thumbnail { index, ..thumbnail }, path)
+        })
+        .unzip()
+}
+
+// fills the disk thumbnail cache for every image that isn't cached yet, only
+// working while nothing else is being decoded or scanned
+fn pregenerate_thumbnails(
+    paths: Vec<Arc<PathBuf>>,
+    linear_light: bool,
+    handle: &ExtEventSink,
+) {
+    let generation = THUMBNAIL_PREGENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    let uncached: Vec<Arc<PathBuf>> = paths
+        .into_iter()
+        .filter(|path| !thumbnail_cache::is_cached(path, linear_light))
+        .collect();
+    let total = uncached.len();
+    for (i, path) in uncached.iter().enumerate() {
+        background::wait_for_idle();
+        if THUMBNAIL_PREGENERATION.load(Ordering::SeqCst) != generation {
+            return;
+        }
+        // only fills the caches, which don't depend on the orientation
+        if let Err(err) =
+            create_thumbnail_from_path(path, i, false, linear_light)
+        {
+            error!("Error creating thumbnail for {}: {}", path.display(), err);
+        }
+        handle
+            .submit_command(
+                THUMBNAIL_CACHE_PROGRESS,
+                Some((i + 1, total)),
+                Target::Auto,
+            )
+            .unwrap();
+    }
+    handle
+        .submit_command(THUMBNAIL_CACHE_PROGRESS, None, Target::Auto)
+        .unwrap();
+    if total > 0 {
+        let message = match total {
+            1 => "Cached the thumbnail of 1 image".to_string(),
+            total => format!("Cached the thumbnails of {} images", total),
+        };
+        let _ = handle.submit_command(SHOW_TOAST, message, Target::Auto);
+    }
+}
+
+// also counts the files that were skipped, RAW files shown with their JPEG
+// aren't among them
+fn check_folder_has_images(
+    folder: &Path,
+    settings: &Settings,
+    probes: &mut ProbeWorker,
+) -> (Vector<Thumbnail>, Vector<Arc<PathBuf>>, usize) {
+    let timeout = Duration::from_secs_f64(settings.scan_timeout);
     let mut images = Vector::new();
     let mut paths = Vector::new();
-    let entries = fs::read_dir(entry.path()).unwrap();
+    // the folder can be removed or become unreadable while a scan is on its
+    // way to it, it's skipped like files that can't be read
+    let dir = match fs::read_dir(folder) {
+        Ok(dir) => dir,
+        Err(err) => {
+            error!("Error reading {}: {}", folder.display(), err);
+            return (images, paths, 1);
+        }
+    };
+    let mut unreadable = 0;
+    let entries: Vec<_> = dir
+        .filter_map(|file| match file {
+            Ok(file) => Some(file),
+            Err(err) => {
+                error!("Error reading {}: {}", folder.display(), err);
+                unreadable += 1;
+                None
+            }
+        })
+        .filter(|file| file.path().is_file())
+        .collect();
+    let files = entries.len() + unreadable;
+    let flags = sidecar::load_flags(folder);
+    let ratings = sidecar::load_ratings(folder);
+    let captions = sidecar::load_captions(folder);
+    // RAW files are grouped with the JPEG of the same name instead of getting
+    // an entry of their own
+    let raws: HashMap<_, _> = entries
+        .iter()
+        .map(|file| file.path())
+        .filter(|path| is_raw(path))
+        .filter_map(|path| Some((path.file_stem()?.to_owned(), path.clone())))
+        .collect();
     for file in entries {
-        let file = file.unwrap();
-        if file.path().is_file() {
-            match Reader::open(file.path()) {
-                Ok(image) => match image.format() {
-                    Some(image::ImageFormat::Png)
-                    | Some(image::ImageFormat::Jpeg) => {}
-                    Some(_) | None => continue,
-                },
-                Err(err) => {
-                    error!("Error opening file: {}", err);
-                    continue;
+        let path = file.path();
+        // list mode never opens the files, so their names have to do
+        let result = if settings.list_mode {
+            Ok(probe_from_extension(&path))
+        } else {
+            probes.probe(&path, timeout)
+        };
+        let (probe, timed_out) = match result {
+            Ok(Some(probe)) => (probe, false),
+            Ok(None) | Err(RecvTimeoutError::Disconnected) => continue,
+            // keeps files that look like images by their extension so they
+            // can be retried later
+            Err(RecvTimeoutError::Timeout) => {
+                match probe_from_extension(&path) {
+                    Some(probe) => {
+                        error!("Timed out reading {}", path.display());
+                        (probe, true)
+                    }
+                    None => continue,
                 }
-            };
-            images.push_back(Thumbnail {
-                index: images.len(),
-                image: ImageBuf::empty(),
-            });
-            paths.push_back(Arc::new(file.path().to_path_buf()));
+            }
+        };
+        if settings.is_too_small(probe.dimensions) {
+            continue;
         }
+        let format = probe.format;
+        images.push_back(Thumbnail {
+            id: next_id(),
+            index: images.len(),
+            image: ImageBuf::empty(),
+            format,
+            partial: false,
+            captured: probe.captured,
+            flag: flags.get(&file.file_name()).copied().unwrap_or(Flag::None),
+            rating: ratings.get(&file.file_name()).copied().unwrap_or(0),
+            caption: captions.get(&file.file_name()).cloned(),
+            dimensions: probe.dimensions,
+            raw_pair: match format {
+                FileFormat::Jpeg => file
+                    .path()
+                    .file_stem()
+                    .and_then(|stem| raws.get(stem))
+                    .map(|raw| Arc::new(raw.clone())),
+                _ => None,
+            },
+            timed_out,
+            modified: probe.modified,
+            file_size: probe.file_size,
+            location: probe.location,
+            failed: false,
+        });
+        paths.push_back(Arc::new(path));
+    }
+    let paired = images
+        .iter()
+        .filter(|thumbnail| thumbnail.raw_pair.is_some())
+        .count();
+    let skipped = files.saturating_sub(images.len() + paired);
+    (images, paths, skipped)
+}
+
+// what can be told about an image without reading it
+fn probe_from_extension(path: &Path) -> Option<ImageProbe> {
+    match FileFormat::from_path(path) {
+        format @ FileFormat::Png
+        | format @ FileFormat::Jpeg
+        | format @ FileFormat::Psd
+        | format @ FileFormat::Avif
+        | format @ FileFormat::Jxl
+        | format @ FileFormat::Heif
+        | format @ FileFormat::Raw => Some(ImageProbe {
+            format,
+            captured: None,
+            dimensions: None,
+            modified: None,
+            file_size: None,
+            location: None,
+        }),
+        _ => None,
     }
-    (images, paths)
 }
 
 fn create_first_image_thumbnail(
     folder: &ImageFolder,
+    auto_rotate: bool,
+    linear_light: bool,
 ) -> Result<Thumbnail, ImageError> {
     let image_path = folder.paths[0].clone();
-    let image = Reader::open(image_path.as_ref())?
-        .with_guessed_format()?
-        .decode()?
-        .to_rgb8();
+    let (image, partial) = decode_lenient(&image_path)?;
+    let image = if auto_rotate {
+        apply_orientation(image, metadata::orientation(&image_path))
+    } else {
+        image
+    };
     const THUMBNAIL_HEIGHT_MAX: f64 = 250.;
     let image = find_largest_square_crop(&image);
     let (width, height) = image.dimensions();
@@ -276,7 +1465,7 @@ fn create_first_image_thumbnail(
         let scaled_height = height as f64 * scale;
         (scaled_width.trunc() as u32, scaled_height.trunc() as u32)
     };
-    let image = thumbnail(&image, new_width, new_height);
+    let image = downscale(&image, new_width, new_height, linear_light);
     let (width, height) = image.dimensions();
     let image = ImageBuf::from_raw(
         image.into_raw(),
@@ -285,7 +1474,24 @@ fn create_first_image_thumbnail(
         height as usize,
     );
 
-    Ok(Thumbnail { index: 0, image })
+    Ok(Thumbnail {
+        id: 0,
+        index: 0,
+        image,
+        format: FileFormat::from_path(&image_path),
+        partial,
+        captured: None,
+        flag: Flag::None,
+        rating: 0,
+        caption: None,
+        dimensions: None,
+        raw_pair: None,
+        timed_out: false,
+        modified: None,
+        file_size: None,
+        location: None,
+        failed: false,
+    })
 }
 
 fn find_largest_square_crop(
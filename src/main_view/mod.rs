@@ -1,5 +1,7 @@
 mod data;
 mod view;
 
-pub use data::{AppView, MainViewController};
+pub use data::{
+    move_root_folder, rescan_folder, sort_folders, AppView, MainViewController,
+};
 pub use view::main_view;
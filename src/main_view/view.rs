@@ -2,39 +2,109 @@ use druid::{
     commands::SHOW_OPEN_PANEL,
     lens,
     widget::{
-        Container, Controller, CrossAxisAlignment, Flex, Image, Label,
+        Container, Controller, CrossAxisAlignment, Either, Flex, Image, Label,
         MainAxisAlignment, Painter,
     },
-    Color, Command, Cursor, FileDialogOptions, ImageBuf, LensExt,
-    RenderContext, Target, Widget, WidgetExt,
+    Color, Command, Cursor, Env, EventCtx, FileDialogOptions, ImageBuf,
+    LensExt, RenderContext, Target, Widget, WidgetExt,
 };
 use druid_gridview::GridView;
 
 use crate::{
-    app_commands::SELECTED_FOLDER,
+    app_commands::{SELECTED_FOLDER, TOGGLE_FOLDER_COLLAPSED},
     app_data::{AppState, GalleryThumbnailController, ImageFolder},
-    widgets::{Button, Scroll},
+    background,
+    diagnostics::open_diagnostics,
+    format,
+    preferences::open_preferences,
+    settings::number_locale,
+    widgets::{text_button, Scroll},
 };
 
-use super::MainViewController;
+use super::{sort_folders, MainViewController};
 
 pub fn main_view() -> Box<dyn Widget<AppState>> {
-    let add_folder_btn = Button::new(
-        "+ Add Folder",
-        Color::BLACK,
-        Color::rgb8(0xff, 0xff, 0xff),
-        Color::rgb8(0xdd, 0xdd, 0xdd),
-        Color::rgb8(0x9f, 0x9f, 0x9f),
-        16.,
-    )
-    .on_click(|ctx, _data, _env| {
+    let add_folder_btn = text_button("+ Add Folder", |ctx, _data, _env| {
         let file_dialog = FileDialogOptions::new().select_directories();
         ctx.submit_command(SHOW_OPEN_PANEL.with(file_dialog));
     })
     .fix_height(50.);
 
+    let preferences_btn =
+        text_button("Preferences", |ctx, _data, _env| open_preferences(ctx))
+            .fix_height(50.);
+
+    let pause_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.pause_background {
+                "Resume".to_string()
+            } else {
+                "Pause".to_string()
+            }
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.pause_background = !data.settings.pause_background;
+            data.settings.save();
+            background::set_paused(data.settings.pause_background);
+        },
+    )
+    .fix_height(50.);
+
+    // folders found later go straight to their place in the order
+    let folder_sort_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            format!("Sort: {}", data.settings.folder_sort.label())
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.folder_sort = data.settings.folder_sort.next();
+            data.settings.save();
+            sort_folders(data);
+        },
+    )
+    .fix_height(50.);
+
+    let diagnostics_btn = text_button("Diagnostics", |ctx, data, _env| {
+        open_diagnostics(ctx, data)
+    })
+    .fix_height(50.);
+
+    // subtly shows the library being scanned again, or the thumbnails being
+    // cached in the background
+    let cache_progress = Label::dynamic(|data: &AppState, _env| {
+        let locale = data.settings.number_locale;
+        if data.settings.pause_background {
+            return "Background work paused".to_string();
+        }
+        if data.rescan_collapsed.is_some() {
+            return format!(
+                "Scanning, {} found",
+                match data.all_images.len() {
+                    1 => "1 folder".to_string(),
+                    count =>
+                        format!("{} folders", format::count(count, locale)),
+                }
+            );
+        }
+        match data.thumbnail_cache_progress {
+            Some((done, total)) => format!(
+                "Caching thumbnails {}/{}",
+                format::count(done, locale),
+                format::count(total, locale)
+            ),
+            None => String::new(),
+        }
+    })
+    .with_text_color(Color::rgb8(0x90, 0x90, 0x90))
+    .with_text_size(12.);
+
     let menu_btns = Container::new(
         Flex::row()
+            .with_child(cache_progress)
+            .with_spacer(10.)
+            .with_child(pause_btn)
+            .with_child(folder_sort_btn)
+            .with_child(diagnostics_btn)
+            .with_child(preferences_btn)
             .with_child(add_folder_btn)
             .must_fill_main_axis(true)
             .main_axis_alignment(MainAxisAlignment::End)
@@ -45,7 +115,8 @@ pub fn main_view() -> Box<dyn Widget<AppState>> {
     let gallery_list = GridView::new(image_gridview_builder)
         .wrap()
         .with_spacing(30.)
-        .padding(20.);
+        .padding(20.)
+        .env_scope(|env, data: &AppState| data.settings.set_env(env));
     let layout = Flex::column()
         .with_child(menu_btns)
         .with_child(gallery_list)
@@ -61,35 +132,70 @@ pub fn main_view() -> Box<dyn Widget<AppState>> {
     )
 }
 
-fn image_gridview_builder() -> impl Widget<(ImageFolder, usize)> {
+fn image_gridview_builder() -> impl Widget<(ImageFolder, usize, bool)> {
     // this lenses into the image folder found in the tuple
     // let thumbnails_lens = lens!((ImageFolder, usize), 0)
     //     .map(|data| data.thumbnails.clone(), |_folder, _put| ());
-    let folder_thumbnail_lens = lens!((ImageFolder, usize), 0)
+    let folder_thumbnail_lens = lens!((ImageFolder, usize, bool), 0)
         .map(|data| data.folder_thumbnail.clone(), |_folder, _put| ());
 
+    // the disclosure triangle collapses or expands the folder
+    let disclosure = Label::dynamic(
+        |(folder, _idx, _focused): &(ImageFolder, usize, bool), _env| {
+            if folder.collapsed {
+                "▸".to_string()
+            } else {
+                "▾".to_string()
+            }
+        },
+    )
+    .with_text_color(Color::BLACK)
+    .padding(5.)
+    .on_click(|ctx, (folder, _idx, _focused), _env| {
+        ctx.submit_command(TOGGLE_FOLDER_COLLAPSED.with(folder.id));
+    });
+
     // this will display the folder name
-    let folder_name =
-        Label::dynamic(|(folder, _idx): &(ImageFolder, usize), _env| {
+    let folder_name = Label::dynamic(
+        |(folder, _idx, _focused): &(ImageFolder, usize, bool), _env| {
             folder
                 .name
                 .file_name()
                 .unwrap()
                 .to_string_lossy()
                 .to_string()
-        })
-        .with_text_color(Color::BLACK)
-        .padding(5.);
+        },
+    )
+    .with_text_color(Color::BLACK)
+    .padding(5.)
+    .on_click(open_folder);
+
+    let image_count = Label::dynamic(
+        |(folder, _idx, _focused): &(ImageFolder, usize, bool), env: &Env| {
+            let locale = number_locale(env);
+            format!("{} images", format::count(folder.paths.len(), locale))
+        },
+    )
+    .with_text_color(Color::rgb8(0x60, 0x60, 0x60))
+    .padding(5.);
 
     let thumbnail = Image::new(ImageBuf::empty())
         .controller(GalleryThumbnailController)
         .lens(folder_thumbnail_lens)
         .fix_size(250., 250.);
+    let thumbnail = Either::new(
+        |(folder, _idx, _focused): &(ImageFolder, usize, bool), _env| {
+            folder.collapsed
+        },
+        image_count.fix_width(250.),
+        thumbnail,
+    )
+    .on_click(open_folder);
 
     Flex::column()
-        .with_child(folder_name)
+        .with_child(Flex::row().with_child(disclosure).with_child(folder_name))
         .with_child(thumbnail)
-        .background(Painter::new(|ctx, _data, _env| {
+        .background(Painter::new(|ctx, (_folder, _idx, focused), _env| {
             let is_hot = ctx.is_hot();
             let is_active = ctx.is_active();
             let (background_color, border_color, border_width) = if is_active {
@@ -98,7 +204,7 @@ fn image_gridview_builder() -> impl Widget<(ImageFolder, usize)> {
                     Color::rgb8(0x16, 0x69, 0xdd),
                     6.,
                 )
-            } else if is_hot {
+            } else if is_hot || *focused {
                 (
                     Color::rgb8(0xdd, 0xdd, 0xdd),
                     Color::rgb8(0x2a, 0x82, 0xfc),
@@ -117,26 +223,31 @@ fn image_gridview_builder() -> impl Widget<(ImageFolder, usize)> {
             ctx.fill(rect, &background_color);
         }))
         .controller(FolderThumbnailController)
-        .on_click(|ctx, (_folder, idx), _env| {
-            ctx.submit_command(Command::new(
-                SELECTED_FOLDER,
-                *idx,
-                Target::Auto,
-            ))
-        })
+}
+
+// everything in a folder tile except the disclosure triangle opens the folder
+fn open_folder(
+    ctx: &mut EventCtx,
+    (folder, _idx, _focused): &mut (ImageFolder, usize, bool),
+    _env: &Env,
+) {
+    ctx.submit_command(Command::new(SELECTED_FOLDER, folder.id, Target::Auto))
 }
 
 struct FolderThumbnailController;
 
-impl Controller<(ImageFolder, usize), Container<(ImageFolder, usize)>>
-    for FolderThumbnailController
+impl
+    Controller<
+        (ImageFolder, usize, bool),
+        Container<(ImageFolder, usize, bool)>,
+    > for FolderThumbnailController
 {
     fn event(
         &mut self,
-        child: &mut Container<(ImageFolder, usize)>,
+        child: &mut Container<(ImageFolder, usize, bool)>,
         ctx: &mut druid::EventCtx,
         event: &druid::Event,
-        data: &mut (ImageFolder, usize),
+        data: &mut (ImageFolder, usize, bool),
         env: &druid::Env,
     ) {
         if ctx.is_hot() {
@@ -147,10 +258,10 @@ impl Controller<(ImageFolder, usize), Container<(ImageFolder, usize)>>
 
     fn lifecycle(
         &mut self,
-        child: &mut Container<(ImageFolder, usize)>,
+        child: &mut Container<(ImageFolder, usize, bool)>,
         ctx: &mut druid::LifeCycleCtx,
         event: &druid::LifeCycle,
-        data: &(ImageFolder, usize),
+        data: &(ImageFolder, usize, bool),
         env: &druid::Env,
     ) {
         child.lifecycle(ctx, event, data, env)
@@ -158,10 +269,10 @@ impl Controller<(ImageFolder, usize), Container<(ImageFolder, usize)>>
 
     fn update(
         &mut self,
-        child: &mut Container<(ImageFolder, usize)>,
+        child: &mut Container<(ImageFolder, usize, bool)>,
         ctx: &mut druid::UpdateCtx,
-        old_data: &(ImageFolder, usize),
-        data: &(ImageFolder, usize),
+        old_data: &(ImageFolder, usize, bool),
+        data: &(ImageFolder, usize, bool),
         env: &druid::Env,
     ) {
         child.update(ctx, old_data, data, env)
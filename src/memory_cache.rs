@@ -0,0 +1,201 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use image::RgbImage;
+use once_cell::sync::Lazy;
+
+const BYTES_PER_MB: usize = 1024 * 1024;
+
+// every decoded image kept in memory, thumbnails and full images share one
+// budget so the app stays within a predictable footprint
+static CACHE: Lazy<Mutex<MemoryCache>> =
+    Lazy::new(|| Mutex::new(MemoryCache::new()));
+// thumbnails are kept at 16 bits per pixel instead of 24
+static COMPACT_THUMBNAILS: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CacheKind {
+    Thumbnail,
+    // thumbnails downscaled in linear light, kept apart so switching the
+    // quality doesn't show the other kind
+    LinearThumbnail,
+    FullImage,
+}
+
+// how an entry's pixels are kept
+enum Pixels {
+    Rgb(Arc<RgbImage>),
+    // 5 bits of red, 6 of green and 5 of blue packed into two bytes
+    Rgb565 {
+        width: u32,
+        height: u32,
+        data: Vec<u8>,
+    },
+}
+
+impl Pixels {
+    fn pack(image: &RgbImage) -> Self {
+        let mut data = Vec::with_capacity(image.as_raw().len() / 3 * 2);
+        for pixel in image.pixels() {
+            let [r, g, b] = pixel.0;
+            let packed =
+                (r as u16 >> 3) << 11 | (g as u16 >> 2) << 5 | b as u16 >> 3;
+            data.extend_from_slice(&packed.to_le_bytes());
+        }
+        let (width, height) = image.dimensions();
+        Pixels::Rgb565 {
+            width,
+            height,
+            data,
+        }
+    }
+
+    fn bytes(&self) -> usize {
+        match self {
+            Pixels::Rgb(image) => image.as_raw().len(),
+            Pixels::Rgb565 { data, .. } => data.len(),
+        }
+    }
+
+    fn to_rgb(&self) -> Arc<RgbImage> {
+        match self {
+            Pixels::Rgb(image) => image.clone(),
+            Pixels::Rgb565 {
+                width,
+                height,
+                data,
+            } => {
+                let mut raw = Vec::with_capacity(data.len() / 2 * 3);
+                for packed in data.chunks_exact(2) {
+                    let packed = u16::from_le_bytes([packed[0], packed[1]]);
+                    // repeats the top bits in the ones that were dropped so
+                    // full intensity stays at 255
+                    let r = (packed >> 11) as u8;
+                    let g = (packed >> 5 & 0x3f) as u8;
+                    let b = (packed & 0x1f) as u8;
+                    raw.extend_from_slice(&[
+                        r << 3 | r >> 2,
+                        g << 2 | g >> 4,
+                        b << 3 | b >> 2,
+                    ]);
+                }
+                Arc::new(RgbImage::from_raw(*width, *height, raw).unwrap())
+            }
+        }
+    }
+}
+
+struct CacheEntry {
+    pixels: Pixels,
+    bytes: usize,
+    // value of the cache's clock the last time this entry was used
+    last_used: u64,
+}
+
+struct MemoryCache {
+    entries: HashMap<(CacheKind, PathBuf), CacheEntry>,
+    budget: usize,
+    usage: usize,
+    clock: u64,
+}
+
+impl MemoryCache {
+    fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            budget: 0,
+            usage: 0,
+            clock: 0,
+        }
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    // evicts the least recently used entries, no matter what kind they are,
+    // until the cache fits in the budget
+    fn evict(&mut self) {
+        while self.usage > self.budget {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_key, entry)| entry.last_used)
+                .map(|(key, _entry)| key.clone());
+            match oldest {
+                Some(key) => {
+                    let entry = self.entries.remove(&key).unwrap();
+                    self.usage -= entry.bytes;
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+pub fn get(kind: CacheKind, path: &Path) -> Option<Arc<RgbImage>> {
+    let mut cache = CACHE.lock().unwrap();
+    let now = cache.tick();
+    let entry = cache.entries.get_mut(&(kind, path.to_path_buf()))?;
+    entry.last_used = now;
+    Some(entry.pixels.to_rgb())
+}
+
+pub fn insert(kind: CacheKind, path: &Path, image: Arc<RgbImage>) {
+    let pixels = match kind {
+        CacheKind::Thumbnail | CacheKind::LinearThumbnail
+            if COMPACT_THUMBNAILS.load(Ordering::Relaxed) =>
+        {
+            Pixels::pack(&image)
+        }
+        _ => Pixels::Rgb(image),
+    };
+    let mut cache = CACHE.lock().unwrap();
+    let bytes = pixels.bytes();
+    // an image bigger than the whole budget would only evict everything else
+    if bytes > cache.budget {
+        return;
+    }
+    let last_used = cache.tick();
+    let entry = CacheEntry {
+        pixels,
+        bytes,
+        last_used,
+    };
+    if let Some(old) = cache.entries.insert((kind, path.to_path_buf()), entry) {
+        cache.usage -= old.bytes;
+    }
+    cache.usage += bytes;
+    cache.evict();
+}
+
+pub fn remove(kind: CacheKind, path: &Path) {
+    let mut cache = CACHE.lock().unwrap();
+    if let Some(old) = cache.entries.remove(&(kind, path.to_path_buf())) {
+        cache.usage -= old.bytes;
+    }
+}
+
+pub fn set_budget_mb(budget: usize) {
+    let mut cache = CACHE.lock().unwrap();
+    cache.budget = budget * BYTES_PER_MB;
+    cache.evict();
+}
+
+// thumbnails already in the cache stay the way they were stored
+pub fn set_compact_thumbnails(compact: bool) {
+    COMPACT_THUMBNAILS.store(compact, Ordering::Relaxed);
+}
+
+// current usage in megabytes
+pub fn usage_mb() -> usize {
+    let cache = CACHE.lock().unwrap();
+    cache.usage / BYTES_PER_MB
+}
@@ -0,0 +1,419 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufReader, ErrorKind},
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use exif::{Exif, In, Reader, Tag, Value};
+use image::ImageFormat;
+use log::error;
+use once_cell::sync::Lazy;
+
+use crate::{
+    app_data::FileFormat,
+    decode::{is_psd, psd_dimensions},
+    settings::CaptionContent,
+    thumbnailer,
+};
+
+// summaries of images that have been shown, so painting a caption doesn't
+// have to read the file
+static SUMMARIES: Lazy<Mutex<HashMap<PathBuf, Arc<ExifSummary>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+// the summaries are small, but a slideshow through a huge folder shouldn't
+// keep all of them
+const MAX_SUMMARIES: usize = 1000;
+
+// what scanning reads from an image file
+#[derive(Debug, Clone)]
+pub struct ImageProbe {
+    pub format: FileFormat,
+    pub captured: Option<i64>,
+    pub dimensions: Option<(u32, u32)>,
+    pub modified: Option<i64>,
+    pub file_size: Option<u64>,
+    pub location: Option<(f64, f64)>,
+}
+
+// how long the first retry of a failed open waits, each one after waits
+// twice as long as the last
+const RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+// opens the file, trying again a few times when the error might go away on
+// its own, like on a network share or while the file is still being copied
+fn open_with_retries(
+    path: &Path,
+    retries: u32,
+) -> io::Result<image::io::Reader<BufReader<File>>> {
+    let mut backoff = RETRY_BACKOFF;
+    let mut attempt = 0;
+    loop {
+        match image::io::Reader::open(path) {
+            Ok(reader) => return Ok(reader),
+            Err(err) if attempt < retries && is_transient(&err) => {
+                thread::sleep(backoff);
+                backoff *= 2;
+                attempt += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+// errors that won't change by trying again fail right away
+fn is_transient(err: &io::Error) -> bool {
+    !matches!(
+        err.kind(),
+        ErrorKind::NotFound
+            | ErrorKind::PermissionDenied
+            | ErrorKind::InvalidInput
+            | ErrorKind::InvalidData
+    )
+}
+
+// None when the file isn't an image the viewer shows
+pub fn probe_image(path: &Path, retries: u32) -> Option<ImageProbe> {
+    let format = match open_with_retries(path, retries) {
+        Ok(image) => match image.format() {
+            Some(ImageFormat::Png) => FileFormat::Png,
+            Some(ImageFormat::Jpeg) => FileFormat::Jpeg,
+            None if is_psd(path) => FileFormat::Psd,
+            Some(_) | None => match thumbnailer::format(path) {
+                Some(format) => format,
+                None => return None,
+            },
+        },
+        Err(err) => {
+            error!("Error opening file: {}", err);
+            return None;
+        }
+    };
+    Some(ImageProbe {
+        format,
+        captured: capture_time(path),
+        dimensions: match format {
+            FileFormat::Psd => psd_dimensions(path),
+            _ => image::image_dimensions(path).ok(),
+        },
+        modified: modified_time(path),
+        file_size: path.metadata().ok().map(|metadata| metadata.len()),
+        location: gps_location(path),
+    })
+}
+
+// probes files one after the other on a thread of its own, so a scan
+// doesn't start a thread for every file, and gives up on a file once the
+// timeout passes. A file on an unresponsive drive keeps the thread blocked
+// until the read returns, so a new one takes over for the files after it
+pub struct ProbeWorker {
+    retries: u32,
+    worker: Option<(Sender<PathBuf>, Receiver<Option<ImageProbe>>)>,
+}
+
+impl ProbeWorker {
+    pub fn new(retries: u32) -> Self {
+        Self {
+            retries,
+            worker: None,
+        }
+    }
+
+    pub fn probe(
+        &mut self,
+        path: &Path,
+        timeout: Duration,
+    ) -> Result<Option<ImageProbe>, RecvTimeoutError> {
+        let retries = self.retries;
+        let (paths, probes) = self
+            .worker
+            .get_or_insert_with(|| spawn_probe_worker(retries));
+        if paths.send(path.to_path_buf()).is_err() {
+            self.worker = None;
+            return Err(RecvTimeoutError::Disconnected);
+        }
+        let result = probes.recv_timeout(timeout);
+        if result.is_err() {
+            self.worker = None;
+        }
+        result
+    }
+}
+
+// the thread stops once the worker drops its sender, or once it finishes a
+// file nobody waits for anymore
+fn spawn_probe_worker(
+    retries: u32,
+) -> (Sender<PathBuf>, Receiver<Option<ImageProbe>>) {
+    let (path_sender, path_receiver) = mpsc::channel::<PathBuf>();
+    let (probe_sender, probe_receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for path in path_receiver {
+            if probe_sender.send(probe_image(&path, retries)).is_err() {
+                break;
+            }
+        }
+    });
+    (path_sender, probe_receiver)
+}
+
+// the shooting details a caption shows
+#[derive(Debug, Clone, Default)]
+pub struct ExifSummary {
+    pub camera: Option<String>,
+    pub lens: Option<String>,
+    // shutter speed, aperture, ISO and focal length, the ones that are known
+    pub exposure: Option<String>,
+}
+
+impl ExifSummary {
+    pub fn caption(&self, content: CaptionContent) -> Option<String> {
+        let camera = [&self.camera, &self.lens]
+            .iter()
+            .filter_map(|part| part.as_deref())
+            .collect::<Vec<_>>()
+            .join(" · ");
+        let camera = Some(camera).filter(|camera| !camera.is_empty());
+        match content {
+            CaptionContent::Camera => camera,
+            CaptionContent::Exposure => self.exposure.clone(),
+            // what the user wrote isn't part of the EXIF
+            CaptionContent::Description => None,
+            CaptionContent::Full => match (camera, &self.exposure) {
+                (Some(camera), Some(exposure)) => {
+                    Some(format!("{}\n{}", camera, exposure))
+                }
+                (camera, exposure) => camera.or_else(|| exposure.clone()),
+            },
+        }
+    }
+}
+
+// reads the image's shooting details and keeps them for cached_exif_summary
+pub fn read_exif_summary(path: &Path) -> Arc<ExifSummary> {
+    let summary = Arc::new(exif_summary(path).unwrap_or_default());
+    let mut summaries = SUMMARIES.lock().unwrap();
+    if summaries.len() >= MAX_SUMMARIES {
+        summaries.clear();
+    }
+    summaries.insert(path.to_path_buf(), summary.clone());
+    summary
+}
+
+pub fn cached_exif_summary(path: &Path) -> Option<Arc<ExifSummary>> {
+    SUMMARIES.lock().unwrap().get(path).cloned()
+}
+
+fn exif_summary(path: &Path) -> Option<ExifSummary> {
+    let mut file = BufReader::new(File::open(path).ok()?);
+    let exif = Reader::new().read_from_container(&mut file).ok()?;
+    let text = |tag: Tag| {
+        let field = exif.get_field(tag, In::PRIMARY)?;
+        let value = field.display_value().to_string();
+        let value = value.trim_matches(|c| c == '"' || c == ' ').to_string();
+        Some(value).filter(|value| !value.is_empty())
+    };
+    let camera = match (text(Tag::Make), text(Tag::Model)) {
+        // models usually start with the make already
+        (Some(make), Some(model)) if model.starts_with(&make) => Some(model),
+        (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+        (make, model) => make.or(model),
+    };
+    let exposure = [
+        with_unit(&exif, Tag::ExposureTime),
+        with_unit(&exif, Tag::FNumber),
+        text(Tag::PhotographicSensitivity).map(|iso| format!("ISO {}", iso)),
+        with_unit(&exif, Tag::FocalLength),
+    ]
+    .iter()
+    .flatten()
+    .cloned()
+    .collect::<Vec<_>>()
+    .join("  ");
+    Some(ExifSummary {
+        camera,
+        lens: text(Tag::LensModel),
+        exposure: Some(exposure).filter(|exposure| !exposure.is_empty()),
+    })
+}
+
+// every EXIF field of the image itself, one "tag: value" line each
+pub fn exif_text(path: &Path) -> Option<String> {
+    let mut file = BufReader::new(File::open(path).ok()?);
+    let exif = Reader::new().read_from_container(&mut file).ok()?;
+    let text = exif
+        .fields()
+        .filter(|field| field.ifd_num == In::PRIMARY)
+        .map(|field| {
+            format!("{}: {}", field.tag, field.display_value().with_unit(&exif))
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(text).filter(|text| !text.is_empty())
+}
+
+// the value as EXIF readers usually show it, like "1/250 s" or "f/2.8"
+fn with_unit(exif: &Exif, tag: Tag) -> Option<String> {
+    let field = exif.get_field(tag, In::PRIMARY)?;
+    Some(field.display_value().with_unit(exif).to_string())
+}
+
+// seconds since the unix epoch the photo was taken at, read from the EXIF
+// data. Files without one don't get their modification time instead, so
+// they sort after the photos rather than amongst them
+pub fn capture_time(path: &Path) -> Option<i64> {
+    let mut file = BufReader::new(File::open(path).ok()?);
+    let exif = Reader::new().read_from_container(&mut file).ok()?;
+    let field = exif
+        .get_field(Tag::DateTimeOriginal, In::PRIMARY)
+        .or_else(|| exif.get_field(Tag::DateTime, In::PRIMARY))?;
+    match &field.value {
+        Value::Ascii(values) => parse_exif_date(values.first()?),
+        _ => None,
+    }
+}
+
+// latitude and longitude in degrees, south and west being negative
+pub fn gps_location(path: &Path) -> Option<(f64, f64)> {
+    let mut file = BufReader::new(File::open(path).ok()?);
+    let exif = Reader::new().read_from_container(&mut file).ok()?;
+    let latitude = gps_degrees(&exif, Tag::GPSLatitude, Tag::GPSLatitudeRef)?;
+    let longitude =
+        gps_degrees(&exif, Tag::GPSLongitude, Tag::GPSLongitudeRef)?;
+    Some((latitude, longitude))
+}
+
+// GPS coordinates are stored as degrees, minutes and seconds with the
+// hemisphere in a tag of its own
+fn gps_degrees(exif: &Exif, tag: Tag, reference: Tag) -> Option<f64> {
+    let parts = match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Rational(parts) if parts.len() == 3 => parts.clone(),
+        _ => return None,
+    };
+    let degrees =
+        parts[0].to_f64() + parts[1].to_f64() / 60. + parts[2].to_f64() / 3600.;
+    if !degrees.is_finite() {
+        return None;
+    }
+    let negative = match &exif.get_field(reference, In::PRIMARY)?.value {
+        Value::Ascii(values) => matches!(
+            values.first().and_then(|value| value.first()),
+            Some(b'S') | Some(b'W')
+        ),
+        _ => false,
+    };
+    Some(if negative { -degrees } else { degrees })
+}
+
+// the EXIF orientation, 1 meaning the pixels are stored upright
+pub fn orientation(path: &Path) -> u32 {
+    let read = || {
+        let mut file = BufReader::new(File::open(path).ok()?);
+        let exif = Reader::new().read_from_container(&mut file).ok()?;
+        exif.get_field(Tag::Orientation, In::PRIMARY)?
+            .value
+            .get_uint(0)
+    };
+    read().unwrap_or(1)
+}
+
+// seconds since the unix epoch the file was last modified
+pub fn modified_time(path: &Path) -> Option<i64> {
+    let modified = path.metadata().ok()?.modified().ok()?;
+    let since_epoch = modified.duration_since(UNIX_EPOCH).ok()?;
+    Some(since_epoch.as_secs() as i64)
+}
+
+// EXIF dates look like "2021:06:14 18:03:27" and have no time zone, so they
+// are treated as UTC
+fn parse_exif_date(date: &[u8]) -> Option<i64> {
+    let date = std::str::from_utf8(date).ok()?.trim();
+    let mut parts = date
+        .split(|c| c == ':' || c == ' ')
+        .map(|part| part.parse::<i64>().ok());
+    let year = parts.next()??;
+    let month = parts.next()??;
+    let day = parts.next()??;
+    let hour = parts.next()??;
+    let minute = parts.next()??;
+    let second = parts.next()??;
+    if year == 0 || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    Some(days * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+// number of days since 1970-01-01 for a date in the proleptic gregorian
+// calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era =
+        year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_errors_that_can_go_away() {
+        for kind in [
+            ErrorKind::Interrupted,
+            ErrorKind::TimedOut,
+            ErrorKind::WouldBlock,
+            ErrorKind::Other,
+        ]
+        .iter()
+        {
+            assert!(is_transient(&io::Error::from(*kind)), "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn fails_right_away_on_errors_that_stay() {
+        for kind in [
+            ErrorKind::NotFound,
+            ErrorKind::PermissionDenied,
+            ErrorKind::InvalidInput,
+            ErrorKind::InvalidData,
+        ]
+        .iter()
+        {
+            assert!(!is_transient(&io::Error::from(*kind)), "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn parses_exif_dates() {
+        assert_eq!(parse_exif_date(b"1970:01:01 00:00:00"), Some(0));
+        assert_eq!(parse_exif_date(b"2021:03:04 05:06:07"), Some(1614834367));
+        assert_eq!(parse_exif_date(b"2000:02:29 23:59:59"), Some(951868799));
+    }
+
+    #[test]
+    fn ignores_padding_around_exif_dates() {
+        assert_eq!(parse_exif_date(b" 1970:01:01 00:00:00 "), Some(0));
+    }
+
+    #[test]
+    fn rejects_blank_and_invalid_exif_dates() {
+        assert_eq!(parse_exif_date(b"    :  :     :  :  "), None);
+        assert_eq!(parse_exif_date(b"0000:00:00 00:00:00"), None);
+        assert_eq!(parse_exif_date(b"2021:13:01 00:00:00"), None);
+        assert_eq!(parse_exif_date(b"2021:01:32 00:00:00"), None);
+        assert_eq!(parse_exif_date(b"2021:01:01"), None);
+        assert_eq!(parse_exif_date(&[0xff, 0xfe]), None);
+    }
+}
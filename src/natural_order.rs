@@ -0,0 +1,107 @@
+use std::{cmp::Ordering, iter::Peekable, path::Path, str::Chars};
+
+// orders names the way file managers do, numbers by their value so img2
+// comes before img10 and letters ignoring case
+pub fn compare(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x = take_number(&mut a_chars);
+                let y = take_number(&mut b_chars);
+                let order = compare_numbers(&x, &y);
+                if order != Ordering::Equal {
+                    return order;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let order = x.to_lowercase().cmp(y.to_lowercase());
+                if order != Ordering::Equal {
+                    return order;
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
+pub fn compare_paths(a: &Path, b: &Path) -> Ordering {
+    compare(&a.to_string_lossy(), &b.to_string_lossy())
+}
+
+fn take_number(chars: &mut Peekable<Chars>) -> String {
+    let mut number = String::new();
+    while let Some(digit) = chars.peek().copied().filter(char::is_ascii_digit) {
+        number.push(digit);
+        chars.next();
+    }
+    number
+}
+
+// compares by value without parsing, so any number of digits works, and
+// puts the one with fewer leading zeros first when the values are the same
+fn compare_numbers(a: &str, b: &str) -> Ordering {
+    let a_value = a.trim_start_matches('0');
+    let b_value = b.trim_start_matches('0');
+    a_value
+        .len()
+        .cmp(&b_value.len())
+        .then_with(|| a_value.cmp(b_value))
+        .then_with(|| a.len().cmp(&b.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_numbers_by_value() {
+        assert_eq!(compare("img2", "img10"), Ordering::Less);
+        assert_eq!(compare("img10", "img2"), Ordering::Greater);
+        assert_eq!(compare("img10", "img10"), Ordering::Equal);
+        assert_eq!(compare("2 beach", "10 beach"), Ordering::Less);
+        assert_eq!(
+            compare(
+                "img99999999999999999999999",
+                "img100000000000000000000000"
+            ),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn puts_fewer_leading_zeros_first() {
+        assert_eq!(compare("img007", "img8"), Ordering::Less);
+        assert_eq!(compare("img7", "img007"), Ordering::Less);
+        assert_eq!(compare("img007", "img07"), Ordering::Greater);
+        assert_eq!(compare("img0", "img00"), Ordering::Less);
+    }
+
+    #[test]
+    fn ignores_case() {
+        assert_eq!(compare("Apple", "banana"), Ordering::Less);
+        assert_eq!(compare("apple", "Banana"), Ordering::Less);
+        assert_eq!(compare("IMG2", "img10"), Ordering::Less);
+        // names that only differ in case still get a fixed order
+        assert_eq!(compare("IMG1", "img1"), Ordering::Less);
+    }
+
+    #[test]
+    fn puts_shorter_names_first() {
+        assert_eq!(compare("img", "img1"), Ordering::Less);
+        assert_eq!(compare("img1", "img1a"), Ordering::Less);
+    }
+
+    #[test]
+    fn compares_whole_paths() {
+        assert_eq!(
+            compare_paths(Path::new("a/img2.jpg"), Path::new("a/img10.jpg")),
+            Ordering::Less
+        );
+    }
+}
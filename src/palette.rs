@@ -0,0 +1,117 @@
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use druid::{im::Vector, Data};
+use image::RgbImage;
+use once_cell::sync::Lazy;
+
+// palettes of images that have been shown, so flicking back to one doesn't
+// go through its pixels again
+static PALETTES: Lazy<Mutex<HashMap<PathBuf, Arc<ImagePalette>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+const MAX_PALETTES: usize = 200;
+// how many dominant colors are picked out
+const PALETTE_SIZE: usize = 6;
+// the dominant colors are worked out from at most this many pixels spread
+// evenly over the image, more doesn't change them noticeably
+const MAX_SAMPLES: usize = 50_000;
+
+#[derive(Debug, Clone, Data, Default)]
+pub struct ImagePalette {
+    pub average: (u8, u8, u8),
+    // the colors covering the most of the image come first
+    pub dominant: Vector<(u8, u8, u8)>,
+}
+
+pub fn hex((r, g, b): (u8, u8, u8)) -> String {
+    format!("#{:02X}{:02X}{:02X}", r, g, b)
+}
+
+pub fn cached_palette(path: &Path) -> Option<Arc<ImagePalette>> {
+    PALETTES.lock().unwrap().get(path).cloned()
+}
+
+// goes through all of the image's pixels, so large images should be done
+// off the main thread
+pub fn image_palette(path: &Path, image: &RgbImage) -> Arc<ImagePalette> {
+    if let Some(palette) = cached_palette(path) {
+        return palette;
+    }
+    let pixels: Vec<[u8; 3]> = image.pixels().map(|pixel| pixel.0).collect();
+    let step = (pixels.len() / MAX_SAMPLES).max(1);
+    let samples = pixels.iter().step_by(step).copied().collect();
+    let palette = Arc::new(ImagePalette {
+        average: average(&pixels),
+        dominant: median_cut(samples, PALETTE_SIZE).into_iter().collect(),
+    });
+    let mut palettes = PALETTES.lock().unwrap();
+    if palettes.len() >= MAX_PALETTES {
+        palettes.clear();
+    }
+    palettes.insert(path.to_path_buf(), palette.clone());
+    palette
+}
+
+fn average(pixels: &[[u8; 3]]) -> (u8, u8, u8) {
+    if pixels.is_empty() {
+        return (0, 0, 0);
+    }
+    let mut sums = [0u64; 3];
+    for pixel in pixels {
+        for (sum, value) in sums.iter_mut().zip(pixel.iter()) {
+            *sum += *value as u64;
+        }
+    }
+    let count = pixels.len() as u64;
+    let channel = |sum: u64| ((sum + count / 2) / count) as u8;
+    (channel(sums[0]), channel(sums[1]), channel(sums[2]))
+}
+
+// splits the colors into boxes, each time cutting the box with the widest
+// spread of one channel at that channel's median, and gives the average
+// color of each box
+fn median_cut(pixels: Vec<[u8; 3]>, colors: usize) -> Vec<(u8, u8, u8)> {
+    if pixels.is_empty() {
+        return Vec::new();
+    }
+    let mut boxes = vec![pixels];
+    while boxes.len() < colors {
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .map(|(idx, colors)| {
+                let (channel, range) = widest_channel(colors);
+                (idx, channel, range)
+            })
+            .max_by_key(|(_, _, range)| *range);
+        let (idx, channel) = match widest {
+            Some((idx, channel, range)) if range > 0 => (idx, channel),
+            // every box is a single color already
+            _ => break,
+        };
+        let mut colors = boxes.swap_remove(idx);
+        colors.sort_unstable_by_key(|pixel| pixel[channel]);
+        let upper = colors.split_off(colors.len() / 2);
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+    boxes.sort_by_key(|colors| Reverse(colors.len()));
+    boxes.iter().map(|colors| average(colors)).collect()
+}
+
+// the channel whose values are furthest apart, and how far apart they are
+fn widest_channel(pixels: &[[u8; 3]]) -> (usize, u8) {
+    (0..3)
+        .map(|channel| {
+            let values = pixels.iter().map(|pixel| pixel[channel]);
+            let min = values.clone().min().unwrap_or(0);
+            let max = values.max().unwrap_or(0);
+            (channel, max - min)
+        })
+        .max_by_key(|(_, range)| *range)
+        .unwrap()
+}
@@ -0,0 +1,934 @@
+use std::{path::PathBuf, sync::Arc};
+
+use druid::{
+    commands::{OPEN_FILE, SHOW_OPEN_PANEL},
+    im::Vector,
+    lens,
+    widget::{
+        Controller, CrossAxisAlignment, Either, Flex, Label, List, Scroll,
+        SizedBox,
+    },
+    Color, Command, Env, Event, EventCtx, FileDialogOptions, Target, Widget,
+    WidgetExt, WindowDesc,
+};
+
+use crate::{
+    app_commands::{MOVE_ROOT_FOLDER, RESCAN_ALL_FOLDERS},
+    app_data::AppState,
+    background, format,
+    main_view::move_root_folder,
+    memory_cache, render_cache,
+    settings::EXTERNAL_THUMBNAILERS,
+    thumbnailer,
+    widgets::text_button,
+};
+
+// opens the preferences in a window of their own, they edit the app's
+// settings directly so changes show up right away
+pub fn open_preferences(ctx: &mut EventCtx) {
+    let window = WindowDesc::new(preferences_view)
+        .title("Preferences")
+        .window_size((440., 640.));
+    ctx.new_window(window);
+}
+
+fn preferences_view() -> impl Widget<AppState> {
+    let combine_subfolders_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.combine_subfolders {
+                "On".to_string()
+            } else {
+                "Off".to_string()
+            }
+        },
+        |ctx, data: &mut AppState, _env| {
+            data.settings.combine_subfolders =
+                !data.settings.combine_subfolders;
+            ctx.submit_command(Command::new(
+                RESCAN_ALL_FOLDERS,
+                (),
+                Target::Global,
+            ));
+        },
+    );
+
+    let navigation_scope_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.navigate_within_folder {
+                "Within Folder".to_string()
+            } else {
+                "Whole Gallery".to_string()
+            }
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.navigate_within_folder =
+                !data.settings.navigate_within_folder;
+        },
+    );
+
+    let sort_order_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            data.settings.sort_order.label().to_string()
+        },
+        |ctx, data: &mut AppState, _env| {
+            data.settings.sort_order = data.settings.sort_order.next();
+            ctx.submit_command(Command::new(
+                RESCAN_ALL_FOLDERS,
+                (),
+                Target::Global,
+            ));
+        },
+    );
+
+    // cycles from the grid to masonry to details and back
+    let gallery_layout_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.details_gallery {
+                "Details".to_string()
+            } else if data.settings.masonry_gallery {
+                "Masonry".to_string()
+            } else {
+                "Grid".to_string()
+            }
+        },
+        |_ctx, data: &mut AppState, _env| {
+            let settings = &mut data.settings;
+            if settings.details_gallery {
+                settings.details_gallery = false;
+                settings.masonry_gallery = false;
+            } else if settings.masonry_gallery {
+                settings.details_gallery = true;
+            } else {
+                settings.masonry_gallery = true;
+            }
+        },
+    );
+
+    let date_grouping_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            data.settings.date_grouping.label().to_string()
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.date_grouping = data.settings.date_grouping.next();
+        },
+    );
+
+    // takes effect for folders scanned from now on, so they're rescanned
+    let list_mode_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.list_mode {
+                "Names Only".to_string()
+            } else {
+                "Thumbnails".to_string()
+            }
+        },
+        |ctx, data: &mut AppState, _env| {
+            data.settings.list_mode = !data.settings.list_mode;
+            ctx.submit_command(Command::new(
+                RESCAN_ALL_FOLDERS,
+                (),
+                Target::Global,
+            ));
+        },
+    );
+
+    // both change which images are found, so the folders are rescanned
+    let small_images_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.skip_small_images {
+                "Skip".to_string()
+            } else {
+                "Show".to_string()
+            }
+        },
+        |ctx, data: &mut AppState, _env| {
+            data.settings.skip_small_images = !data.settings.skip_small_images;
+            ctx.submit_command(Command::new(
+                RESCAN_ALL_FOLDERS,
+                (),
+                Target::Global,
+            ));
+        },
+    );
+
+    // changes which folders are found, so the folders are rescanned
+    let exclusion_marker_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.exclusion_marker.is_empty() {
+                "Off".to_string()
+            } else {
+                data.settings.exclusion_marker.clone()
+            }
+        },
+        |ctx, data: &mut AppState, _env| {
+            data.settings.exclusion_marker =
+                data.settings.next_exclusion_marker();
+            ctx.submit_command(Command::new(
+                RESCAN_ALL_FOLDERS,
+                (),
+                Target::Global,
+            ));
+        },
+    );
+
+    // the formats it reads are only found by a scan, so the folders are
+    // rescanned
+    let external_thumbnailer_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            let command = &data.settings.external_thumbnailer;
+            match command.split_whitespace().next() {
+                None => "Off".to_string(),
+                Some(program)
+                    if EXTERNAL_THUMBNAILERS.contains(&command.as_str()) =>
+                {
+                    program.to_string()
+                }
+                Some(_) => "Custom".to_string(),
+            }
+        },
+        |ctx, data: &mut AppState, _env| {
+            data.settings.external_thumbnailer =
+                data.settings.next_external_thumbnailer();
+            thumbnailer::set_command(&data.settings.external_thumbnailer);
+            ctx.submit_command(Command::new(
+                RESCAN_ALL_FOLDERS,
+                (),
+                Target::Global,
+            ));
+        },
+    );
+
+    let min_image_size_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            format!("Under {} px", data.settings.min_image_size)
+        },
+        |ctx, data: &mut AppState, _env| {
+            data.settings.min_image_size = data.settings.next_min_image_size();
+            if data.settings.skip_small_images {
+                ctx.submit_command(Command::new(
+                    RESCAN_ALL_FOLDERS,
+                    (),
+                    Target::Global,
+                ));
+            }
+        },
+    );
+
+    let paused_decodes_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.pause_foreground {
+                "Wait".to_string()
+            } else {
+                "Decode Anyway".to_string()
+            }
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.pause_foreground = !data.settings.pause_foreground;
+            background::set_pause_foreground(data.settings.pause_foreground);
+        },
+    );
+
+    let thumbnail_fill_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.cover_thumbnails {
+                "Cover".to_string()
+            } else {
+                "Contain".to_string()
+            }
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.cover_thumbnails = !data.settings.cover_thumbnails;
+        },
+    );
+
+    let thumbnail_quality_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.linear_thumbnails {
+                "Accurate".to_string()
+            } else {
+                "Fast".to_string()
+            }
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.linear_thumbnails = !data.settings.linear_thumbnails;
+        },
+    );
+
+    let thumbnail_memory_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.compact_thumbnails {
+                "16-bit Color".to_string()
+            } else {
+                "Full Color".to_string()
+            }
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.compact_thumbnails =
+                !data.settings.compact_thumbnails;
+            memory_cache::set_compact_thumbnails(
+                data.settings.compact_thumbnails,
+            );
+        },
+    );
+
+    let format_badges_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.show_format_badges {
+                "Shown".to_string()
+            } else {
+                "Hidden".to_string()
+            }
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.show_format_badges =
+                !data.settings.show_format_badges;
+        },
+    );
+
+    let tile_dates_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.show_tile_dates {
+                "Shown".to_string()
+            } else {
+                "Hidden".to_string()
+            }
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.show_tile_dates = !data.settings.show_tile_dates;
+        },
+    );
+
+    let gallery_overscan_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            format!("{} rows", data.settings.gallery_overscan)
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.gallery_overscan =
+                data.settings.next_gallery_overscan();
+        },
+    );
+
+    let image_fit_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.adaptive_fit {
+                "By Orientation".to_string()
+            } else {
+                "Whole Image".to_string()
+            }
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.adaptive_fit = !data.settings.adaptive_fit;
+        },
+    );
+
+    let click_to_advance_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.click_to_advance {
+                "On".to_string()
+            } else {
+                "Off".to_string()
+            }
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.click_to_advance = !data.settings.click_to_advance;
+        },
+    );
+
+    let display_resampling_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.smooth_display {
+                "Quality".to_string()
+            } else {
+                "Speed".to_string()
+            }
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.smooth_display = !data.settings.smooth_display;
+        },
+    );
+
+    let smooth_zoom_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.smooth_zoom {
+                "Animated".to_string()
+            } else {
+                "Instant".to_string()
+            }
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.smooth_zoom = !data.settings.smooth_zoom;
+        },
+    );
+
+    let wheel_action_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            data.settings.wheel_action.label().to_string()
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.wheel_action = data.settings.wheel_action.next();
+        },
+    );
+
+    let fit_window_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.fit_window_to_image {
+                "On".to_string()
+            } else {
+                "Off".to_string()
+            }
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.fit_window_to_image =
+                !data.settings.fit_window_to_image;
+        },
+    );
+
+    let panoramas_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.scroll_panoramas {
+                "Scroll".to_string()
+            } else {
+                "Fit".to_string()
+            }
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.scroll_panoramas = !data.settings.scroll_panoramas;
+        },
+    );
+
+    let tall_images_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.scroll_tall_images {
+                "Scroll".to_string()
+            } else {
+                "Fit".to_string()
+            }
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.scroll_tall_images =
+                !data.settings.scroll_tall_images;
+        },
+    );
+
+    let transparency_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.checkerboard_background {
+                "Checkerboard".to_string()
+            } else {
+                "White".to_string()
+            }
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.checkerboard_background =
+                !data.settings.checkerboard_background;
+        },
+    );
+
+    let slideshow_transition_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            data.settings.slideshow_transition.label().to_string()
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.slideshow_transition =
+                data.settings.slideshow_transition.next();
+        },
+    );
+
+    let slideshow_sequences_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.slideshow_plays_sequences {
+                "Play Through".to_string()
+            } else {
+                "Frame by Frame".to_string()
+            }
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.slideshow_plays_sequences =
+                !data.settings.slideshow_plays_sequences;
+        },
+    );
+
+    let slideshow_prefetch_btn = text_button(
+        |data: &AppState, _env: &Env| match data.settings.slideshow_prefetch {
+            0 => "Off".to_string(),
+            1 => "1 image".to_string(),
+            count => format!("{} images", count),
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.slideshow_prefetch =
+                data.settings.next_slideshow_prefetch();
+        },
+    );
+
+    let flicker_interval_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            format!("{}s", data.settings.flicker_interval)
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.flicker_interval =
+                data.settings.next_flicker_interval();
+        },
+    );
+
+    let peek_key_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            format!("Hold {}", data.settings.peek_key.label())
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.peek_key = data.settings.peek_key.next();
+        },
+    );
+
+    let folder_keys_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            data.settings.folder_keys.label().to_string()
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.folder_keys = data.settings.folder_keys.next();
+        },
+    );
+
+    let playback_fps_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            format!("{} fps", data.settings.playback_fps)
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.playback_fps = data.settings.next_playback_fps();
+        },
+    );
+
+    let max_frame_rate_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            format!("{} fps", data.settings.max_frame_rate)
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.max_frame_rate = data.settings.next_max_frame_rate();
+        },
+    );
+
+    let back_button_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.back_to_library {
+                "Library".to_string()
+            } else {
+                "Gallery".to_string()
+            }
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.back_to_library = !data.settings.back_to_library;
+        },
+    );
+
+    let advance_on_rate_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.advance_on_rate {
+                "Next Image".to_string()
+            } else {
+                "Stay".to_string()
+            }
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.advance_on_rate = !data.settings.advance_on_rate;
+        },
+    );
+
+    let confirm_trash_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.confirm_trash {
+                "Ask First".to_string()
+            } else {
+                "Right Away".to_string()
+            }
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.confirm_trash = !data.settings.confirm_trash;
+        },
+    );
+
+    let folder_end_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            data.settings.folder_end_action.label().to_string()
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.folder_end_action =
+                data.settings.folder_end_action.next();
+        },
+    );
+
+    let status_bar_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.show_status_bar {
+                "Shown".to_string()
+            } else {
+                "Hidden".to_string()
+            }
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.show_status_bar = !data.settings.show_status_bar;
+        },
+    );
+
+    let caption_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.show_caption {
+                "Shown".to_string()
+            } else {
+                "Hidden".to_string()
+            }
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.show_caption = !data.settings.show_caption;
+        },
+    );
+
+    let caption_content_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            data.settings.caption_content.label().to_string()
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.caption_content =
+                data.settings.caption_content.next();
+        },
+    );
+
+    let letterbox_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            data.settings.letterbox_color.label().to_string()
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.letterbox_color =
+                data.settings.letterbox_color.next();
+        },
+    );
+
+    let image_frame_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            data.settings.image_frame.label().to_string()
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.image_frame = data.settings.image_frame.next();
+        },
+    );
+
+    let caption_position_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            data.settings.caption_position.label().to_string()
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.caption_position =
+                data.settings.caption_position.next();
+        },
+    );
+
+    let auto_rotate_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.auto_rotate {
+                "On".to_string()
+            } else {
+                "Off".to_string()
+            }
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.auto_rotate = !data.settings.auto_rotate;
+        },
+    );
+
+    let scan_timeout_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            format!("{}s", data.settings.scan_timeout)
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.scan_timeout = data.settings.next_scan_timeout();
+        },
+    );
+
+    let open_retries_btn = text_button(
+        |data: &AppState, _env: &Env| match data.settings.open_retries {
+            0 => "Off".to_string(),
+            1 => "1 retry".to_string(),
+            retries => format!("{} retries", retries),
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.open_retries = data.settings.next_open_retries();
+        },
+    );
+
+    let recent_days_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            format!("Last {} days", data.settings.recent_days)
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.recent_days = data.settings.next_recent_days();
+        },
+    );
+
+    let scan_summary_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            if data.settings.scan_summary {
+                "On".to_string()
+            } else {
+                "Off".to_string()
+            }
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.scan_summary = !data.settings.scan_summary;
+        },
+    );
+
+    let number_locale_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            data.settings.number_locale.label().to_string()
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.number_locale = data.settings.number_locale.next();
+        },
+    );
+
+    let startup_view_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            data.settings.startup_view.label().to_string()
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.startup_view = data.settings.startup_view.next();
+        },
+    );
+
+    // pins the folder that was added most recently
+    let pinned_folder_btn = text_button(
+        |data: &AppState, _env: &Env| match &data.settings.pinned_folder {
+            Some(folder) => folder
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| folder.display().to_string()),
+            None => "Pin Last Folder".to_string(),
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.pinned_folder = data.settings.last_folder.clone();
+        },
+    );
+
+    let favorites_folder_btn = text_button(
+        |data: &AppState, _env: &Env| match &data.settings.favorites_folder {
+            Some(folder) => folder
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| folder.display().to_string()),
+            None => "Choose Folder".to_string(),
+        },
+        |ctx, _data: &mut AppState, _env| {
+            let file_dialog = FileDialogOptions::new()
+                .select_directories()
+                .title("Choose Favorites Folder");
+            ctx.submit_command(SHOW_OPEN_PANEL.with(file_dialog));
+        },
+    );
+
+    let cache_budget_btn = text_button(
+        |data: &AppState, _env: &Env| {
+            const MB: u64 = 1024 * 1024;
+            let locale = data.settings.number_locale;
+            format!(
+                "{} / {}",
+                format::bytes(data.cache_usage as u64 * MB, locale),
+                format::bytes(
+                    data.settings.cache_budget_mb as u64 * MB,
+                    locale
+                )
+            )
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.cache_budget_mb = data.settings.next_cache_budget();
+            memory_cache::set_budget_mb(data.settings.cache_budget_mb);
+            render_cache::set_budget_mb(data.settings.cache_budget_mb);
+            data.cache_usage = memory_cache::usage_mb();
+        },
+    );
+
+    let render_cache_btn = text_button(
+        |data: &AppState, _env: &Env| match data
+            .settings
+            .render_cache_megapixels
+        {
+            0 => "Off".to_string(),
+            megapixels => format!("Images over {} MP", megapixels),
+        },
+        |_ctx, data: &mut AppState, _env| {
+            data.settings.render_cache_megapixels =
+                data.settings.next_render_cache_megapixels();
+            render_cache::set_min_megapixels(
+                data.settings.render_cache_megapixels,
+            );
+        },
+    );
+
+    let rows = Flex::column()
+        .with_child(setting_row("Combine Subfolders", combine_subfolders_btn))
+        .with_child(setting_row("Navigation", navigation_scope_btn))
+        .with_child(setting_row("Sort", sort_order_btn))
+        .with_child(setting_row("Gallery Layout", gallery_layout_btn))
+        .with_child(setting_row("Group by Date", date_grouping_btn))
+        .with_child(setting_row("Gallery Tiles", list_mode_btn))
+        .with_child(setting_row("Small Images", small_images_btn))
+        .with_child(setting_row("Small Image Limit", min_image_size_btn))
+        .with_child(setting_row("Skip Folders With", exclusion_marker_btn))
+        .with_child(setting_row(
+            "External Thumbnailer",
+            external_thumbnailer_btn,
+        ))
+        .with_child(setting_row("Thumbnails", thumbnail_fill_btn))
+        .with_child(setting_row("Thumbnail Quality", thumbnail_quality_btn))
+        .with_child(setting_row("Cached Thumbnails", thumbnail_memory_btn))
+        .with_child(setting_row("Format Badges", format_badges_btn))
+        .with_child(setting_row("Tile Dates", tile_dates_btn))
+        .with_child(setting_row("Overscan", gallery_overscan_btn))
+        .with_child(setting_row("Opening While Paused", paused_decodes_btn))
+        .with_child(setting_row("Image Fit", image_fit_btn))
+        .with_child(setting_row("Click to Advance", click_to_advance_btn))
+        .with_child(setting_row("Display Resampling", display_resampling_btn))
+        .with_child(setting_row("Zoom", smooth_zoom_btn))
+        .with_child(setting_row("Mouse Wheel", wheel_action_btn))
+        .with_child(setting_row("Fit Window to Image", fit_window_btn))
+        .with_child(setting_row("Panoramas", panoramas_btn))
+        .with_child(setting_row("Tall Images", tall_images_btn))
+        .with_child(setting_row("Transparency", transparency_btn))
+        .with_child(setting_row("Letterbox", letterbox_btn))
+        .with_child(setting_row("Image Frame", image_frame_btn))
+        .with_child(setting_row("Transition", slideshow_transition_btn))
+        .with_child(setting_row("Slideshow Sequences", slideshow_sequences_btn))
+        .with_child(setting_row("Slideshow Prefetch", slideshow_prefetch_btn))
+        .with_child(setting_row("Flicker", flicker_interval_btn))
+        .with_child(setting_row("Previous Image", peek_key_btn))
+        .with_child(setting_row("Playback Rate", playback_fps_btn))
+        .with_child(setting_row("Frame Rate Cap", max_frame_rate_btn))
+        .with_child(setting_row("At Folder End", folder_end_btn))
+        .with_child(setting_row("Back Button", back_button_btn))
+        .with_child(setting_row("After Rating", advance_on_rate_btn))
+        .with_child(setting_row("Move to Trash", confirm_trash_btn))
+        .with_child(setting_row("Next Folder", folder_keys_btn))
+        .with_child(setting_row("Status Bar", status_bar_btn))
+        .with_child(setting_row("Caption", caption_btn))
+        .with_child(setting_row("Caption Shows", caption_content_btn))
+        .with_child(setting_row("Caption Corner", caption_position_btn))
+        .with_child(setting_row("EXIF Auto-Rotate", auto_rotate_btn))
+        .with_child(setting_row("File Timeout", scan_timeout_btn))
+        .with_child(setting_row("Retry Opening", open_retries_btn))
+        .with_child(setting_row("Scan Summary", scan_summary_btn))
+        .with_child(setting_row("Recent Filter", recent_days_btn))
+        .with_child(setting_row("Number Format", number_locale_btn))
+        .with_child(setting_row("On Startup", startup_view_btn))
+        .with_child(setting_row("Pinned Folder", pinned_folder_btn))
+        .with_child(setting_row("Favorites Folder", favorites_folder_btn))
+        .with_child(setting_row("Memory Cache", cache_budget_btn))
+        .with_child(setting_row("Display Cache", render_cache_btn))
+        .with_child(root_folders())
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .padding(20.);
+    // there are more settings than fit in the window
+    Scroll::new(rows)
+        .vertical()
+        .expand()
+        .background(Color::WHITE)
+        .controller(PreferencesController)
+}
+
+fn setting_row(
+    name: &str,
+    button: impl Widget<AppState> + 'static,
+) -> impl Widget<AppState> {
+    Flex::row()
+        .with_child(
+            Label::new(name)
+                .with_text_color(Color::BLACK)
+                .with_text_size(16.)
+                .fix_width(180.),
+        )
+        .with_child(button.fix_height(40.))
+        .padding((0., 4.))
+}
+
+// a root folder and its position among the roots
+type RootRow = (Arc<PathBuf>, usize);
+
+// only shown once there's more than one root to put in order
+fn root_folders() -> impl Widget<AppState> {
+    let rows = List::new(|| {
+        Flex::row()
+            .with_child(
+                Label::dynamic(|(root, _): &RootRow, _env| {
+                    root.display().to_string()
+                })
+                .with_text_color(Color::BLACK)
+                .with_text_size(14.)
+                .fix_width(300.),
+            )
+            .with_child(root_button("↑", true))
+            .with_spacer(4.)
+            .with_child(root_button("↓", false))
+            .padding((0., 2.))
+    })
+    .lens(lens::Map::new(
+        |data: &AppState| {
+            data.root_folders
+                .iter()
+                .cloned()
+                .enumerate()
+                .map(|(idx, root)| (root, idx))
+                .collect::<Vector<RootRow>>()
+        },
+        |_data, _rows| (),
+    ));
+    let section = Flex::column()
+        .with_child(
+            Label::new("Root Folders")
+                .with_text_color(Color::BLACK)
+                .with_text_size(16.)
+                .padding((0., 8.)),
+        )
+        .with_child(rows)
+        .cross_axis_alignment(CrossAxisAlignment::Start);
+    Either::new(
+        |data: &AppState, _env| data.root_folders.len() > 1,
+        section,
+        SizedBox::empty(),
+    )
+}
+
+fn root_button(text: &str, up: bool) -> impl Widget<RootRow> {
+    text_button(text, move |ctx, (_, idx): &mut RootRow, _env| {
+        ctx.submit_command(MOVE_ROOT_FOLDER.with((*idx, up)));
+    })
+    .fix_size(36., 32.)
+}
+
+// saves the settings once the preferences window is closed
+struct PreferencesController;
+
+impl<W: Widget<AppState>> Controller<AppState, W> for PreferencesController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        match event {
+            Event::WindowDisconnected => data.settings.save(),
+            // the only folder chosen from the preferences is the favorites
+            // folder
+            Event::Command(cmd) if cmd.is(OPEN_FILE) => {
+                let file_info = cmd.get_unchecked(OPEN_FILE);
+                data.settings.favorites_folder =
+                    Some(Arc::new(file_info.path().to_path_buf()));
+                return;
+            }
+            Event::Command(cmd) if cmd.is(MOVE_ROOT_FOLDER) => {
+                let (idx, up) = *cmd.get_unchecked(MOVE_ROOT_FOLDER);
+                move_root_folder(data, idx, up);
+                return;
+            }
+            _ => (),
+        }
+        child.event(ctx, event, data, env)
+    }
+}
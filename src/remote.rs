@@ -0,0 +1,183 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    env::temp_dir,
+    fs,
+    hash::{Hash, Hasher},
+    io::Read,
+    path::{Path, PathBuf},
+    process,
+    time::Duration,
+};
+
+use image::ImageFormat;
+use log::error;
+
+// how long a download waits on the server before giving up
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+// anything bigger is refused instead of filling up the memory
+const MAX_DOWNLOAD_BYTES: u64 = 100 * 1024 * 1024;
+// downloads left behind by an instance that didn't quit cleanly are removed
+// once they're this old
+const STALE_DOWNLOADS: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+// every running instance keeps its downloads in a folder of its own, so one
+// that starts or quits doesn't remove images another one is showing
+fn downloads_root() -> PathBuf {
+    temp_dir().join("image-viewer-downloads")
+}
+
+// downloaded images are kept here until the app quits, each in a folder of
+// its own so its gallery only has that image
+fn downloads_dir() -> PathBuf {
+    downloads_root().join(process::id().to_string())
+}
+
+pub fn is_url(path: &str) -> bool {
+    let path = path.to_ascii_lowercase();
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
+// a downloaded image isn't one of the user's files, so it can't be trashed
+pub fn is_download(path: &Path) -> bool {
+    path.starts_with(downloads_root())
+}
+
+// removes what this instance downloaded, or an earlier one that had the
+// same process id, along with what other instances left behind long ago
+pub fn clear_downloads() {
+    remove_downloads(&downloads_dir());
+    let entries = match fs::read_dir(downloads_root()) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let stale = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .map_or(false, |age| age >= STALE_DOWNLOADS);
+        if stale {
+            remove_downloads(&entry.path());
+        }
+    }
+}
+
+fn remove_downloads(downloads: &Path) {
+    if !downloads.exists() {
+        return;
+    }
+    if let Err(err) = fs::remove_dir_all(downloads) {
+        error!("Error removing {}: {}", downloads.display(), err);
+    }
+}
+
+// downloads the image and saves it so it can be opened like any other file,
+// returning where it was saved. Only images the viewer shows are kept
+pub fn download_image(url: &str) -> Result<PathBuf, String> {
+    let response = ureq::get(url)
+        .timeout(DOWNLOAD_TIMEOUT)
+        .call()
+        .map_err(|err| err.to_string())?;
+    let mut bytes = Vec::new();
+    // a byte past the limit tells a download that's too big from one that's
+    // exactly at it
+    response
+        .into_reader()
+        .take(MAX_DOWNLOAD_BYTES + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|err| err.to_string())?;
+    if bytes.len() as u64 > MAX_DOWNLOAD_BYTES {
+        return Err(format!(
+            "bigger than {} MB",
+            MAX_DOWNLOAD_BYTES / (1024 * 1024)
+        ));
+    }
+    let extension = match image::guess_format(&bytes) {
+        Ok(ImageFormat::Png) => "png",
+        Ok(ImageFormat::Jpeg) => "jpg",
+        _ => return Err("not a PNG or JPEG image".to_string()),
+    };
+    let mut hasher = DefaultHasher::new();
+    url.hash(&mut hasher);
+    let folder = downloads_dir().join(format!("{:016x}", hasher.finish()));
+    fs::create_dir_all(&folder).map_err(|err| err.to_string())?;
+    let path = folder.join(file_name(url, extension));
+    fs::write(&path, &bytes).map_err(|err| err.to_string())?;
+    Ok(path)
+}
+
+// the last part of the url's path without its query, with an extension
+// that matches what was downloaded
+fn file_name(url: &str, extension: &str) -> String {
+    let path = url.split(|c| c == '?' || c == '#').next().unwrap_or(url);
+    let name = path.trim_end_matches('/').rsplit('/').next().unwrap_or("");
+    let stem: String = Path::new(name)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("")
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
+        .collect();
+    if stem.is_empty() {
+        format!("image.{}", extension)
+    } else {
+        format!("{}.{}", stem, extension)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_downloads_after_the_url() {
+        assert_eq!(
+            file_name("https://example.com/photos/cat.jpeg", "jpg"),
+            "cat.jpg"
+        );
+        assert_eq!(
+            file_name("https://example.com/photos/", "png"),
+            "photos.png"
+        );
+        assert_eq!(file_name("https://example.com/raw", "png"), "raw.png");
+    }
+
+    #[test]
+    fn leaves_out_the_query_and_fragment() {
+        assert_eq!(
+            file_name("https://example.com/cat.jpg?size=large&v=2", "jpg"),
+            "cat.jpg"
+        );
+        assert_eq!(
+            file_name("https://example.com/dog.png#top", "png"),
+            "dog.png"
+        );
+        assert_eq!(
+            file_name("https://example.com/a/b.png?next=/c/d.jpg", "png"),
+            "b.png"
+        );
+    }
+
+    #[test]
+    fn keeps_only_safe_characters() {
+        assert_eq!(
+            file_name("https://example.com/summer%20trip.jpg", "jpg"),
+            "summer20trip.jpg"
+        );
+        assert_eq!(
+            file_name("https://example.com/my_photo-1.png", "png"),
+            "my_photo-1.png"
+        );
+        assert_eq!(
+            file_name("https://example.com/%25%25.png", "png"),
+            "2525.png"
+        );
+    }
+
+    #[test]
+    fn falls_back_to_a_generic_name() {
+        assert_eq!(file_name("https://example.com/%.jpg", "jpg"), "image.jpg");
+        assert_eq!(file_name("", "png"), "image.png");
+    }
+}
@@ -0,0 +1,176 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    time::SystemTime,
+};
+
+use image::RgbImage;
+use log::error;
+
+use crate::{decode, thumbnail_cache};
+
+const BYTES_PER_MB: u64 = 1024 * 1024;
+
+// images with fewer pixels than this decode quickly enough as they are, 0
+// turns the cache off
+static MIN_PIXELS: AtomicU64 = AtomicU64::new(0);
+// renders are removed oldest first once together they take up more than
+// this on disk
+static BUDGET: AtomicU64 = AtomicU64::new(0);
+
+// downscaled copies of huge images at the size they're displayed at, so
+// viewing them again doesn't decode the whole file
+fn render_dir() -> PathBuf {
+    thumbnail_cache::cache_dir().join("renders")
+}
+
+pub fn set_min_megapixels(megapixels: u32) {
+    MIN_PIXELS.store(megapixels as u64 * 1_000_000, Ordering::SeqCst);
+}
+
+pub fn set_budget_mb(budget: usize) {
+    BUDGET.store(budget as u64 * BYTES_PER_MB, Ordering::SeqCst);
+}
+
+// whether a render should be kept of this decoded image
+pub fn is_huge(image: &RgbImage) -> bool {
+    let min_pixels = MIN_PIXELS.load(Ordering::SeqCst);
+    let (width, height) = image.dimensions();
+    min_pixels > 0 && width as u64 * height as u64 >= min_pixels
+}
+
+// renders are keyed by the image path, its modification time and the size
+// they were made for
+fn render_path(path: &Path, target: (u32, u32)) -> Option<PathBuf> {
+    if target.0 == 0 || target.1 == 0 {
+        return None;
+    }
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    modified.hash(&mut hasher);
+    target.hash(&mut hasher);
+    Some(render_dir().join(format!("{:016x}.png", hasher.finish())))
+}
+
+// the size of the image a render was made from is kept next to it, so the
+// image view can show the original dimensions
+fn size_path(render_path: &Path) -> PathBuf {
+    render_path.with_extension("size")
+}
+
+fn read_size(render_path: &Path) -> Option<(u32, u32)> {
+    let size = fs::read_to_string(size_path(render_path)).ok()?;
+    let mut parts =
+        size.split_whitespace().map(|part| part.parse::<u32>().ok());
+    match (parts.next()??, parts.next()??) {
+        (0, _) | (_, 0) => None,
+        size => Some(size),
+    }
+}
+
+// the render and the size of the image as stored, before it was downscaled
+pub fn load(path: &Path, target: (u32, u32)) -> Option<(RgbImage, (u32, u32))> {
+    if MIN_PIXELS.load(Ordering::SeqCst) == 0 {
+        return None;
+    }
+    let render_path = render_path(path, target)?;
+    if !render_path.exists() {
+        return None;
+    }
+    // renders made before the size was kept are made again
+    let size = read_size(&render_path)?;
+    match image::open(&render_path) {
+        Ok(image) => Some((image.to_rgb8(), size)),
+        Err(err) => {
+            error!(
+                "Error reading cached render {}: {}",
+                render_path.display(),
+                err
+            );
+            None
+        }
+    }
+}
+
+// keeps the image downscaled to fit in the target size, then removes the
+// oldest renders until they fit in the budget again. The caller holds a
+// WriteJob for as long as this runs
+pub fn store(path: &Path, image: &RgbImage, target: (u32, u32)) {
+    let render_path = match render_path(path, target) {
+        Some(render_path) => render_path,
+        None => return,
+    };
+    let (width, height) = image.dimensions();
+    let scale = (target.0 as f64 / width as f64)
+        .min(target.1 as f64 / height as f64)
+        .min(1.);
+    let render = decode::downscale(
+        image,
+        ((width as f64 * scale).round() as u32).max(1),
+        ((height as f64 * scale).round() as u32).max(1),
+        false,
+    );
+    if let Err(err) = fs::create_dir_all(render_dir()) {
+        error!("Error creating render cache directory: {}", err);
+        return;
+    }
+    if let Err(err) = render.save(&render_path) {
+        error!(
+            "Error writing cached render {}: {}",
+            render_path.display(),
+            err
+        );
+        return;
+    }
+    let size = format!("{} {}", width, height);
+    if let Err(err) = fs::write(size_path(&render_path), size) {
+        error!(
+            "Error writing cached render {}: {}",
+            render_path.display(),
+            err
+        );
+        let _ = fs::remove_file(&render_path);
+        return;
+    }
+    trim();
+}
+
+fn trim() {
+    let entries = match fs::read_dir(render_dir()) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    let mut renders: Vec<(SystemTime, u64, PathBuf)> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            if entry.path().extension()? != "png" {
+                return None;
+            }
+            let metadata = entry.metadata().ok()?;
+            Some((metadata.modified().ok()?, metadata.len(), entry.path()))
+        })
+        .collect();
+    renders.sort();
+    let budget = BUDGET.load(Ordering::SeqCst);
+    let mut usage: u64 = renders.iter().map(|(_, bytes, _)| bytes).sum();
+    for (_, bytes, path) in renders {
+        if usage <= budget {
+            break;
+        }
+        match fs::remove_file(&path) {
+            Ok(()) => {
+                usage -= bytes;
+                let _ = fs::remove_file(size_path(&path));
+            }
+            Err(err) => error!(
+                "Error removing cached render {}: {}",
+                path.display(),
+                err
+            ),
+        }
+    }
+}
@@ -0,0 +1,1348 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+
+use druid::{
+    im::Vector, widget::FillStrat, ArcStr, Color, Data, Env, KbKey, Key,
+    KeyEvent, Lens,
+};
+use log::error;
+
+pub const SHOW_FORMAT_BADGES: Key<bool> =
+    Key::new("image-viewer.show-format-badges");
+pub const SHOW_TILE_DATES: Key<bool> = Key::new("image-viewer.show-tile-dates");
+pub const COVER_THUMBNAILS: Key<bool> =
+    Key::new("image-viewer.cover-thumbnails");
+// the label of the number locale
+pub const NUMBER_LOCALE: Key<ArcStr> = Key::new("image-viewer.number-locale");
+
+// the budgets the cache budget setting cycles through
+pub const CACHE_BUDGETS_MB: [usize; 5] = [128, 256, 512, 1024, 2048];
+// the overscan setting cycles through these row counts
+pub const GALLERY_OVERSCANS: [usize; 5] = [0, 1, 2, 4, 8];
+// the flicker rate setting cycles through these intervals in seconds
+pub const FLICKER_INTERVALS: [f64; 4] = [0.1, 0.25, 0.5, 1.];
+// the scan timeout setting cycles through these durations in seconds
+pub const SCAN_TIMEOUTS: [f64; 5] = [1., 2., 5., 10., 30.];
+// the shortest and longest durations in seconds the settings file can set
+const SLIDESHOW_INTERVAL_RANGE: (f64, f64) = (0.5, 3600.);
+const FLICKER_INTERVAL_RANGE: (f64, f64) = (0.05, 10.);
+const SCAN_TIMEOUT_RANGE: (f64, f64) = (0.1, 600.);
+// the open retries setting cycles through these counts
+pub const OPEN_RETRIES: [u32; 4] = [0, 1, 2, 4];
+// the recent filter setting cycles through these numbers of days
+pub const RECENT_DAYS: [u32; 5] = [1, 3, 7, 14, 30];
+// the slideshow prefetch setting cycles through these numbers of images
+pub const SLIDESHOW_PREFETCHES: [usize; 5] = [0, 1, 2, 3, 5];
+// the small image limit cycles through these sizes in pixels
+pub const MIN_IMAGE_SIZES: [u32; 4] = [32, 64, 128, 256];
+// the exclusion marker setting cycles through these file names, the empty
+// one turns it off
+pub const EXCLUSION_MARKERS: [&str; 3] = [".nomedia", ".noimages", ""];
+// the external thumbnailer setting cycles through these commands, the empty
+// one turns it off. {input} is the image's path and {output} the PNG the
+// command should write
+pub const EXTERNAL_THUMBNAILERS: [&str; 3] = [
+    "",
+    "ffmpeg -loglevel error -y -i {input} -frames:v 1 {output}",
+    "vipsthumbnail {input} --size 1600 -o {output}",
+];
+// the playback rate setting cycles through these frames per second
+pub const PLAYBACK_RATES: [u32; 4] = [12, 24, 30, 60];
+// the frame rate cap setting cycles through these frames per second
+pub const FRAME_RATE_CAPS: [u32; 4] = [24, 30, 50, 60];
+// the display cache setting cycles through these sizes in megapixels, 0
+// turns it off
+pub const RENDER_CACHE_THRESHOLDS: [u32; 4] = [0, 24, 50, 100];
+
+// how the slideshow moves from one image to the next
+#[derive(Debug, Clone, Copy, Data, PartialEq, Eq)]
+pub enum SlideshowTransition {
+    None,
+    Fade,
+    SlideLeft,
+}
+
+impl SlideshowTransition {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SlideshowTransition::None => "None",
+            SlideshowTransition::Fade => "Fade",
+            SlideshowTransition::SlideLeft => "Slide",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            SlideshowTransition::None => SlideshowTransition::Fade,
+            SlideshowTransition::Fade => SlideshowTransition::SlideLeft,
+            SlideshowTransition::SlideLeft => SlideshowTransition::None,
+        }
+    }
+
+    pub fn parse(label: &str) -> Option<Self> {
+        match label {
+            "None" => Some(SlideshowTransition::None),
+            "Fade" => Some(SlideshowTransition::Fade),
+            "Slide" => Some(SlideshowTransition::SlideLeft),
+            _ => None,
+        }
+    }
+}
+
+// order of the images inside a folder
+#[derive(Debug, Clone, Copy, Data, PartialEq, Eq)]
+pub enum SortOrder {
+    Name,
+    // interleaves images from every subfolder by when they were taken
+    CaptureTime,
+}
+
+impl SortOrder {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SortOrder::Name => "Name",
+            SortOrder::CaptureTime => "Capture Time",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            SortOrder::Name => SortOrder::CaptureTime,
+            SortOrder::CaptureTime => SortOrder::Name,
+        }
+    }
+
+    pub fn parse(label: &str) -> Option<Self> {
+        match label {
+            "Name" => Some(SortOrder::Name),
+            "Capture Time" => Some(SortOrder::CaptureTime),
+            _ => None,
+        }
+    }
+}
+
+// order of the folders in the main view
+#[derive(Debug, Clone, Copy, Data, PartialEq, Eq)]
+pub enum FolderSort {
+    // the folders of each root together, in the order of the roots
+    Library,
+    ImageCount,
+    TotalSize,
+}
+
+impl FolderSort {
+    pub fn label(&self) -> &'static str {
+        match self {
+            FolderSort::Library => "Library",
+            FolderSort::ImageCount => "Image Count",
+            FolderSort::TotalSize => "Total Size",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            FolderSort::Library => FolderSort::ImageCount,
+            FolderSort::ImageCount => FolderSort::TotalSize,
+            FolderSort::TotalSize => FolderSort::Library,
+        }
+    }
+
+    pub fn parse(label: &str) -> Option<Self> {
+        match label {
+            "Library" => Some(FolderSort::Library),
+            "Image Count" => Some(FolderSort::ImageCount),
+            "Total Size" => Some(FolderSort::TotalSize),
+            _ => None,
+        }
+    }
+}
+
+// what moving past the last image of a folder does
+#[derive(Debug, Clone, Copy, Data, PartialEq, Eq)]
+pub enum FolderEndAction {
+    Stop,
+    Wrap,
+    // opens the first image of the next folder in the library
+    NextFolder,
+}
+
+impl FolderEndAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            FolderEndAction::Stop => "Stop",
+            FolderEndAction::Wrap => "Wrap",
+            FolderEndAction::NextFolder => "Next Folder",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            FolderEndAction::Stop => FolderEndAction::Wrap,
+            FolderEndAction::Wrap => FolderEndAction::NextFolder,
+            FolderEndAction::NextFolder => FolderEndAction::Stop,
+        }
+    }
+
+    pub fn parse(label: &str) -> Option<Self> {
+        match label {
+            "Stop" => Some(FolderEndAction::Stop),
+            "Wrap" => Some(FolderEndAction::Wrap),
+            "Next Folder" => Some(FolderEndAction::NextFolder),
+            _ => None,
+        }
+    }
+}
+
+// what turning the mouse wheel over the image view does
+#[derive(Debug, Clone, Copy, Data, PartialEq, Eq)]
+pub enum WheelAction {
+    Zoom,
+    // moves on to the next image or back to the previous one
+    Navigate,
+    // pans a zoomed image
+    Scroll,
+}
+
+impl WheelAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            WheelAction::Zoom => "Zoom",
+            WheelAction::Navigate => "Navigate",
+            WheelAction::Scroll => "Scroll",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            WheelAction::Zoom => WheelAction::Navigate,
+            WheelAction::Navigate => WheelAction::Scroll,
+            WheelAction::Scroll => WheelAction::Zoom,
+        }
+    }
+
+    pub fn parse(label: &str) -> Option<Self> {
+        match label {
+            "Zoom" => Some(WheelAction::Zoom),
+            "Navigate" => Some(WheelAction::Navigate),
+            "Scroll" => Some(WheelAction::Scroll),
+            _ => None,
+        }
+    }
+}
+
+// how numbers and dates are written
+#[derive(Debug, Clone, Copy, Data, PartialEq, Eq)]
+pub enum NumberLocale {
+    // follows the locale the environment is set to
+    System,
+    UnitedStates,
+    German,
+    French,
+}
+
+impl NumberLocale {
+    pub fn label(&self) -> &'static str {
+        match self {
+            NumberLocale::System => "System",
+            NumberLocale::UnitedStates => "US",
+            NumberLocale::German => "German",
+            NumberLocale::French => "French",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            NumberLocale::System => NumberLocale::UnitedStates,
+            NumberLocale::UnitedStates => NumberLocale::German,
+            NumberLocale::German => NumberLocale::French,
+            NumberLocale::French => NumberLocale::System,
+        }
+    }
+
+    pub fn parse(label: &str) -> Option<Self> {
+        match label {
+            "System" => Some(NumberLocale::System),
+            "US" => Some(NumberLocale::UnitedStates),
+            "German" => Some(NumberLocale::German),
+            "French" => Some(NumberLocale::French),
+            _ => None,
+        }
+    }
+
+    // the locale System stands for, read from the usual environment
+    // variables and falling back to the US
+    pub fn resolve(&self) -> Self {
+        if *self != NumberLocale::System {
+            return *self;
+        }
+        let locale = ["LC_ALL", "LC_NUMERIC", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        if locale.starts_with("de") {
+            NumberLocale::German
+        } else if locale.starts_with("fr") {
+            NumberLocale::French
+        } else {
+            NumberLocale::UnitedStates
+        }
+    }
+
+    pub fn thousands_separator(&self) -> char {
+        match self.resolve() {
+            NumberLocale::German => '.',
+            // a narrow no-break space
+            NumberLocale::French => '\u{202f}',
+            _ => ',',
+        }
+    }
+
+    pub fn decimal_separator(&self) -> char {
+        match self.resolve() {
+            NumberLocale::German | NumberLocale::French => ',',
+            _ => '.',
+        }
+    }
+}
+
+// what the main view opens with when the app starts
+#[derive(Debug, Clone, Copy, Data, PartialEq, Eq)]
+pub enum StartupView {
+    // the folder that was added most recently
+    LastFolder,
+    PinnedFolder,
+    // asks for a folder before showing anything
+    ChooseFolder,
+}
+
+impl StartupView {
+    pub fn label(&self) -> &'static str {
+        match self {
+            StartupView::LastFolder => "Last Folder",
+            StartupView::PinnedFolder => "Pinned Folder",
+            StartupView::ChooseFolder => "Choose Folder",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            StartupView::LastFolder => StartupView::PinnedFolder,
+            StartupView::PinnedFolder => StartupView::ChooseFolder,
+            StartupView::ChooseFolder => StartupView::LastFolder,
+        }
+    }
+
+    pub fn parse(label: &str) -> Option<Self> {
+        match label {
+            "Last Folder" => Some(StartupView::LastFolder),
+            "Pinned Folder" => Some(StartupView::PinnedFolder),
+            "Choose Folder" => Some(StartupView::ChooseFolder),
+            _ => None,
+        }
+    }
+}
+
+// what the caption over the image shows
+#[derive(Debug, Clone, Copy, Data, PartialEq, Eq)]
+pub enum CaptionContent {
+    // the camera and lens over the exposure
+    Full,
+    Camera,
+    Exposure,
+    // the caption the user wrote for the image
+    Description,
+}
+
+impl CaptionContent {
+    pub fn label(&self) -> &'static str {
+        match self {
+            CaptionContent::Full => "Camera and Exposure",
+            CaptionContent::Camera => "Camera",
+            CaptionContent::Exposure => "Exposure",
+            CaptionContent::Description => "Description",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            CaptionContent::Full => CaptionContent::Camera,
+            CaptionContent::Camera => CaptionContent::Exposure,
+            CaptionContent::Exposure => CaptionContent::Description,
+            CaptionContent::Description => CaptionContent::Full,
+        }
+    }
+
+    pub fn parse(label: &str) -> Option<Self> {
+        match label {
+            "Camera and Exposure" => Some(CaptionContent::Full),
+            "Camera" => Some(CaptionContent::Camera),
+            "Exposure" => Some(CaptionContent::Exposure),
+            "Description" => Some(CaptionContent::Description),
+            _ => None,
+        }
+    }
+}
+
+// the corner of the image the metadata caption sits in
+#[derive(Debug, Clone, Copy, Data, PartialEq, Eq)]
+pub enum CaptionPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl CaptionPosition {
+    pub fn label(&self) -> &'static str {
+        match self {
+            CaptionPosition::TopLeft => "Top Left",
+            CaptionPosition::TopRight => "Top Right",
+            CaptionPosition::BottomLeft => "Bottom Left",
+            CaptionPosition::BottomRight => "Bottom Right",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            CaptionPosition::TopLeft => CaptionPosition::TopRight,
+            CaptionPosition::TopRight => CaptionPosition::BottomRight,
+            CaptionPosition::BottomRight => CaptionPosition::BottomLeft,
+            CaptionPosition::BottomLeft => CaptionPosition::TopLeft,
+        }
+    }
+
+    pub fn parse(label: &str) -> Option<Self> {
+        match label {
+            "Top Left" => Some(CaptionPosition::TopLeft),
+            "Top Right" => Some(CaptionPosition::TopRight),
+            "Bottom Left" => Some(CaptionPosition::BottomLeft),
+            "Bottom Right" => Some(CaptionPosition::BottomRight),
+            _ => None,
+        }
+    }
+}
+
+// the color around the shown image where it doesn't fill the view, apart
+// from the white of the rest of the window
+#[derive(Debug, Clone, Copy, Data, PartialEq, Eq)]
+pub enum LetterboxColor {
+    White,
+    LightGray,
+    MediumGray,
+    Black,
+}
+
+impl LetterboxColor {
+    pub fn label(&self) -> &'static str {
+        match self {
+            LetterboxColor::White => "White",
+            LetterboxColor::LightGray => "Light Gray",
+            LetterboxColor::MediumGray => "Medium Gray",
+            LetterboxColor::Black => "Black",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            LetterboxColor::White => LetterboxColor::LightGray,
+            LetterboxColor::LightGray => LetterboxColor::MediumGray,
+            LetterboxColor::MediumGray => LetterboxColor::Black,
+            LetterboxColor::Black => LetterboxColor::White,
+        }
+    }
+
+    pub fn parse(label: &str) -> Option<Self> {
+        match label {
+            "White" => Some(LetterboxColor::White),
+            "Light Gray" => Some(LetterboxColor::LightGray),
+            "Medium Gray" => Some(LetterboxColor::MediumGray),
+            "Black" => Some(LetterboxColor::Black),
+            _ => None,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            LetterboxColor::White => Color::WHITE,
+            LetterboxColor::LightGray => Color::rgb8(0xd8, 0xd8, 0xd8),
+            LetterboxColor::MediumGray => Color::rgb8(0x80, 0x80, 0x80),
+            LetterboxColor::Black => Color::BLACK,
+        }
+    }
+}
+
+// what's drawn around the shown image to set it apart from the background
+#[derive(Debug, Clone, Copy, Data, PartialEq, Eq)]
+pub enum ImageFrame {
+    None,
+    LightBorder,
+    DarkBorder,
+    Shadow,
+}
+
+impl ImageFrame {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ImageFrame::None => "None",
+            ImageFrame::LightBorder => "Light Border",
+            ImageFrame::DarkBorder => "Dark Border",
+            ImageFrame::Shadow => "Shadow",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            ImageFrame::None => ImageFrame::LightBorder,
+            ImageFrame::LightBorder => ImageFrame::DarkBorder,
+            ImageFrame::DarkBorder => ImageFrame::Shadow,
+            ImageFrame::Shadow => ImageFrame::None,
+        }
+    }
+
+    pub fn parse(label: &str) -> Option<Self> {
+        match label {
+            "None" => Some(ImageFrame::None),
+            "Light Border" => Some(ImageFrame::LightBorder),
+            "Dark Border" => Some(ImageFrame::DarkBorder),
+            "Shadow" => Some(ImageFrame::Shadow),
+            _ => None,
+        }
+    }
+}
+
+// how the gallery splits into sections by the date the images were taken
+#[derive(Debug, Clone, Copy, Data, PartialEq, Eq)]
+pub enum DateGrouping {
+    Off,
+    Day,
+    Month,
+    Year,
+}
+
+impl DateGrouping {
+    pub fn label(&self) -> &'static str {
+        match self {
+            DateGrouping::Off => "Off",
+            DateGrouping::Day => "Day",
+            DateGrouping::Month => "Month",
+            DateGrouping::Year => "Year",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            DateGrouping::Off => DateGrouping::Day,
+            DateGrouping::Day => DateGrouping::Month,
+            DateGrouping::Month => DateGrouping::Year,
+            DateGrouping::Year => DateGrouping::Off,
+        }
+    }
+
+    pub fn parse(label: &str) -> Option<Self> {
+        match label {
+            "Off" => Some(DateGrouping::Off),
+            "Day" => Some(DateGrouping::Day),
+            "Month" => Some(DateGrouping::Month),
+            "Year" => Some(DateGrouping::Year),
+            _ => None,
+        }
+    }
+}
+
+// the key held down to show the previously viewed image
+#[derive(Debug, Clone, Copy, Data, PartialEq, Eq)]
+pub enum PeekKey {
+    Q,
+    Backquote,
+    Backslash,
+}
+
+impl PeekKey {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PeekKey::Q => "Q",
+            PeekKey::Backquote => "`",
+            PeekKey::Backslash => "\\",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            PeekKey::Q => PeekKey::Backquote,
+            PeekKey::Backquote => PeekKey::Backslash,
+            PeekKey::Backslash => PeekKey::Q,
+        }
+    }
+
+    pub fn parse(label: &str) -> Option<Self> {
+        match label {
+            "Q" => Some(PeekKey::Q),
+            "`" => Some(PeekKey::Backquote),
+            "\\" => Some(PeekKey::Backslash),
+            _ => None,
+        }
+    }
+
+    pub fn matches(&self, key: &str) -> bool {
+        key.eq_ignore_ascii_case(self.label())
+    }
+}
+
+// the keys that move on to the next or previous folder from a gallery
+#[derive(Debug, Clone, Copy, Data, PartialEq, Eq)]
+pub enum FolderKeys {
+    Brackets,
+    CtrlPage,
+    CtrlArrows,
+}
+
+impl FolderKeys {
+    pub fn label(&self) -> &'static str {
+        match self {
+            FolderKeys::Brackets => "[ and ]",
+            FolderKeys::CtrlPage => "Ctrl+Page Up/Down",
+            FolderKeys::CtrlArrows => "Ctrl+Left/Right",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            FolderKeys::Brackets => FolderKeys::CtrlPage,
+            FolderKeys::CtrlPage => FolderKeys::CtrlArrows,
+            FolderKeys::CtrlArrows => FolderKeys::Brackets,
+        }
+    }
+
+    pub fn parse(label: &str) -> Option<Self> {
+        match label {
+            "[ and ]" => Some(FolderKeys::Brackets),
+            "Ctrl+Page Up/Down" => Some(FolderKeys::CtrlPage),
+            "Ctrl+Left/Right" => Some(FolderKeys::CtrlArrows),
+            _ => None,
+        }
+    }
+
+    // true for the key that goes to the next folder, false for the one going
+    // back, None for any other key
+    pub fn direction(&self, event: &KeyEvent) -> Option<bool> {
+        let ctrl = event.mods.ctrl();
+        match (self, &event.key, ctrl) {
+            (FolderKeys::Brackets, KbKey::Character(key), false) => {
+                match key.as_str() {
+                    "]" => Some(true),
+                    "[" => Some(false),
+                    _ => None,
+                }
+            }
+            (FolderKeys::CtrlPage, KbKey::PageDown, true)
+            | (FolderKeys::CtrlArrows, KbKey::ArrowRight, true) => Some(true),
+            (FolderKeys::CtrlPage, KbKey::PageUp, true)
+            | (FolderKeys::CtrlArrows, KbKey::ArrowLeft, true) => Some(false),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Data, Lens, Debug)]
+pub struct Settings {
+    // zoom level, relative to the image's actual size, that double clicking
+    // the image jumps to
+    pub double_click_zoom: f64,
+    // fits portrait images to the height of the image view and landscape
+    // ones to its width, rather than fitting every image whole
+    pub adaptive_fit: bool,
+    // clicking the right half of the image goes to the next one and the left
+    // half to the previous one, while the image isn't zoomed in
+    pub click_to_advance: bool,
+    // tells how many images were found once a scan finishes
+    pub scan_summary: bool,
+    // smooths the shown image when it's scaled down or up a little, it's
+    // drawn with nearest neighbor otherwise, which is faster
+    pub smooth_display: bool,
+    // zooming eases into the new zoom instead of jumping to it
+    pub smooth_zoom: bool,
+    // what the wheel does over the image view without a modifier held
+    pub wheel_action: WheelAction,
+    // resizes the window to the shape of each image that is shown
+    pub fit_window_to_image: bool,
+    // the image view's back button goes straight to the library instead of
+    // the folder's gallery
+    pub back_to_library: bool,
+    // rating an image with the number keys moves on to the next one, for
+    // going through a folder without leaving the number row
+    pub advance_on_rate: bool,
+    // the delete key asks before moving the image to the trash
+    pub confirm_trash: bool,
+    // opens very wide images fit to the height of the view so they can be
+    // scrolled through sideways, instead of fitting them whole
+    pub scroll_panoramas: bool,
+    // opens very tall images fit to the width of the view so they can be
+    // read by scrolling down through them
+    pub scroll_tall_images: bool,
+    // shows a checkerboard behind images instead of white, so their
+    // transparent parts stand out
+    pub checkerboard_background: bool,
+    pub image_frame: ImageFrame,
+    pub letterbox_color: LetterboxColor,
+    // how many images shift+arrow and page up/down skip at once
+    pub navigation_step: usize,
+    // shows a folder and all of its subfolders as a single gallery
+    pub combine_subfolders: bool,
+    // the arrows only move between images from the same folder as the shown
+    // one in a combined gallery, instead of through all of its images
+    pub navigate_within_folder: bool,
+    pub show_format_badges: bool,
+    // gallery tiles show when their photo was taken, or when the file was
+    // last modified for ones without a capture time
+    pub show_tile_dates: bool,
+    // fills the gallery tiles by center cropping thumbnails instead of
+    // letterboxing them
+    pub cover_thumbnails: bool,
+    // downscales thumbnails in linear light so fine high contrast detail
+    // doesn't come out darker than it is, which is slower
+    pub linear_thumbnails: bool,
+    // keeps cached thumbnails at a lower color depth so more of them fit in
+    // the memory cache
+    pub compact_thumbnails: bool,
+    // holds off scanning, thumbnail pre-generation and slideshow prefetching
+    // until it's turned off again
+    pub pause_background: bool,
+    // images and thumbnails being looked at also wait while background work
+    // is paused, instead of being decoded right away
+    pub pause_foreground: bool,
+    // megabytes of decoded thumbnails and full images kept in memory
+    pub cache_budget_mb: usize,
+    // seconds each image is shown for during a slideshow
+    pub slideshow_interval: f64,
+    // a slideshow plays a numbered sequence through once at the playback
+    // rate before moving past it, instead of showing each frame for the
+    // whole interval
+    pub slideshow_plays_sequences: bool,
+    // how many of the images coming up in a slideshow are decoded ahead of
+    // time, fewer when they wouldn't fit in half the memory cache
+    pub slideshow_prefetch: usize,
+    pub slideshow_transition: SlideshowTransition,
+    // seconds the transition between two slideshow images takes
+    pub transition_duration: f64,
+    pub sort_order: SortOrder,
+    pub folder_sort: FolderSort,
+    // rows of gallery thumbnails above and below the viewport that get
+    // decoded ahead of being scrolled into view
+    pub gallery_overscan: usize,
+    // seconds between swaps when flickering between two images
+    pub flicker_interval: f64,
+    pub peek_key: PeekKey,
+    pub folder_keys: FolderKeys,
+    // lays the gallery out in justified rows that keep each image's aspect
+    // ratio instead of a grid of squares
+    pub masonry_gallery: bool,
+    // lists the images as rows with a small thumbnail and their details,
+    // this takes over from the masonry and grid layouts
+    pub details_gallery: bool,
+    // sorts the grid and masonry layouts newest first and splits them into
+    // sections under the date the images in them were taken
+    pub date_grouping: DateGrouping,
+    // scans folders by file name only and shows names instead of
+    // thumbnails, so nothing is decoded until an image is opened
+    pub list_mode: bool,
+    // leaves images out of the scan that fit inside a square of the
+    // minimum size, like icons and sprites
+    pub skip_small_images: bool,
+    pub min_image_size: u32,
+    // folders holding a file with this name are left out of the library
+    // along with everything below them, nothing is left out when it's empty
+    pub exclusion_marker: String,
+    // command run to read formats the built-in decoders reject, none are
+    // read that way when it's empty
+    pub external_thumbnailer: String,
+    pub folder_end_action: FolderEndAction,
+    // a line of information about the shown image below the image view
+    pub show_status_bar: bool,
+    // the camera and exposure drawn over the image, and into exported views
+    pub show_caption: bool,
+    pub caption_content: CaptionContent,
+    pub caption_position: CaptionPosition,
+    // turns images upright using their EXIF orientation, otherwise the
+    // pixels are shown as they are stored
+    pub auto_rotate: bool,
+    // seconds scanning waits on a single file before skipping past it
+    pub scan_timeout: f64,
+    // times opening a file is tried again after an error that might be
+    // temporary, waiting longer before each try
+    pub open_retries: u32,
+    // how many days back the gallery's recent filter reaches
+    pub recent_days: u32,
+    pub number_locale: NumberLocale,
+    pub startup_view: StartupView,
+    // frames per second numbered image sequences are played back at
+    pub playback_fps: u32,
+    // animations never go faster than this many frames per second, however
+    // short their frames are
+    pub max_frame_rate: u32,
+    // images of at least this many megapixels are kept on disk at the size
+    // they're displayed at, 0 keeps none
+    pub render_cache_megapixels: u32,
+    // always opened on startup when the startup view is the pinned folder
+    pub pinned_folder: Option<Arc<PathBuf>>,
+    // the folder added most recently, remembered for the next start
+    pub last_folder: Option<Arc<PathBuf>>,
+    // where the favorite key copies the current image to
+    pub favorites_folder: Option<Arc<PathBuf>>,
+    // the order root folders are shown in once they've been reordered,
+    // roots that aren't in it go after
+    pub root_order: Vector<Arc<PathBuf>>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            double_click_zoom: 1.,
+            adaptive_fit: false,
+            click_to_advance: false,
+            fit_window_to_image: false,
+            back_to_library: false,
+            advance_on_rate: false,
+            confirm_trash: true,
+            scroll_panoramas: false,
+            scroll_tall_images: false,
+            smooth_display: true,
+            smooth_zoom: true,
+            wheel_action: WheelAction::Zoom,
+            scan_summary: true,
+            checkerboard_background: false,
+            image_frame: ImageFrame::None,
+            letterbox_color: LetterboxColor::White,
+            navigation_step: 10,
+            combine_subfolders: false,
+            navigate_within_folder: true,
+            show_format_badges: true,
+            show_tile_dates: false,
+            cover_thumbnails: false,
+            linear_thumbnails: false,
+            compact_thumbnails: false,
+            pause_background: false,
+            pause_foreground: false,
+            cache_budget_mb: 512,
+            slideshow_interval: 5.,
+            slideshow_plays_sequences: false,
+            slideshow_prefetch: 2,
+            slideshow_transition: SlideshowTransition::Fade,
+            transition_duration: 0.5,
+            sort_order: SortOrder::Name,
+            folder_sort: FolderSort::Library,
+            gallery_overscan: 2,
+            flicker_interval: 0.5,
+            peek_key: PeekKey::Q,
+            folder_keys: FolderKeys::Brackets,
+            masonry_gallery: false,
+            details_gallery: false,
+            date_grouping: DateGrouping::Off,
+            list_mode: false,
+            skip_small_images: false,
+            min_image_size: 64,
+            exclusion_marker: EXCLUSION_MARKERS[0].to_string(),
+            external_thumbnailer: EXTERNAL_THUMBNAILERS[0].to_string(),
+            folder_end_action: FolderEndAction::Stop,
+            show_status_bar: false,
+            show_caption: false,
+            caption_content: CaptionContent::Full,
+            caption_position: CaptionPosition::BottomRight,
+            auto_rotate: true,
+            scan_timeout: 5.,
+            open_retries: 2,
+            recent_days: 7,
+            number_locale: NumberLocale::System,
+            startup_view: StartupView::LastFolder,
+            playback_fps: 24,
+            max_frame_rate: 50,
+            render_cache_megapixels: 0,
+            pinned_folder: None,
+            last_folder: None,
+            favorites_folder: None,
+            root_order: Vector::new(),
+        }
+    }
+}
+
+// settings are kept between runs in this file, one "<key> <value>" pair per
+// line
+fn config_path() -> PathBuf {
+    let config_dir = std::env::var_os("APPDATA")
+        .or_else(|| std::env::var_os("XDG_CONFIG_HOME"))
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME")
+                .map(|home| PathBuf::from(home).join(".config"))
+        })
+        .unwrap_or_else(std::env::temp_dir);
+    config_dir.join("image-viewer").join("settings")
+}
+
+fn parse_into<T: FromStr>(field: &mut T, value: &str) {
+    if let Ok(value) = value.parse() {
+        *field = value;
+    }
+}
+
+// durations are kept within the range, an edited settings file with a
+// negative or huge one would otherwise panic when it's turned into a
+// Duration. Ones that aren't a number keep the default
+fn parse_seconds(field: &mut f64, value: &str, (min, max): (f64, f64)) {
+    if let Ok(value) = value.parse::<f64>() {
+        if !value.is_nan() {
+            *field = value.max(min).min(max);
+        }
+    }
+}
+
+impl Settings {
+    // the saved settings, with defaults for anything missing or unreadable
+    pub fn load() -> Self {
+        let mut settings = Self::default();
+        let contents = match fs::read_to_string(config_path()) {
+            Ok(contents) => contents,
+            Err(_) => return settings,
+        };
+        for line in contents.lines() {
+            let mut parts = line.splitn(2, ' ');
+            if let (Some(key), Some(value)) = (parts.next(), parts.next()) {
+                settings.set(key, value);
+            }
+        }
+        settings
+    }
+
+    fn set(&mut self, key: &str, value: &str) {
+        match key {
+            "double_click_zoom" => {
+                parse_into(&mut self.double_click_zoom, value)
+            }
+            "adaptive_fit" => parse_into(&mut self.adaptive_fit, value),
+            "click_to_advance" => parse_into(&mut self.click_to_advance, value),
+            "scan_summary" => parse_into(&mut self.scan_summary, value),
+            "smooth_display" => parse_into(&mut self.smooth_display, value),
+            "smooth_zoom" => parse_into(&mut self.smooth_zoom, value),
+            "wheel_action" => {
+                if let Some(action) = WheelAction::parse(value) {
+                    self.wheel_action = action;
+                }
+            }
+            "fit_window_to_image" => {
+                parse_into(&mut self.fit_window_to_image, value)
+            }
+            "back_to_library" => parse_into(&mut self.back_to_library, value),
+            "advance_on_rate" => parse_into(&mut self.advance_on_rate, value),
+            "confirm_trash" => parse_into(&mut self.confirm_trash, value),
+            "scroll_panoramas" => parse_into(&mut self.scroll_panoramas, value),
+            "scroll_tall_images" => {
+                parse_into(&mut self.scroll_tall_images, value)
+            }
+            "checkerboard_background" => {
+                parse_into(&mut self.checkerboard_background, value)
+            }
+            "navigation_step" => parse_into(&mut self.navigation_step, value),
+            "combine_subfolders" => {
+                parse_into(&mut self.combine_subfolders, value)
+            }
+            "navigate_within_folder" => {
+                parse_into(&mut self.navigate_within_folder, value)
+            }
+            "show_format_badges" => {
+                parse_into(&mut self.show_format_badges, value)
+            }
+            "show_tile_dates" => parse_into(&mut self.show_tile_dates, value),
+            "cover_thumbnails" => parse_into(&mut self.cover_thumbnails, value),
+            "linear_thumbnails" => {
+                parse_into(&mut self.linear_thumbnails, value)
+            }
+            "compact_thumbnails" => {
+                parse_into(&mut self.compact_thumbnails, value)
+            }
+            "cache_budget_mb" => parse_into(&mut self.cache_budget_mb, value),
+            "slideshow_interval" => parse_seconds(
+                &mut self.slideshow_interval,
+                value,
+                SLIDESHOW_INTERVAL_RANGE,
+            ),
+            "slideshow_plays_sequences" => {
+                parse_into(&mut self.slideshow_plays_sequences, value)
+            }
+            "slideshow_transition" => {
+                if let Some(transition) = SlideshowTransition::parse(value) {
+                    self.slideshow_transition = transition;
+                }
+            }
+            "transition_duration" => {
+                parse_into(&mut self.transition_duration, value)
+            }
+            "sort_order" => {
+                if let Some(order) = SortOrder::parse(value) {
+                    self.sort_order = order;
+                }
+            }
+            "folder_sort" => {
+                if let Some(sort) = FolderSort::parse(value) {
+                    self.folder_sort = sort;
+                }
+            }
+            "slideshow_prefetch" => {
+                parse_into(&mut self.slideshow_prefetch, value)
+            }
+            "pause_background" => parse_into(&mut self.pause_background, value),
+            "pause_foreground" => parse_into(&mut self.pause_foreground, value),
+            "gallery_overscan" => parse_into(&mut self.gallery_overscan, value),
+            "flicker_interval" => parse_seconds(
+                &mut self.flicker_interval,
+                value,
+                FLICKER_INTERVAL_RANGE,
+            ),
+            "peek_key" => {
+                if let Some(key) = PeekKey::parse(value) {
+                    self.peek_key = key;
+                }
+            }
+            "folder_keys" => {
+                if let Some(keys) = FolderKeys::parse(value) {
+                    self.folder_keys = keys;
+                }
+            }
+            "masonry_gallery" => parse_into(&mut self.masonry_gallery, value),
+            "details_gallery" => parse_into(&mut self.details_gallery, value),
+            "list_mode" => parse_into(&mut self.list_mode, value),
+            "skip_small_images" => {
+                parse_into(&mut self.skip_small_images, value)
+            }
+            "min_image_size" => parse_into(&mut self.min_image_size, value),
+            "exclusion_marker" => self.exclusion_marker = value.to_string(),
+            "external_thumbnailer" => {
+                self.external_thumbnailer = value.to_string()
+            }
+            "date_grouping" => {
+                if let Some(grouping) = DateGrouping::parse(value) {
+                    self.date_grouping = grouping;
+                }
+            }
+            "folder_end_action" => {
+                if let Some(action) = FolderEndAction::parse(value) {
+                    self.folder_end_action = action;
+                }
+            }
+            "show_status_bar" => parse_into(&mut self.show_status_bar, value),
+            "show_caption" => parse_into(&mut self.show_caption, value),
+            "caption_content" => {
+                if let Some(content) = CaptionContent::parse(value) {
+                    self.caption_content = content;
+                }
+            }
+            "letterbox_color" => {
+                if let Some(color) = LetterboxColor::parse(value) {
+                    self.letterbox_color = color;
+                }
+            }
+            "image_frame" => {
+                if let Some(frame) = ImageFrame::parse(value) {
+                    self.image_frame = frame;
+                }
+            }
+            "caption_position" => {
+                if let Some(position) = CaptionPosition::parse(value) {
+                    self.caption_position = position;
+                }
+            }
+            "auto_rotate" => parse_into(&mut self.auto_rotate, value),
+            "scan_timeout" => {
+                parse_seconds(&mut self.scan_timeout, value, SCAN_TIMEOUT_RANGE)
+            }
+            "open_retries" => parse_into(&mut self.open_retries, value),
+            "recent_days" => parse_into(&mut self.recent_days, value),
+            "number_locale" => {
+                if let Some(locale) = NumberLocale::parse(value) {
+                    self.number_locale = locale;
+                }
+            }
+            "startup_view" => {
+                if let Some(view) = StartupView::parse(value) {
+                    self.startup_view = view;
+                }
+            }
+            "playback_fps" => parse_into(&mut self.playback_fps, value),
+            "max_frame_rate" => parse_into(&mut self.max_frame_rate, value),
+            "render_cache_megapixels" => {
+                parse_into(&mut self.render_cache_megapixels, value)
+            }
+            "pinned_folder" => {
+                self.pinned_folder = Some(Arc::new(PathBuf::from(value)))
+            }
+            "last_folder" => {
+                self.last_folder = Some(Arc::new(PathBuf::from(value)))
+            }
+            "favorites_folder" => {
+                self.favorites_folder = Some(Arc::new(PathBuf::from(value)))
+            }
+            // one line for each root, in order
+            "root_folder" => {
+                self.root_order.push_back(Arc::new(PathBuf::from(value)))
+            }
+            _ => (),
+        }
+    }
+
+    pub fn save(&self) {
+        let mut contents = vec![
+            format!("double_click_zoom {}", self.double_click_zoom),
+            format!("adaptive_fit {}", self.adaptive_fit),
+            format!("click_to_advance {}", self.click_to_advance),
+            format!("fit_window_to_image {}", self.fit_window_to_image),
+            format!("back_to_library {}", self.back_to_library),
+            format!("advance_on_rate {}", self.advance_on_rate),
+            format!("confirm_trash {}", self.confirm_trash),
+            format!("scroll_panoramas {}", self.scroll_panoramas),
+            format!("scroll_tall_images {}", self.scroll_tall_images),
+            format!("smooth_display {}", self.smooth_display),
+            format!("smooth_zoom {}", self.smooth_zoom),
+            format!("wheel_action {}", self.wheel_action.label()),
+            format!("scan_summary {}", self.scan_summary),
+            format!("checkerboard_background {}", self.checkerboard_background),
+            format!("image_frame {}", self.image_frame.label()),
+            format!("letterbox_color {}", self.letterbox_color.label()),
+            format!("navigation_step {}", self.navigation_step),
+            format!("combine_subfolders {}", self.combine_subfolders),
+            format!("navigate_within_folder {}", self.navigate_within_folder),
+            format!("show_format_badges {}", self.show_format_badges),
+            format!("show_tile_dates {}", self.show_tile_dates),
+            format!("cover_thumbnails {}", self.cover_thumbnails),
+            format!("linear_thumbnails {}", self.linear_thumbnails),
+            format!("compact_thumbnails {}", self.compact_thumbnails),
+            format!("pause_background {}", self.pause_background),
+            format!("pause_foreground {}", self.pause_foreground),
+            format!("cache_budget_mb {}", self.cache_budget_mb),
+            format!("slideshow_interval {}", self.slideshow_interval),
+            format!(
+                "slideshow_plays_sequences {}",
+                self.slideshow_plays_sequences
+            ),
+            format!("slideshow_prefetch {}", self.slideshow_prefetch),
+            format!(
+                "slideshow_transition {}",
+                self.slideshow_transition.label()
+            ),
+            format!("transition_duration {}", self.transition_duration),
+            format!("sort_order {}", self.sort_order.label()),
+            format!("folder_sort {}", self.folder_sort.label()),
+            format!("gallery_overscan {}", self.gallery_overscan),
+            format!("flicker_interval {}", self.flicker_interval),
+            format!("peek_key {}", self.peek_key.label()),
+            format!("folder_keys {}", self.folder_keys.label()),
+            format!("masonry_gallery {}", self.masonry_gallery),
+            format!("details_gallery {}", self.details_gallery),
+            format!("list_mode {}", self.list_mode),
+            format!("skip_small_images {}", self.skip_small_images),
+            format!("min_image_size {}", self.min_image_size),
+            format!("exclusion_marker {}", self.exclusion_marker),
+            format!("external_thumbnailer {}", self.external_thumbnailer),
+            format!("date_grouping {}", self.date_grouping.label()),
+            format!("folder_end_action {}", self.folder_end_action.label()),
+            format!("show_status_bar {}", self.show_status_bar),
+            format!("show_caption {}", self.show_caption),
+            format!("caption_content {}", self.caption_content.label()),
+            format!("caption_position {}", self.caption_position.label()),
+            format!("auto_rotate {}", self.auto_rotate),
+            format!("scan_timeout {}", self.scan_timeout),
+            format!("open_retries {}", self.open_retries),
+            format!("recent_days {}", self.recent_days),
+            format!("number_locale {}", self.number_locale.label()),
+            format!("startup_view {}", self.startup_view.label()),
+            format!("playback_fps {}", self.playback_fps),
+            format!("max_frame_rate {}", self.max_frame_rate),
+            format!("render_cache_megapixels {}", self.render_cache_megapixels),
+        ];
+        if let Some(folder) = &self.pinned_folder {
+            contents.push(format!("pinned_folder {}", folder.display()));
+        }
+        if let Some(folder) = &self.last_folder {
+            contents.push(format!("last_folder {}", folder.display()));
+        }
+        if let Some(folder) = &self.favorites_folder {
+            contents.push(format!("favorites_folder {}", folder.display()));
+        }
+        for root in &self.root_order {
+            contents.push(format!("root_folder {}", root.display()));
+        }
+        let contents = contents.join("\n");
+        let path = config_path();
+        let result = fs::create_dir_all(path.parent().unwrap())
+            .and_then(|_| fs::write(&path, contents));
+        if let Err(err) = result {
+            error!("Error saving settings to {}: {}", path.display(), err);
+        }
+    }
+
+    // the folder to open on startup, if the startup view opens one
+    pub fn startup_folder(&self) -> Option<Arc<PathBuf>> {
+        match self.startup_view {
+            StartupView::LastFolder => self.last_folder.clone(),
+            StartupView::PinnedFolder => self.pinned_folder.clone(),
+            StartupView::ChooseFolder => None,
+        }
+    }
+
+    // exposes the settings needed by widgets that only see part of the
+    // data, like gallery tiles
+    pub fn set_env(&self, env: &mut Env) {
+        env.set(SHOW_FORMAT_BADGES, self.show_format_badges);
+        env.set(SHOW_TILE_DATES, self.show_tile_dates);
+        env.set(COVER_THUMBNAILS, self.cover_thumbnails);
+        env.set(NUMBER_LOCALE, self.number_locale.label());
+    }
+
+    // the budget after the current one, wrapping around to the smallest
+    pub fn next_cache_budget(&self) -> usize {
+        CACHE_BUDGETS_MB
+            .iter()
+            .copied()
+            .find(|budget| *budget > self.cache_budget_mb)
+            .unwrap_or(CACHE_BUDGETS_MB[0])
+    }
+
+    pub fn next_flicker_interval(&self) -> f64 {
+        FLICKER_INTERVALS
+            .iter()
+            .copied()
+            .find(|interval| *interval > self.flicker_interval)
+            .unwrap_or(FLICKER_INTERVALS[0])
+    }
+
+    pub fn next_scan_timeout(&self) -> f64 {
+        SCAN_TIMEOUTS
+            .iter()
+            .copied()
+            .find(|timeout| *timeout > self.scan_timeout)
+            .unwrap_or(SCAN_TIMEOUTS[0])
+    }
+
+    pub fn next_open_retries(&self) -> u32 {
+        OPEN_RETRIES
+            .iter()
+            .copied()
+            .find(|retries| *retries > self.open_retries)
+            .unwrap_or(OPEN_RETRIES[0])
+    }
+
+    pub fn next_recent_days(&self) -> u32 {
+        RECENT_DAYS
+            .iter()
+            .copied()
+            .find(|days| *days > self.recent_days)
+            .unwrap_or(RECENT_DAYS[0])
+    }
+
+    pub fn next_playback_fps(&self) -> u32 {
+        PLAYBACK_RATES
+            .iter()
+            .copied()
+            .find(|fps| *fps > self.playback_fps)
+            .unwrap_or(PLAYBACK_RATES[0])
+    }
+
+    pub fn next_max_frame_rate(&self) -> u32 {
+        FRAME_RATE_CAPS
+            .iter()
+            .copied()
+            .find(|fps| *fps > self.max_frame_rate)
+            .unwrap_or(FRAME_RATE_CAPS[0])
+    }
+
+    pub fn next_render_cache_megapixels(&self) -> u32 {
+        RENDER_CACHE_THRESHOLDS
+            .iter()
+            .copied()
+            .find(|megapixels| *megapixels > self.render_cache_megapixels)
+            .unwrap_or(RENDER_CACHE_THRESHOLDS[0])
+    }
+
+    // the playback rate held to the frame rate cap, a rate of 0 set in the
+    // settings file by hand counts as 1
+    pub fn capped_playback_fps(&self) -> u32 {
+        self.playback_fps.min(self.max_frame_rate).max(1)
+    }
+
+    // how long each frame of a sequence is shown
+    pub fn frame_interval(&self) -> Duration {
+        Duration::from_secs_f64(1. / self.capped_playback_fps() as f64)
+    }
+
+    pub fn next_slideshow_prefetch(&self) -> usize {
+        SLIDESHOW_PREFETCHES
+            .iter()
+            .copied()
+            .find(|prefetch| *prefetch > self.slideshow_prefetch)
+            .unwrap_or(SLIDESHOW_PREFETCHES[0])
+    }
+
+    pub fn next_min_image_size(&self) -> u32 {
+        MIN_IMAGE_SIZES
+            .iter()
+            .copied()
+            .find(|size| *size > self.min_image_size)
+            .unwrap_or(MIN_IMAGE_SIZES[0])
+    }
+
+    // a name set in the settings file by hand moves on to the first one
+    pub fn next_exclusion_marker(&self) -> String {
+        let next = EXCLUSION_MARKERS
+            .iter()
+            .position(|marker| *marker == self.exclusion_marker)
+            .map_or(0, |idx| (idx + 1) % EXCLUSION_MARKERS.len());
+        EXCLUSION_MARKERS[next].to_string()
+    }
+
+    // a command set in the settings file by hand moves on to the first one
+    pub fn next_external_thumbnailer(&self) -> String {
+        let next = EXTERNAL_THUMBNAILERS
+            .iter()
+            .position(|command| *command == self.external_thumbnailer)
+            .map_or(0, |idx| (idx + 1) % EXTERNAL_THUMBNAILERS.len());
+        EXTERNAL_THUMBNAILERS[next].to_string()
+    }
+
+    // the folder has the exclusion marker in it, so the scan skips it
+    pub fn is_excluded(&self, folder: &Path) -> bool {
+        !self.exclusion_marker.is_empty()
+            && folder.join(&self.exclusion_marker).is_file()
+    }
+
+    // the image is too small to be shown by the small image settings, images
+    // whose size isn't known are kept
+    pub fn is_too_small(&self, dimensions: Option<(u32, u32)>) -> bool {
+        self.skip_small_images
+            && dimensions.map_or(false, |(width, height)| {
+                width.max(height) < self.min_image_size
+            })
+    }
+
+    pub fn next_gallery_overscan(&self) -> usize {
+        GALLERY_OVERSCANS
+            .iter()
+            .copied()
+            .find(|overscan| *overscan > self.gallery_overscan)
+            .unwrap_or(GALLERY_OVERSCANS[0])
+    }
+}
+
+pub fn number_locale(env: &Env) -> NumberLocale {
+    NumberLocale::parse(&env.get(NUMBER_LOCALE)).unwrap_or(NumberLocale::System)
+}
+
+pub fn thumbnail_fill(env: &Env) -> FillStrat {
+    if env.get(COVER_THUMBNAILS) {
+        FillStrat::Cover
+    } else {
+        FillStrat::Contain
+    }
+}
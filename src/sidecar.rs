@@ -0,0 +1,306 @@
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use log::error;
+
+use crate::app_data::Flag;
+
+// every folder keeps the flags of its images in this file, one
+// "<flag> <file name>" pair per line
+const FLAGS_FILE_NAME: &str = ".image-viewer-flags";
+// and their ratings in this one, one "<stars> <file name>" pair per line
+const RATINGS_FILE_NAME: &str = ".image-viewer-ratings";
+// and their captions in this one, one "<caption><tab><file name>" pair per
+// line
+const CAPTIONS_FILE_NAME: &str = ".image-viewer-captions";
+
+fn flags_path(folder: &Path) -> PathBuf {
+    folder.join(FLAGS_FILE_NAME)
+}
+
+fn ratings_path(folder: &Path) -> PathBuf {
+    folder.join(RATINGS_FILE_NAME)
+}
+
+fn captions_path(folder: &Path) -> PathBuf {
+    folder.join(CAPTIONS_FILE_NAME)
+}
+
+pub fn load_flags(folder: &Path) -> HashMap<OsString, Flag> {
+    let contents = match fs::read_to_string(flags_path(folder)) {
+        Ok(contents) => contents,
+        Err(_) => return HashMap::new(),
+    };
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, ' ');
+            let flag = Flag::parse(parts.next()?)?;
+            let file_name = parts.next()?;
+            Some((OsString::from(file_name), flag))
+        })
+        .collect()
+}
+
+// changes the flag of the image in its folder's flags file, the file is
+// removed when nothing is flagged anymore
+pub fn save_flag(path: &Path, flag: Flag) {
+    let value = match flag {
+        Flag::None => None,
+        flag => Some(flag.as_str()),
+    };
+    update_entry(path, flags_path, ' ', value);
+}
+
+pub fn load_ratings(folder: &Path) -> HashMap<OsString, u8> {
+    let contents = match fs::read_to_string(ratings_path(folder)) {
+        Ok(contents) => contents,
+        Err(_) => return HashMap::new(),
+    };
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, ' ');
+            let rating = parts.next()?.parse().ok().filter(|r| *r <= 5)?;
+            let file_name = parts.next()?;
+            Some((OsString::from(file_name), rating))
+        })
+        .collect()
+}
+
+// changes the rating of the image in its folder's ratings file, the file is
+// removed when nothing is rated anymore
+pub fn save_rating(path: &Path, rating: u8) {
+    let value = match rating {
+        0 => None,
+        rating => Some(rating.to_string()),
+    };
+    update_entry(path, ratings_path, ' ', value.as_deref());
+}
+
+pub fn load_captions(folder: &Path) -> HashMap<OsString, Arc<String>> {
+    let contents = match fs::read_to_string(captions_path(folder)) {
+        Ok(contents) => contents,
+        Err(_) => return HashMap::new(),
+    };
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, '\t');
+            let caption = parts.next().filter(|caption| !caption.is_empty())?;
+            let file_name = parts.next()?;
+            Some((OsString::from(file_name), Arc::new(caption.to_string())))
+        })
+        .collect()
+}
+
+// changes the caption of the image in its folder's captions file, the file
+// is removed when nothing has a caption anymore. Tabs and line breaks in a
+// caption are kept as spaces so each one stays on its line
+pub fn save_caption(path: &Path, caption: Option<&str>) {
+    let caption =
+        caption.map(|caption| caption.replace(&['\t', '\n', '\r'][..], " "));
+    update_entry(path, captions_path, '\t', caption.as_deref());
+}
+
+// changes the line of a single image, or removes it when there's no value,
+// and keeps every other line as it was. Images that aren't in the gallery,
+// like ones that are excluded or couldn't be read, keep what they had
+fn update_entry(
+    path: &Path,
+    sidecar_path: fn(&Path) -> PathBuf,
+    separator: char,
+    value: Option<&str>,
+) {
+    let (folder, file_name) = match (path.parent(), path.file_name()) {
+        (Some(folder), Some(file_name)) => (folder, file_name),
+        _ => return,
+    };
+    let file_name = file_name.to_string_lossy();
+    let sidecar = sidecar_path(folder);
+    let contents = match fs::read_to_string(&sidecar) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+        // writing would lose what couldn't be read
+        Err(err) => {
+            error!("Error reading {}: {}", sidecar.display(), err);
+            return;
+        }
+    };
+    let line =
+        value.map(|value| format!("{}{}{}", value, separator, file_name));
+    let mut found = false;
+    let mut lines: Vec<String> = contents
+        .lines()
+        .filter_map(|current| {
+            if current.splitn(2, separator).nth(1) != Some(file_name.as_ref()) {
+                return Some(current.to_string());
+            }
+            // a name that's in the file more than once only keeps one line
+            if found {
+                return None;
+            }
+            found = true;
+            line.clone()
+        })
+        .collect();
+    if !found {
+        lines.extend(line);
+    }
+    let mut contents = lines.join("\n");
+    if !contents.is_empty() {
+        contents.push('\n');
+    }
+    write_or_remove(&sidecar, &contents);
+}
+
+// an empty sidecar file isn't kept around
+fn write_or_remove(path: &Path, contents: &str) {
+    let result = if contents.is_empty() {
+        if path.exists() {
+            fs::remove_file(&path)
+        } else {
+            Ok(())
+        }
+    } else {
+        fs::write(&path, contents)
+    };
+    if let Err(err) = result {
+        error!("Error saving {}: {}", path.display(), err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a new folder for the test to keep its sidecar files in
+    fn test_folder(name: &str) -> PathBuf {
+        let folder = std::env::temp_dir().join(format!(
+            "image-viewer-sidecar-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+        folder
+    }
+
+    #[test]
+    fn flags_round_trip() {
+        let folder = test_folder("flags");
+        save_flag(&folder.join("summer trip 01.jpg"), Flag::Pick);
+        save_flag(&folder.join("b.png"), Flag::Reject);
+        let flags = load_flags(&folder);
+        assert_eq!(flags.len(), 2);
+        assert_eq!(flags[&OsString::from("summer trip 01.jpg")], Flag::Pick);
+        assert_eq!(flags[&OsString::from("b.png")], Flag::Reject);
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn saving_a_flag_keeps_the_other_lines() {
+        let folder = test_folder("flags-kept");
+        fs::write(flags_path(&folder), "pick gone.jpg\nreject a b.jpg\n")
+            .unwrap();
+        save_flag(&folder.join("a b.jpg"), Flag::Pick);
+        save_flag(&folder.join("new.jpg"), Flag::Reject);
+        assert_eq!(
+            fs::read_to_string(flags_path(&folder)).unwrap(),
+            "pick gone.jpg\npick a b.jpg\nreject new.jpg\n"
+        );
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn clearing_every_flag_removes_the_file() {
+        let folder = test_folder("flags-cleared");
+        save_flag(&folder.join("a.jpg"), Flag::Pick);
+        save_flag(&folder.join("a.jpg"), Flag::None);
+        assert!(!flags_path(&folder).exists());
+        assert!(load_flags(&folder).is_empty());
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn ratings_round_trip() {
+        let folder = test_folder("ratings");
+        save_rating(&folder.join("summer trip 01.jpg"), 5);
+        save_rating(&folder.join("b.png"), 1);
+        save_rating(&folder.join("b.png"), 3);
+        let ratings = load_ratings(&folder);
+        assert_eq!(ratings.len(), 2);
+        assert_eq!(ratings[&OsString::from("summer trip 01.jpg")], 5);
+        assert_eq!(ratings[&OsString::from("b.png")], 3);
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn saving_a_rating_keeps_the_other_lines() {
+        let folder = test_folder("ratings-kept");
+        fs::write(ratings_path(&folder), "4 gone.jpg\n2 a b.jpg\n").unwrap();
+        save_rating(&folder.join("a b.jpg"), 0);
+        assert_eq!(
+            fs::read_to_string(ratings_path(&folder)).unwrap(),
+            "4 gone.jpg\n"
+        );
+        let ratings = load_ratings(&folder);
+        assert_eq!(ratings[&OsString::from("gone.jpg")], 4);
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn captions_round_trip() {
+        let folder = test_folder("captions");
+        save_caption(&folder.join("summer trip 01.jpg"), Some("at the lake"));
+        save_caption(&folder.join("b c.png"), Some("two\tlines\nhere"));
+        let captions = load_captions(&folder);
+        assert_eq!(captions.len(), 2);
+        assert_eq!(
+            captions[&OsString::from("summer trip 01.jpg")].as_str(),
+            "at the lake"
+        );
+        assert_eq!(
+            captions[&OsString::from("b c.png")].as_str(),
+            "two lines here"
+        );
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn saving_a_caption_keeps_the_other_lines() {
+        let folder = test_folder("captions-kept");
+        fs::write(
+            captions_path(&folder),
+            "not in the gallery\tgone.jpg\nold words\ta b.jpg\n",
+        )
+        .unwrap();
+        save_caption(&folder.join("a b.jpg"), Some("new words"));
+        save_caption(&folder.join("gone 2.jpg"), None);
+        assert_eq!(
+            fs::read_to_string(captions_path(&folder)).unwrap(),
+            "not in the gallery\tgone.jpg\nnew words\ta b.jpg\n"
+        );
+        save_caption(&folder.join("a b.jpg"), None);
+        save_caption(&folder.join("gone.jpg"), None);
+        assert!(!captions_path(&folder).exists());
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn ignores_ratings_out_of_range() {
+        let folder = test_folder("ratings-range");
+        fs::write(ratings_path(&folder), "9 a.jpg\nx b.jpg\n3 c.jpg\n")
+            .unwrap();
+        let ratings = load_ratings(&folder);
+        assert_eq!(ratings.len(), 1);
+        assert_eq!(ratings[&OsString::from("c.jpg")], 3);
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
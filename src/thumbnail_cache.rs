@@ -0,0 +1,88 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use image::RgbImage;
+use log::error;
+
+use crate::background::WriteJob;
+
+pub fn cache_dir() -> PathBuf {
+    std::env::temp_dir().join("image-viewer-thumbnails")
+}
+
+// thumbnails are keyed by the image path and its modification time so an
+// edited image doesn't keep showing a stale thumbnail, ones downscaled in
+// linear light are kept next to the others
+fn cache_path(path: &Path, linear_light: bool) -> Option<PathBuf> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    modified.hash(&mut hasher);
+    let suffix = if linear_light { "-linear" } else { "" };
+    Some(cache_dir().join(format!("{:016x}{}.png", hasher.finish(), suffix)))
+}
+
+pub fn is_cached(path: &Path, linear_light: bool) -> bool {
+    match cache_path(path, linear_light) {
+        Some(cache_path) => cache_path.exists(),
+        None => false,
+    }
+}
+
+pub fn load(path: &Path, linear_light: bool) -> Option<RgbImage> {
+    let cache_path = cache_path(path, linear_light)?;
+    if !cache_path.exists() {
+        return None;
+    }
+    match image::open(&cache_path) {
+        Ok(image) => Some(image.to_rgb8()),
+        Err(err) => {
+            error!(
+                "Error reading cached thumbnail {}: {}",
+                cache_path.display(),
+                err
+            );
+            None
+        }
+    }
+}
+
+pub fn store(path: &Path, thumbnail: &RgbImage, linear_light: bool) {
+    let _job = WriteJob::start();
+    let cache_path = match cache_path(path, linear_light) {
+        Some(cache_path) => cache_path,
+        None => return,
+    };
+    if let Err(err) = fs::create_dir_all(cache_dir()) {
+        error!("Error creating thumbnail cache directory: {}", err);
+        return;
+    }
+    if let Err(err) = thumbnail.save(&cache_path) {
+        error!(
+            "Error writing cached thumbnail {}: {}",
+            cache_path.display(),
+            err
+        );
+    }
+}
+
+pub fn remove(path: &Path, linear_light: bool) {
+    let cache_path = match cache_path(path, linear_light) {
+        Some(cache_path) => cache_path,
+        None => return,
+    };
+    if !cache_path.exists() {
+        return;
+    }
+    if let Err(err) = fs::remove_file(&cache_path) {
+        error!(
+            "Error removing cached thumbnail {}: {}",
+            cache_path.display(),
+            err
+        );
+    }
+}
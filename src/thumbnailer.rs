@@ -0,0 +1,128 @@
+use std::{
+    fs,
+    path::Path,
+    process::{self, Command, Stdio},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use image::RgbImage;
+use log::error;
+use once_cell::sync::Lazy;
+
+use crate::app_data::FileFormat;
+
+// the command run for formats the built-in decoders reject, with {input}
+// replaced by the image's path and {output} by where it should write a PNG.
+// Empty when there's no external thumbnailer
+static COMMAND: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(String::new()));
+// every preview gets its own output file, several can be made at once
+static NEXT_OUTPUT: AtomicU64 = AtomicU64::new(0);
+// a thumbnailer that hasn't finished by then is stopped
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+// extensions only the external thumbnailer can read, and the formats they
+// are shown as
+const EXTERNAL_FORMATS: [(&str, FileFormat); 9] = [
+    ("avif", FileFormat::Avif),
+    ("jxl", FileFormat::Jxl),
+    ("heic", FileFormat::Heif),
+    ("heif", FileFormat::Heif),
+    ("cr3", FileFormat::Raw),
+    ("raf", FileFormat::Raw),
+    ("orf", FileFormat::Raw),
+    ("rw2", FileFormat::Raw),
+    ("nrw", FileFormat::Raw),
+];
+
+pub fn set_command(command: &str) {
+    *COMMAND.lock().unwrap() = command.trim().to_string();
+}
+
+// the format of a file only the external thumbnailer can read, none when
+// there's no thumbnailer to read it
+pub fn format(path: &Path) -> Option<FileFormat> {
+    if COMMAND.lock().unwrap().is_empty() {
+        return None;
+    }
+    let extension = path.extension()?.to_str()?;
+    EXTERNAL_FORMATS
+        .iter()
+        .find(|(external, _)| external.eq_ignore_ascii_case(extension))
+        .map(|(_, format)| *format)
+}
+
+// runs the external thumbnailer on the image and reads what it wrote. It
+// blocks until the tool is done, so it should be run off the main thread.
+// A missing tool or one that fails gives none
+pub fn preview(path: &Path) -> Option<RgbImage> {
+    let command = COMMAND.lock().unwrap().clone();
+    if command.is_empty() {
+        return None;
+    }
+    let output = std::env::temp_dir().join(format!(
+        "image-viewer-thumbnailer-{}-{}.png",
+        process::id(),
+        NEXT_OUTPUT.fetch_add(1, Ordering::SeqCst)
+    ));
+    let image = run(&command, path, &output);
+    let _ = fs::remove_file(&output);
+    image
+}
+
+fn run(command: &str, input: &Path, output: &Path) -> Option<RgbImage> {
+    // each part is substituted on its own, so paths with spaces in them stay
+    // a single argument
+    let mut args = command.split_whitespace().map(|arg| {
+        arg.replace("{input}", &input.to_string_lossy())
+            .replace("{output}", &output.to_string_lossy())
+    });
+    let program = args.next()?;
+    let mut child = match Command::new(&program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(err) => {
+            error!("Error running {}: {}", program, err);
+            return None;
+        }
+    };
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if started.elapsed() < TIMEOUT => {
+                thread::sleep(Duration::from_millis(20))
+            }
+            Ok(None) => {
+                error!("{} timed out on {}", program, input.display());
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+            Err(err) => {
+                error!("Error waiting on {}: {}", program, err);
+                return None;
+            }
+        }
+    };
+    if !status.success() {
+        error!("{} failed on {}: {}", program, input.display(), status);
+        return None;
+    }
+    match image::open(output) {
+        Ok(image) => Some(image.to_rgb8()),
+        Err(err) => {
+            error!("Error reading what {} wrote: {}", program, err);
+            None
+        }
+    }
+}
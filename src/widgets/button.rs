@@ -1,18 +1,23 @@
 use druid::{
     self,
-    widget::{Label, LabelText},
-    Affine, Color, Env, Event, LifeCycle, RenderContext, Size,
+    widget::{Click, ControllerHost, Label, LabelText},
+    Affine, Color, Env, Event, EventCtx, LifeCycle, RenderContext, Size,
+    WidgetExt,
 };
 
 use druid::{Data, Widget};
 
+const DISABLED_TEXT_COLOR: Color = Color::rgb8(0xbb, 0xbb, 0xbb);
+
 pub struct Button<T: Data> {
     text: Label<T>,
-    // color: druid::Color,
+    color: druid::Color,
     background_color: druid::Color,
     hover_color: druid::Color,
     active_color: druid::Color,
     text_size: Size,
+    is_disabled: Option<Box<dyn Fn(&T) -> bool>>,
+    disabled: bool,
 }
 
 impl<T: Data> Button<T> {
@@ -26,15 +31,62 @@ impl<T: Data> Button<T> {
     ) -> Self {
         Self {
             text: Label::new(text)
-                .with_text_color(color)
+                .with_text_color(color.clone())
                 .with_text_size(font_size),
-            // color,
+            color,
             background_color,
             hover_color,
             active_color,
             text_size: Size::ZERO,
+            is_disabled: None,
+            disabled: false,
         }
     }
+
+    // the button is greyed out and doesn't highlight whenever this returns
+    // true for the current data
+    pub fn disabled_if(
+        mut self,
+        is_disabled: impl Fn(&T) -> bool + 'static,
+    ) -> Self {
+        self.is_disabled = Some(Box::new(is_disabled));
+        self
+    }
+
+    // returns true if the disabled state changed
+    fn update_disabled(&mut self, data: &T) -> bool {
+        let disabled = match &self.is_disabled {
+            Some(is_disabled) => is_disabled(data),
+            None => false,
+        };
+        if disabled == self.disabled {
+            return false;
+        }
+        self.disabled = disabled;
+        if disabled {
+            self.text.set_text_color(DISABLED_TEXT_COLOR);
+        } else {
+            self.text.set_text_color(self.color.clone());
+        }
+        true
+    }
+}
+
+// the black on white button of the main view and the preferences, doing the
+// action when it's clicked
+pub fn text_button<T: Data>(
+    text: impl Into<LabelText<T>>,
+    action: impl Fn(&mut EventCtx, &mut T, &Env) + 'static,
+) -> ControllerHost<Button<T>, Click<T>> {
+    Button::new(
+        text,
+        Color::BLACK,
+        Color::rgb8(0xff, 0xff, 0xff),
+        Color::rgb8(0xdd, 0xdd, 0xdd),
+        Color::rgb8(0x9f, 0x9f, 0x9f),
+        16.,
+    )
+    .on_click(action)
 }
 
 impl<T: Data> Widget<T> for Button<T> {
@@ -67,8 +119,12 @@ impl<T: Data> Widget<T> for Button<T> {
         data: &T,
         env: &Env,
     ) {
-        if let LifeCycle::HotChanged(_) = event {
-            ctx.request_paint();
+        match event {
+            LifeCycle::WidgetAdded => {
+                self.update_disabled(data);
+            }
+            LifeCycle::HotChanged(_) => ctx.request_paint(),
+            _ => (),
         }
         self.text.lifecycle(ctx, event, data, env)
     }
@@ -80,6 +136,10 @@ impl<T: Data> Widget<T> for Button<T> {
         data: &T,
         env: &Env,
     ) {
+        if self.update_disabled(data) {
+            ctx.request_layout();
+            ctx.request_paint();
+        }
         self.text.update(ctx, old_data, data, env);
     }
 
@@ -114,14 +174,14 @@ impl<T: Data> Widget<T> for Button<T> {
         let stroke_width = 0.0;
         let rect = size.to_rect();
 
-        let (border_color, bg_color) = if is_hot {
-            if is_active {
-                (self.active_color.clone(), self.active_color.clone())
-            } else {
-                (self.hover_color.clone(), self.hover_color.clone())
-            }
-        } else {
+        // a disabled button doesn't light up when hovered or pressed, its
+        // label is greyed out when it's updated
+        let (border_color, bg_color) = if self.disabled || !is_hot {
             (self.background_color.clone(), self.background_color.clone())
+        } else if is_active {
+            (self.active_color.clone(), self.active_color.clone())
+        } else {
+            (self.hover_color.clone(), self.hover_color.clone())
         };
 
         // paint border
@@ -0,0 +1,266 @@
+use std::{cell::RefCell, marker::PhantomData, rc::Rc};
+
+use druid::widget::prelude::*;
+use druid::{Point, Rect, WidgetPod};
+use druid_gridview::GridIter;
+
+// the vertical extent of a laid out row and the children in it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JustifiedRow {
+    pub top: f64,
+    pub bottom: f64,
+    pub start: usize,
+    pub end: usize,
+}
+
+// the rows from the last layout and the part of them that's scrolled into
+// view, shared with the scroll the layout is in
+#[derive(Debug, Default)]
+pub struct JustifiedRows {
+    pub rows: Vec<JustifiedRow>,
+    // top and bottom of what's in view, every row is laid out until it's
+    // known
+    viewport: Option<(f64, f64)>,
+    // top and bottom of the rows that were laid out last
+    laid_out: (f64, f64),
+}
+
+impl JustifiedRows {
+    // moves what's in view, returns true when it reaches rows that have to
+    // be laid out first
+    pub fn scroll_to(&mut self, top: f64, bottom: f64) -> bool {
+        self.viewport = Some((top, bottom));
+        top < self.laid_out.0 || bottom > self.laid_out.1
+    }
+
+    // the viewport widened by its height on both sides, so scrolling a bit
+    // doesn't need another layout. The children in it are always laid out
+    fn layout_band(&self) -> (f64, f64) {
+        match self.viewport {
+            Some((top, bottom)) => {
+                let margin = bottom - top;
+                (top - margin, bottom + margin)
+            }
+            None => (f64::NEG_INFINITY, f64::INFINITY),
+        }
+    }
+}
+
+// lays its children out in rows stretched to fill the width, the way photo
+// galleries do, with every child keeping its aspect ratio
+pub struct Justified<C, T> {
+    closure: Box<dyn Fn() -> Box<dyn Widget<C>>>,
+    aspect_ratio: Box<dyn Fn(&C) -> f64>,
+    children: Vec<WidgetPod<C, Box<dyn Widget<C>>>>,
+    row_height: f64,
+    // the rows from the last layout, shared so others can tell which
+    // children are visible
+    rows: Rc<RefCell<JustifiedRows>>,
+    phantom: PhantomData<T>,
+}
+
+impl<C: Data, T: GridIter<C>> Justified<C, T> {
+    pub fn new<W: Widget<C> + 'static>(
+        closure: impl Fn() -> W + 'static,
+        aspect_ratio: impl Fn(&C) -> f64 + 'static,
+    ) -> Self {
+        Self {
+            closure: Box::new(move || Box::new(closure())),
+            aspect_ratio: Box::new(aspect_ratio),
+            children: Vec::new(),
+            row_height: 160.,
+            rows: Rc::new(RefCell::new(JustifiedRows::default())),
+            phantom: PhantomData,
+        }
+    }
+
+    // the height rows aim for before being stretched to the width
+    pub fn with_row_height(mut self, row_height: f64) -> Self {
+        self.row_height = row_height;
+        self
+    }
+
+    pub fn with_rows(mut self, rows: Rc<RefCell<JustifiedRows>>) -> Self {
+        self.rows = rows;
+        self
+    }
+
+    // returns true if children were added or removed
+    fn update_child_count(&mut self, data: &T) -> bool {
+        let len = self.children.len();
+        let data_len = data.data_len();
+        if len > data_len {
+            self.children.truncate(data_len);
+        } else {
+            for _ in len..data_len {
+                self.children.push(WidgetPod::new((self.closure)()));
+            }
+        }
+        len != data_len
+    }
+
+    fn layout_rows(
+        &self,
+        aspect_ratios: &[f64],
+        width: f64,
+    ) -> Vec<JustifiedRow> {
+        let mut rows = Vec::new();
+        let mut top = 0.;
+        let mut start = 0;
+        while start < aspect_ratios.len() {
+            let mut end = start;
+            let mut total_ratio = 0.;
+            while end < aspect_ratios.len() {
+                total_ratio += aspect_ratios[end];
+                end += 1;
+                if total_ratio * self.row_height >= width {
+                    break;
+                }
+            }
+            // the last row isn't stretched so a few images don't get blown up
+            let height = if total_ratio * self.row_height >= width {
+                width / total_ratio
+            } else {
+                self.row_height
+            };
+            rows.push(JustifiedRow {
+                top,
+                bottom: top + height,
+                start,
+                end,
+            });
+            top += height;
+            start = end;
+        }
+        rows
+    }
+}
+
+impl<C: Data, T: GridIter<C>> Widget<T> for Justified<C, T> {
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut T,
+        env: &Env,
+    ) {
+        let mut children = self.children.iter_mut();
+        data.for_each_mut(|child_data, _| {
+            if let Some(child) = children.next() {
+                child.event(ctx, event, child_data, env);
+            }
+        });
+    }
+
+    fn lifecycle(
+        &mut self,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &T,
+        env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            if self.update_child_count(data) {
+                ctx.children_changed();
+            }
+        }
+        let mut children = self.children.iter_mut();
+        data.for_each(|child_data, _| {
+            if let Some(child) = children.next() {
+                child.lifecycle(ctx, event, child_data, env);
+            }
+        });
+    }
+
+    fn update(
+        &mut self,
+        ctx: &mut UpdateCtx,
+        _old_data: &T,
+        data: &T,
+        env: &Env,
+    ) {
+        let mut children = self.children.iter_mut();
+        data.for_each(|child_data, _| {
+            if let Some(child) = children.next() {
+                child.update(ctx, child_data, env);
+            }
+        });
+        if self.update_child_count(data) {
+            ctx.children_changed();
+        }
+    }
+
+    fn layout(
+        &mut self,
+        ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        data: &T,
+        env: &Env,
+    ) -> Size {
+        let width = if bc.max().width.is_finite() {
+            bc.max().width
+        } else {
+            bc.min().width.max(self.row_height)
+        };
+        let mut aspect_ratios = Vec::with_capacity(self.children.len());
+        data.for_each(|child_data, _| {
+            aspect_ratios.push((self.aspect_ratio)(child_data).max(0.1));
+        });
+        let rows = self.layout_rows(&aspect_ratios, width);
+        let (top, bottom) = self.rows.borrow().layout_band();
+
+        let mut child_rects = Vec::with_capacity(aspect_ratios.len());
+        for row in rows.iter() {
+            let height = row.bottom - row.top;
+            let mut x = 0.;
+            for aspect_ratio in &aspect_ratios[row.start..row.end] {
+                let child_width = aspect_ratio * height;
+                child_rects.push(Rect::from_origin_size(
+                    Point::new(x, row.top),
+                    Size::new(child_width, height),
+                ));
+                x += child_width;
+            }
+        }
+        let mut children = self.children.iter_mut();
+        data.for_each(|child_data, i| {
+            if let (Some(child), Some(rect)) =
+                (children.next(), child_rects.get(i))
+            {
+                // children far out of view are only laid out again when
+                // their size changes, that keeps them where they'd be
+                // scrolled to
+                let near_view = rect.y1 >= top && rect.y0 <= bottom;
+                if near_view || child.layout_rect().size() != rect.size() {
+                    child.layout(
+                        ctx,
+                        &BoxConstraints::tight(rect.size()),
+                        child_data,
+                        env,
+                    );
+                }
+                child.set_origin(ctx, child_data, env, rect.origin());
+            }
+        });
+
+        let height = rows.last().map(|row| row.bottom).unwrap_or(0.);
+        let mut shared = self.rows.borrow_mut();
+        shared.rows = rows;
+        shared.laid_out = (top, bottom);
+        bc.constrain(Size::new(width, height))
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        // only the children in the region being painted
+        let region = ctx.region().bounding_box();
+        let mut children = self.children.iter_mut();
+        data.for_each(|child_data, _| {
+            if let Some(child) = children.next() {
+                let rect = child.layout_rect();
+                if rect.y1 >= region.y0 && rect.y0 <= region.y1 {
+                    child.paint(ctx, child_data, env);
+                }
+            }
+        });
+    }
+}
@@ -1,6 +1,14 @@
 mod button;
+mod justified;
+mod overlay;
 mod scroll;
 mod scroll_component;
+mod toast;
+mod zoom;
 
-pub use button::Button;
-pub use scroll::Scroll;
+pub use button::{text_button, Button};
+pub use justified::{Justified, JustifiedRow, JustifiedRows};
+pub use overlay::Overlay;
+pub use scroll::{Scroll, SCROLL_TO_WINDOW_RECT};
+pub use toast::{paint_toast_message, Toast, SHOW_TOAST, TOAST_DURATION};
+pub use zoom::Zoom;
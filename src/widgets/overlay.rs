@@ -0,0 +1,70 @@
+use druid::widget::prelude::*;
+use druid::Data;
+
+// paints on top of its child, the counterpart to a `Painter` background
+pub struct Overlay<T, W> {
+    child: W,
+    paint: Box<dyn Fn(&mut PaintCtx, &T, &Env)>,
+}
+
+impl<T, W: Widget<T>> Overlay<T, W> {
+    pub fn new(
+        child: W,
+        paint: impl Fn(&mut PaintCtx, &T, &Env) + 'static,
+    ) -> Self {
+        Self {
+            child,
+            paint: Box::new(paint),
+        }
+    }
+}
+
+impl<T: Data, W: Widget<T>> Widget<T> for Overlay<T, W> {
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut T,
+        env: &Env,
+    ) {
+        self.child.event(ctx, event, data, env)
+    }
+
+    fn lifecycle(
+        &mut self,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &T,
+        env: &Env,
+    ) {
+        self.child.lifecycle(ctx, event, data, env)
+    }
+
+    fn update(
+        &mut self,
+        ctx: &mut UpdateCtx,
+        old_data: &T,
+        data: &T,
+        env: &Env,
+    ) {
+        if !old_data.same(data) || ctx.env_changed() {
+            ctx.request_paint();
+        }
+        self.child.update(ctx, old_data, data, env)
+    }
+
+    fn layout(
+        &mut self,
+        ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        data: &T,
+        env: &Env,
+    ) -> Size {
+        self.child.layout(ctx, bc, data, env)
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        self.child.paint(ctx, data, env);
+        (self.paint)(ctx, data, env);
+    }
+}
@@ -17,7 +17,12 @@
 use crate::widgets::scroll_component::*;
 use druid::widget::prelude::*;
 use druid::widget::ClipBox;
-use druid::{Data, Rect, Vec2};
+use druid::{Data, Rect, Selector, Vec2};
+
+/// Scrolls the minimal distance to show the given rect, in window
+/// coordinates. Should be targeted at the scroll widget.
+pub const SCROLL_TO_WINDOW_RECT: Selector<Rect> =
+    Selector::new("widgets.scroll.scroll-to-window-rect");
 
 /// A container that scrolls its contents.
 ///
@@ -107,6 +112,18 @@ impl<T: Data, W: Widget<T>> Widget<T> for Scroll<T, W> {
         data: &mut T,
         env: &Env,
     ) {
+        if let Event::Command(cmd) = event {
+            if let Some(rect) = cmd.get(SCROLL_TO_WINDOW_RECT) {
+                let region =
+                    *rect - ctx.window_origin().to_vec2() + self.offset();
+                if self.scroll_to(region) {
+                    ctx.request_paint();
+                }
+                ctx.set_handled();
+                return;
+            }
+        }
+
         let scroll_component = &mut self.scroll_component;
         self.clip.with_port(|port| {
             scroll_component.event(port, ctx, event, env);
@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+use druid::piet::{Text, TextLayout, TextLayoutBuilder};
+use druid::widget::prelude::*;
+use druid::{Color, Data, FontFamily, Rect, Selector, TimerToken};
+
+// shows a message over whatever view is open, meant for work that finishes
+// in the background and can be sent through an `ExtEventSink`
+pub const SHOW_TOAST: Selector<String> = Selector::new("widgets.toast.show");
+
+// how long a toast stays on screen
+pub const TOAST_DURATION: Duration = Duration::from_secs(2);
+// what a finished job says usually takes longer to read
+const JOB_TOAST_DURATION: Duration = Duration::from_secs(4);
+
+// paints the message it was last sent on top of its child until it times
+// out or is clicked
+pub struct Toast<W> {
+    child: W,
+    message: Option<String>,
+    timer: TimerToken,
+    // where the message was last painted
+    rect: Rect,
+}
+
+impl<W> Toast<W> {
+    pub fn new(child: W) -> Self {
+        Self {
+            child,
+            message: None,
+            timer: TimerToken::INVALID,
+            rect: Rect::ZERO,
+        }
+    }
+}
+
+impl<T: Data, W: Widget<T>> Widget<T> for Toast<W> {
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut T,
+        env: &Env,
+    ) {
+        match event {
+            Event::Command(cmd) if cmd.is(SHOW_TOAST) => {
+                self.message = Some(cmd.get_unchecked(SHOW_TOAST).clone());
+                self.timer = ctx.request_timer(JOB_TOAST_DURATION);
+                ctx.request_paint();
+            }
+            Event::Timer(token) if *token == self.timer => {
+                self.message = None;
+                ctx.request_paint();
+            }
+            Event::MouseDown(mouse)
+                if self.message.is_some() && self.rect.contains(mouse.pos) =>
+            {
+                self.message = None;
+                ctx.set_handled();
+                ctx.request_paint();
+            }
+            _ => self.child.event(ctx, event, data, env),
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &T,
+        env: &Env,
+    ) {
+        self.child.lifecycle(ctx, event, data, env)
+    }
+
+    fn update(
+        &mut self,
+        ctx: &mut UpdateCtx,
+        old_data: &T,
+        data: &T,
+        env: &Env,
+    ) {
+        self.child.update(ctx, old_data, data, env)
+    }
+
+    fn layout(
+        &mut self,
+        ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        data: &T,
+        env: &Env,
+    ) -> Size {
+        self.child.layout(ctx, bc, data, env)
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        self.child.paint(ctx, data, env);
+        if let Some(message) = &self.message {
+            self.rect = paint_toast_message(ctx, message);
+        }
+    }
+}
+
+// a short message in a box at the bottom of the view, returns the box
+pub fn paint_toast_message(ctx: &mut PaintCtx, message: &str) -> Rect {
+    if message.is_empty() {
+        return Rect::ZERO;
+    }
+    let layout = ctx
+        .text()
+        .new_text_layout(message.to_string())
+        .font(FontFamily::SYSTEM_UI, 14.)
+        .text_color(Color::WHITE)
+        .build()
+        .unwrap();
+    let padding = 8.;
+    let size = Size::new(
+        layout.size().width + padding * 2.,
+        layout.size().height + padding * 2.,
+    );
+    let origin = (
+        (ctx.size().width - size.width) / 2.,
+        ctx.size().height - size.height - padding * 4.,
+    );
+    let rect = Rect::from_origin_size(origin, size);
+    ctx.fill(rect.to_rounded_rect(4.), &Color::rgba8(0, 0, 0, 0xcc));
+    ctx.draw_text(&layout, (origin.0 + padding, origin.1 + padding));
+    rect
+}
@@ -0,0 +1,111 @@
+use druid::widget::prelude::*;
+use druid::{Affine, Data};
+
+// paints its child through a transform read from the data and the widget's
+// size, so the child can be zoomed and panned without having to know about it
+pub struct Zoom<T, W> {
+    child: W,
+    transform: Box<dyn Fn(&T, Size) -> Affine>,
+}
+
+impl<T, W: Widget<T>> Zoom<T, W> {
+    pub fn new(
+        child: W,
+        transform: impl Fn(&T, Size) -> Affine + 'static,
+    ) -> Self {
+        Self {
+            child,
+            transform: Box::new(transform),
+        }
+    }
+
+    pub fn child(&self) -> &W {
+        &self.child
+    }
+
+    pub fn child_mut(&mut self) -> &mut W {
+        &mut self.child
+    }
+}
+
+impl<T: Data, W: Widget<T>> Widget<T> for Zoom<T, W> {
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut T,
+        env: &Env,
+    ) {
+        // mouse positions are mapped back into the child's untransformed
+        // coordinates
+        let inverse = (self.transform)(data, ctx.size()).inverse();
+        let event = match event {
+            Event::MouseDown(mouse) => {
+                let mut mouse = mouse.clone();
+                mouse.pos = inverse * mouse.pos;
+                Event::MouseDown(mouse)
+            }
+            Event::MouseUp(mouse) => {
+                let mut mouse = mouse.clone();
+                mouse.pos = inverse * mouse.pos;
+                Event::MouseUp(mouse)
+            }
+            Event::MouseMove(mouse) => {
+                let mut mouse = mouse.clone();
+                mouse.pos = inverse * mouse.pos;
+                Event::MouseMove(mouse)
+            }
+            Event::Wheel(mouse) => {
+                let mut mouse = mouse.clone();
+                mouse.pos = inverse * mouse.pos;
+                Event::Wheel(mouse)
+            }
+            _ => event.clone(),
+        };
+        self.child.event(ctx, &event, data, env)
+    }
+
+    fn lifecycle(
+        &mut self,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &T,
+        env: &Env,
+    ) {
+        self.child.lifecycle(ctx, event, data, env)
+    }
+
+    fn update(
+        &mut self,
+        ctx: &mut UpdateCtx,
+        old_data: &T,
+        data: &T,
+        env: &Env,
+    ) {
+        let size = ctx.size();
+        if (self.transform)(old_data, size) != (self.transform)(data, size) {
+            ctx.request_paint();
+        }
+        self.child.update(ctx, old_data, data, env)
+    }
+
+    fn layout(
+        &mut self,
+        ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        data: &T,
+        env: &Env,
+    ) -> Size {
+        self.child.layout(ctx, bc, data, env)
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        let transform = (self.transform)(data, ctx.size());
+        let clip = ctx.size().to_rect();
+        ctx.with_save(|ctx| {
+            ctx.clip(clip);
+            ctx.transform(transform);
+            self.child.paint(ctx, data, env);
+        });
+    }
+}
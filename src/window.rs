@@ -0,0 +1,104 @@
+use std::cell::RefCell;
+
+use druid::{
+    im::vector,
+    widget::{Scope, ScopeTransfer},
+    Data, EventCtx, Widget, WindowDesc,
+};
+
+use crate::{app_data::AppState, main_view::AppView, navigator};
+
+// opens another gallery window over the same library, it has views and a
+// selection of its own so it can show a different folder
+pub fn open_window(ctx: &mut EventCtx) {
+    let window = WindowDesc::new(window_view).title("Gallery");
+    ctx.new_window(window);
+}
+
+fn window_view() -> impl Widget<AppState> {
+    Scope::from_function(
+        |app: AppState| AppState {
+            current_image_idx: 0,
+            views: vector![AppView::MainView],
+            selected_folder: None,
+            focused_folder: None,
+            ..app
+        },
+        WindowTransfer::default(),
+        navigator(),
+    )
+}
+
+// keeps a window's own views and selection apart from the library every
+// window shares
+#[derive(Default)]
+struct WindowTransfer {
+    // the library as it was last read. Only what this window changed since
+    // is written back, so it doesn't undo what another window changed
+    read: RefCell<Option<AppState>>,
+}
+
+impl ScopeTransfer for WindowTransfer {
+    type In = AppState;
+
+    type State = AppState;
+
+    fn read_input(&self, state: &mut Self::State, inner: &Self::In) {
+        // folders can go away from under the window, like when another one
+        // scans the library again
+        let folders = inner.all_images.len();
+        *state = AppState {
+            current_image_idx: state.current_image_idx,
+            views: state.views.clone(),
+            selected_folder: state.selected_folder.filter(|idx| *idx < folders),
+            focused_folder: state.focused_folder.filter(|idx| *idx < folders),
+            ..inner.clone()
+        };
+        *self.read.borrow_mut() = Some(inner.clone());
+    }
+
+    fn write_back_input(&self, state: &Self::State, inner: &mut Self::In) {
+        let read = self.read.borrow().clone().unwrap_or_else(|| inner.clone());
+        // every field is named so a new one has to be sorted into the
+        // window's own or the shared ones
+        let AppState {
+            folder_paths,
+            current_image_idx: _,
+            views: _,
+            all_images,
+            selected_folder: _,
+            thumbnail_cache_progress,
+            cache_usage,
+            settings,
+            root_folders,
+            focused_folder: _,
+            log,
+            scan_generation,
+            rescan_collapsed,
+            indexed_folders,
+            opened_path,
+        } = state;
+        macro_rules! write_changed {
+            ($($field:ident),*) => {
+                $(
+                    if !$field.same(&read.$field) {
+                        inner.$field = $field.clone();
+                    }
+                )*
+            };
+        }
+        write_changed!(
+            folder_paths,
+            all_images,
+            thumbnail_cache_progress,
+            cache_usage,
+            settings,
+            root_folders,
+            log,
+            scan_generation,
+            rescan_collapsed,
+            indexed_folders,
+            opened_path
+        );
+    }
+}